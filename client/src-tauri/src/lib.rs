@@ -67,6 +67,7 @@ pub fn run() {
             relay::relay_status,
             relay::relay_stats,
             relay::relay_reset,
+            relay::relay_rotate_identity,
             tunnel::tunnel_start,
             tunnel::tunnel_stop,
             tunnel::tunnel_status,
@@ -15,8 +15,9 @@ use std::sync::Arc;
 
 use tauri::async_runtime::Mutex;
 use tauri::{AppHandle, Manager, State};
+use thewired_relay::config::AuthPolicy;
 use thewired_relay::db::{sqlite, Db};
-use thewired_relay::server::{run_embedded, EmbeddedRelay};
+use thewired_relay::server::{run_embedded, EmbeddedConfig, EmbeddedRelay};
 
 /// Managed Tauri state holding the running relay (if any).
 #[derive(Default)]
@@ -82,6 +83,10 @@ fn db_file(base: &Path) -> PathBuf {
 /// Keychain id for the relay's signing key.
 const RELAY_IDENTITY_SECRET: &str = "embedded_relay_identity";
 
+/// Keychain id for the key a rotation replaced, kept until the relay has
+/// signed its handover to the new one (see [`relay_rotate_identity`]).
+const RELAY_PREVIOUS_IDENTITY_SECRET: &str = "embedded_relay_previous_identity";
+
 fn is_valid_hex_key(s: &str) -> bool {
     let t = s.trim();
     t.len() == 64 && t.chars().all(|c| c.is_ascii_hexdigit())
@@ -167,6 +172,11 @@ pub async fn relay_start(
 
     let base = base_dir(&app)?;
     let secret_key = load_or_create_identity(&base).map_err(|e| format!("relay identity: {e}"))?;
+    let previous_key = crate::keystore::keystore_get_secret(RELAY_PREVIOUS_IDENTITY_SECRET.to_string())
+        .ok()
+        .flatten()
+        .filter(|k| is_valid_hex_key(k))
+        .map(|k| k.trim().to_string());
 
     let db_path = db_file(&base);
     let db_path_str = db_path.to_string_lossy().to_string();
@@ -176,14 +186,23 @@ pub async fn relay_start(
 
     let relay = run_embedded(
         Db::Sqlite(pool),
-        embedded_relay_port(),
-        "The Wired (self-hosted)".to_string(),
-        Some(secret_key),
-        owner_pubkey,
-        lan.unwrap_or(false),
+        EmbeddedConfig {
+            port: embedded_relay_port(),
+            relay_name: "The Wired (self-hosted)".to_string(),
+            relay_secret_key: Some(secret_key),
+            relay_previous_secret_key: previous_key.clone(),
+            owner_pubkey,
+            bind_lan: lan.unwrap_or(false),
+            auth_policy: AuthPolicy::Open,
+            ..Default::default()
+        },
     )
     .await
     .map_err(|e| format!("start embedded relay: {e}"))?;
+    // The handover is signed and recorded in the db; the old key has done its job.
+    if previous_key.is_some() {
+        let _ = crate::keystore::keystore_delete_secret(RELAY_PREVIOUS_IDENTITY_SECRET.to_string());
+    }
 
     let status = RelayStatus::from_relay(&relay);
     *guard = Some(relay);
@@ -280,6 +299,44 @@ pub async fn relay_stats(
     })
 }
 
+/// Rotate the relay's signing key, keeping its data: stop the relay (if
+/// running), keep the current key as the previous one and generate a new one.
+/// On the next start the relay signs a handover from the old key to the new
+/// one and re-signs its groups' state, so members can follow the change —
+/// unlike `relay_reset` with `wipe_identity`, which leaves no link. Returns
+/// the stopped status; the caller restarts the relay.
+#[tauri::command]
+pub async fn relay_rotate_identity(
+    app: AppHandle,
+    state: State<'_, EmbeddedRelayState>,
+) -> Result<RelayStatus, String> {
+    if let Some(relay) = state.0.lock().await.take() {
+        relay.stop().await;
+    }
+
+    let base = base_dir(&app)?;
+    let current = load_or_create_identity(&base).map_err(|e| format!("relay identity: {e}"))?;
+    // A rotation that hasn't been applied yet (the relay never restarted)
+    // still owes the handover from the older key, so keep that one.
+    let pending = crate::keystore::keystore_get_secret(RELAY_PREVIOUS_IDENTITY_SECRET.to_string())
+        .ok()
+        .flatten()
+        .is_some_and(|k| is_valid_hex_key(&k));
+    if !pending {
+        crate::keystore::keystore_set_secret(RELAY_PREVIOUS_IDENTITY_SECRET.to_string(), current)
+            .map_err(|e| format!("store previous relay identity: {e}"))?;
+    }
+
+    use rand::RngCore;
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    crate::keystore::keystore_set_secret(RELAY_IDENTITY_SECRET.to_string(), hex::encode(bytes))
+        .map_err(|e| format!("store relay identity: {e}"))?;
+
+    log::info!("Embedded relay identity rotated; handover is signed on next start");
+    Ok(RelayStatus::stopped())
+}
+
 /// Tear down the host: stop the relay (if running) and delete its stored data.
 /// With `wipe_identity = true` the relay's signing key is also removed — note
 /// this gives the relay a NEW pubkey next start, so any groups it hosts get a
@@ -310,6 +367,7 @@ pub async fn relay_reset(
 
     if wipe_identity {
         let _ = crate::keystore::keystore_delete_secret(RELAY_IDENTITY_SECRET.to_string());
+        let _ = crate::keystore::keystore_delete_secret(RELAY_PREVIOUS_IDENTITY_SECRET.to_string());
         // Also remove any legacy plaintext file.
         let key_path = base.join("identity.key");
         if key_path.exists() {
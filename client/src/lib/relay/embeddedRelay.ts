@@ -65,6 +65,14 @@ export const resetEmbeddedRelay = (
 ): Promise<EmbeddedRelayStatus> =>
   invoke<EmbeddedRelayStatus>("relay_reset", { wipeIdentity });
 
+/**
+ * Give the relay a new signing key but keep its data. The relay must be
+ * restarted; on start it signs a handover from the old key (kind 39011) and
+ * re-signs its groups, and NIP-11 lists both keys for a grace period.
+ */
+export const rotateEmbeddedRelayIdentity = (): Promise<EmbeddedRelayStatus> =>
+  invoke<EmbeddedRelayStatus>("relay_rotate_identity");
+
 /** How the relay is exposed publicly:
  *  - `"named"` — the default `<id>.relay.thewired.app` (stable; needs the
  *    platform's Cloudflare setup),
@@ -0,0 +1,27 @@
+-- Push membership changes to the relay (replaces its 30 s membership-cache TTL).
+-- Every insert/delete on app.space_members NOTIFYs `relay_membership` with
+-- {"group_id","pubkey"}; each relay process LISTENs and re-resolves the open
+-- connections of that pubkey immediately, so a kicked member stops receiving
+-- the space's broadcasts (and gets CLOSED on its subscriptions) right away.
+-- Channel name must match `membership_bus::PG_CHANNEL` in services/relay.
+CREATE OR REPLACE FUNCTION app.notify_space_member_change() RETURNS trigger AS $$
+DECLARE
+    r RECORD;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        r := OLD;
+    ELSE
+        r := NEW;
+    END IF;
+    PERFORM pg_notify(
+        'relay_membership',
+        json_build_object('group_id', r.space_id, 'pubkey', r.pubkey)::text
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS space_members_notify ON app.space_members;
+CREATE TRIGGER space_members_notify
+    AFTER INSERT OR DELETE ON app.space_members
+    FOR EACH ROW EXECUTE FUNCTION app.notify_space_member_change();
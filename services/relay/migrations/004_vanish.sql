@@ -0,0 +1,9 @@
+-- NIP-62 request-to-vanish tombstones. One row per pubkey that asked this relay
+-- to forget them; `store_event` refuses their events with
+-- `created_at <= vanished_until` (except further vanish requests), so copies
+-- re-broadcast from other relays can't repopulate what was purged.
+CREATE TABLE IF NOT EXISTS relay.vanished_pubkeys (
+    pubkey TEXT PRIMARY KEY,
+    vanished_until BIGINT NOT NULL,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,23 @@
+-- NIP-09 / NIP-29 deletion tombstones. Deleting a row alone isn't enough: a
+-- client re-syncing from another relay would re-publish the event and
+-- `store_event` would happily accept it. `store_event` refuses inserts that
+-- match either table.
+
+-- Deleted event ids (kind:5 `e` tags, kind:9005 moderator deletions). The
+-- author is recorded so a kind:5 naming someone else's id blocks nothing.
+CREATE TABLE IF NOT EXISTS relay.deleted_events (
+    event_id TEXT PRIMARY KEY,
+    pubkey TEXT NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Deleted addressable coordinates (kind:5 `a` tags): versions created at or
+-- before `deleted_until` stay deleted; newer ones supersede the deletion.
+CREATE TABLE IF NOT EXISTS relay.deleted_addresses (
+    kind INTEGER NOT NULL,
+    pubkey TEXT NOT NULL,
+    d_tag TEXT NOT NULL,
+    deleted_until BIGINT NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (kind, pubkey, d_tag)
+);
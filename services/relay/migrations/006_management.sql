@@ -0,0 +1,31 @@
+-- NIP-86 relay management (operator moderation over HTTP JSON-RPC).
+
+-- Pubkeys barred from publishing. `allowpubkey` lifts a ban.
+CREATE TABLE IF NOT EXISTS relay.banned_pubkeys (
+    pubkey TEXT PRIMARY KEY,
+    reason TEXT,
+    banned_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Publishing allowlist. Empty means "everyone not banned"; once any pubkey is
+-- allowed, only allowed pubkeys may publish.
+CREATE TABLE IF NOT EXISTS relay.allowed_pubkeys (
+    pubkey TEXT PRIMARY KEY,
+    reason TEXT,
+    allowed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Banned event ids: the row is deleted and `store_event` refuses it from any
+-- author until an `allowevent` lifts the ban.
+CREATE TABLE IF NOT EXISTS relay.banned_events (
+    event_id TEXT PRIMARY KEY,
+    reason TEXT,
+    banned_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Operator overrides of NIP-11 fields (`changerelayname` etc.), keyed by field.
+CREATE TABLE IF NOT EXISTS relay.settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
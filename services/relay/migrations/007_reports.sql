@@ -0,0 +1,20 @@
+-- NIP-56 moderation queue. A kind:1984 report naming an h-tagged event is
+-- filed here per (report, target) under the target's group; the report event
+-- itself is then only readable by its author and that group's admins.
+CREATE TABLE IF NOT EXISTS relay.reports (
+    report_id TEXT NOT NULL,
+    target_event_id TEXT NOT NULL,
+    group_id TEXT NOT NULL,
+    reporter TEXT NOT NULL,
+    report_type TEXT,
+    -- 'open' until resolved; resolution is 'deleted' | 'banned' | 'dismissed'.
+    status TEXT NOT NULL DEFAULT 'open',
+    resolution TEXT,
+    resolved_by TEXT,
+    created_at BIGINT NOT NULL,
+    resolved_at TIMESTAMPTZ,
+    PRIMARY KEY (report_id, target_event_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_reports_group_status ON relay.reports (group_id, status);
+CREATE INDEX IF NOT EXISTS idx_reports_target ON relay.reports (target_event_id);
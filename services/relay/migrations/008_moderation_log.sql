@@ -0,0 +1,16 @@
+-- Per-group moderation audit log: one row per successful NIP-29 management op
+-- (9000/9001/9002/9005/9007/9008). Deliberately separate from relay.events and
+-- without a foreign key to relay.groups, so neither deleting the affected
+-- events nor deleting the group erases the record.
+CREATE TABLE IF NOT EXISTS relay.moderation_log (
+    id TEXT PRIMARY KEY,              -- the relay-signed kind:9080 entry's id
+    group_id TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    action INTEGER NOT NULL,          -- the management event kind
+    targets TEXT[] NOT NULL DEFAULT '{}',
+    reason TEXT,
+    created_at BIGINT NOT NULL,
+    event JSONB NOT NULL              -- the signed entry, served verbatim
+);
+
+CREATE INDEX IF NOT EXISTS idx_moderation_log_group ON relay.moderation_log (group_id, created_at DESC);
@@ -0,0 +1,4 @@
+-- NIP-13 proof-of-work minimum per group, in leading zero bits (0 = none).
+-- Set by a `min_pow` tag on kind:9002 and advertised in the 39000 event;
+-- group admins are exempt.
+ALTER TABLE relay.groups ADD COLUMN IF NOT EXISTS min_pow INTEGER NOT NULL DEFAULT 0;
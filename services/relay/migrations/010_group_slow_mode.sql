@@ -0,0 +1,4 @@
+-- Slow mode: minimum seconds between one member's kind:9 messages in a group
+-- (0 = off). Set by a `slow_mode` tag on kind:9002 and advertised in the 39000
+-- event; group admins are exempt.
+ALTER TABLE relay.groups ADD COLUMN IF NOT EXISTS slow_mode_secs INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,12 @@
+-- Timed NIP-29 mutes (kind:9010 / 9011). A muted member keeps membership and
+-- read access, but their h-tagged publishes are refused until `muted_until`
+-- (unix seconds). Expired rows are simply ignored.
+CREATE TABLE IF NOT EXISTS relay.group_mutes (
+    group_id TEXT NOT NULL REFERENCES relay.groups(group_id) ON DELETE CASCADE,
+    pubkey TEXT NOT NULL,
+    muted_until BIGINT NOT NULL,
+    muted_by TEXT NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (group_id, pubkey)
+);
@@ -0,0 +1,8 @@
+-- Per-group write/read policies (see nostr::nip29::policy), set by tags on
+-- kind:9007/9002 and advertised in the 39000 event:
+--   announcement  — only admins post content,
+--   allowed_kinds — content kinds the group accepts (empty = any),
+--   restricted    — only members may read the group's metadata.
+ALTER TABLE relay.groups ADD COLUMN IF NOT EXISTS announcement BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE relay.groups ADD COLUMN IF NOT EXISTS allowed_kinds INTEGER[] NOT NULL DEFAULT '{}';
+ALTER TABLE relay.groups ADD COLUMN IF NOT EXISTS restricted BOOLEAN NOT NULL DEFAULT FALSE;
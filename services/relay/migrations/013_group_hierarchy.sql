@@ -0,0 +1,6 @@
+-- Sub-groups (channels): a group created with a `parent` tag on kind:9007 is a
+-- child of that group and, unless `inherits` is off, shares its members and
+-- admins. Set once at creation; deleting a group deletes its children.
+ALTER TABLE relay.groups ADD COLUMN IF NOT EXISTS parent_id TEXT REFERENCES relay.groups(group_id) ON DELETE CASCADE;
+ALTER TABLE relay.groups ADD COLUMN IF NOT EXISTS inherits BOOLEAN NOT NULL DEFAULT TRUE;
+CREATE INDEX IF NOT EXISTS idx_groups_parent ON relay.groups (parent_id) WHERE parent_id IS NOT NULL;
@@ -0,0 +1,58 @@
+-- Per-event aggregates (see `nostr::aggregates`): what each stored reaction,
+-- reply or zap receipt contributes to the event it points at, and the
+-- counters derived from it. Contributions cascade away with their event, and
+-- the trigger keeps `event_aggregates` in step on insert and delete, so every
+-- deletion path decrements the counts. Contributions from h-tagged or
+-- `visibility`-tagged events are `gated`: left out of the counters and counted
+-- per request for the readers who can see them. Counting starts with this
+-- migration.
+CREATE TABLE IF NOT EXISTS relay.event_contributions (
+    event_id  TEXT PRIMARY KEY REFERENCES relay.events(id) ON DELETE CASCADE,
+    target_id TEXT NOT NULL,
+    metric    TEXT NOT NULL,
+    key       TEXT NOT NULL DEFAULT '',
+    amount    BIGINT NOT NULL DEFAULT 0,
+    gated     BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS idx_event_contributions_gated
+    ON relay.event_contributions (target_id) WHERE gated;
+
+CREATE TABLE IF NOT EXISTS relay.event_aggregates (
+    target_id TEXT NOT NULL,
+    metric    TEXT NOT NULL,
+    key       TEXT NOT NULL DEFAULT '',
+    count     BIGINT NOT NULL DEFAULT 0,
+    total     BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (target_id, metric, key)
+);
+
+CREATE OR REPLACE FUNCTION relay.apply_event_contribution() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        IF NEW.gated THEN
+            RETURN NULL;
+        END IF;
+        INSERT INTO relay.event_aggregates (target_id, metric, key, count, total)
+        VALUES (NEW.target_id, NEW.metric, NEW.key, 1, NEW.amount)
+        ON CONFLICT (target_id, metric, key) DO UPDATE
+        SET count = relay.event_aggregates.count + 1,
+            total = relay.event_aggregates.total + EXCLUDED.total;
+    ELSE
+        IF OLD.gated THEN
+            RETURN NULL;
+        END IF;
+        UPDATE relay.event_aggregates
+        SET count = count - 1, total = total - OLD.amount
+        WHERE target_id = OLD.target_id AND metric = OLD.metric AND key = OLD.key;
+        DELETE FROM relay.event_aggregates
+        WHERE target_id = OLD.target_id AND metric = OLD.metric AND key = OLD.key AND count <= 0;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS event_contributions_apply ON relay.event_contributions;
+CREATE TRIGGER event_contributions_apply
+    AFTER INSERT OR DELETE ON relay.event_contributions
+    FOR EACH ROW EXECUTE FUNCTION relay.apply_event_contribution();
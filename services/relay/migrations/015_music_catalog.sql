@@ -0,0 +1,62 @@
+-- Music catalog (see `music::catalog`): each track's and album's metadata,
+-- and each album's and playlist's `a` refs in order, extracted at store time.
+-- Rows cascade away with their event, so replaced or deleted releases drop
+-- out of the catalog. Music events stored before this migration are indexed
+-- here; every statement is idempotent, as migrations re-run at startup.
+CREATE TABLE IF NOT EXISTS relay.music_catalog (
+    event_id    TEXT PRIMARY KEY REFERENCES relay.events(id) ON DELETE CASCADE,
+    title       TEXT,
+    artist      TEXT,
+    genre       TEXT,
+    album       TEXT,
+    duration    DOUBLE PRECISION,
+    released_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_music_catalog_artist ON relay.music_catalog (lower(artist));
+CREATE INDEX IF NOT EXISTS idx_music_catalog_genre ON relay.music_catalog (genre);
+CREATE INDEX IF NOT EXISTS idx_music_catalog_album ON relay.music_catalog (album);
+CREATE INDEX IF NOT EXISTS idx_music_catalog_released ON relay.music_catalog (released_at DESC);
+
+CREATE TABLE IF NOT EXISTS relay.music_refs (
+    event_id   TEXT NOT NULL REFERENCES relay.events(id) ON DELETE CASCADE,
+    position   INTEGER NOT NULL,
+    ref_kind   INTEGER NOT NULL,
+    ref_pubkey TEXT NOT NULL,
+    ref_d      TEXT NOT NULL,
+    PRIMARY KEY (event_id, position)
+);
+CREATE INDEX IF NOT EXISTS idx_music_refs_target ON relay.music_refs (ref_pubkey, ref_kind, ref_d);
+
+INSERT INTO relay.music_catalog (event_id, title, artist, genre, album, duration, released_at)
+SELECT e.id,
+       NULLIF(btrim((SELECT t->>1 FROM jsonb_array_elements(e.tags) t WHERE t->>0 = 'title' LIMIT 1)), ''),
+       NULLIF(btrim((SELECT t->>1 FROM jsonb_array_elements(e.tags) t WHERE t->>0 = 'artist' LIMIT 1)), ''),
+       lower(NULLIF(btrim((SELECT t->>1 FROM jsonb_array_elements(e.tags) t WHERE t->>0 = 'genre' LIMIT 1)), '')),
+       CASE WHEN e.kind = 31683 THEN
+           (SELECT t->>1 FROM jsonb_array_elements(e.tags) t
+            WHERE t->>0 = 'a' AND t->>1 ~ '^33123:[0-9a-f]{64}:' LIMIT 1)
+       END,
+       (SELECT btrim(t->>1)::double precision FROM jsonb_array_elements(e.tags) t
+        WHERE t->>0 = 'duration' AND t->>1 ~ '^\s*[0-9]+(\.[0-9]+)?\s*$' LIMIT 1),
+       COALESCE(
+           (SELECT btrim(t->>1)::bigint FROM jsonb_array_elements(e.tags) t
+            WHERE t->>0 = 'published_at' AND t->>1 ~ '^\s*[0-9]{1,18}\s*$' LIMIT 1),
+           e.created_at)
+FROM relay.events e
+WHERE e.kind IN (31683, 33123)
+ON CONFLICT (event_id) DO NOTHING;
+
+INSERT INTO relay.music_refs (event_id, position, ref_kind, ref_pubkey, ref_d)
+SELECT r.id,
+       r.position,
+       split_part(r.v, ':', 1)::int,
+       split_part(r.v, ':', 2),
+       substr(r.v, length(split_part(r.v, ':', 1)) + 67)
+FROM (
+    -- Numbered among all `a` tags, so an unparseable one keeps its slot.
+    SELECT e.id, (row_number() OVER (PARTITION BY e.id ORDER BY t.ord) - 1)::int AS position, t.tag->>1 AS v
+    FROM relay.events e, jsonb_array_elements(e.tags) WITH ORDINALITY AS t(tag, ord)
+    WHERE e.kind IN (33123, 30119) AND t.tag->>0 = 'a'
+) r
+WHERE r.v ~ '^[0-9]{1,9}:[0-9a-f]{64}:'
+ON CONFLICT (event_id, position) DO NOTHING;
@@ -1,86 +1,160 @@
 use axum::extract::ws::{Message, WebSocket};
 use futures::{SinkExt, StreamExt};
 use std::collections::HashSet;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
 use tokio::sync::Mutex;
 
+use crate::membership_bus::MembershipChange;
 use crate::nostr::event::Event;
+use crate::protocol::auth_hold::{self, HeldReqs};
 use crate::protocol::handler;
 use crate::protocol::nip42;
+use crate::rate_limit;
 use crate::server::AppState;
 
-/// How long the per-connection membership cache is trusted before we re-query
-/// `app.space_members`. The publish-side gate (handler.rs) enforces membership
-/// authoritatively per-EVENT, so this cache only governs *receiving*. A 30 s
-/// staleness window means a kicked user keeps reading their old channels for
-/// at most 30 s before they're cut off, even if they hold an open WebSocket.
-/// Refresh is *lazy* — only triggered when an h-tagged event is about to be
-/// forwarded — so idle connections do not poll the DB.
-const MEMBERSHIP_TTL: Duration = Duration::from_secs(30);
-
-/// If `last_refresh` is older than [`MEMBERSHIP_TTL`], re-query the membership
-/// set from `app.space_members`. Failures are logged and leave the cache
-/// untouched (and the timestamp un-bumped, so we'll retry on the next call).
-/// Returns `true` if a refresh happened.
-async fn maybe_refresh_memberships(
+/// CLOSED frame for a subscription whose group the client can no longer read.
+fn revoked_frame(sub_id: &str) -> String {
+    format!(r#"["CLOSED","{sub_id}","restricted: no longer a member of this group"]"#)
+}
+
+/// Apply a membership change published on the bus (`membership_bus`) to this
+/// connection's cached group set. The change only says "membership of X in G
+/// may have changed", so we re-resolve G against the DB (UNION of both
+/// membership worlds on Postgres) for every pubkey authenticated on this
+/// connection — the cache is their union, so G stays readable while ANY of
+/// them still belongs to it. If access was lost, every subscription scoped to G
+/// via `#h` is dropped and the returned CLOSED frames must be sent. Lookup
+/// failures are logged and leave the cache untouched.
+async fn apply_membership_change(
     state: &Arc<AppState>,
-    authed_pubkey: &Option<String>,
+    change: &MembershipChange,
+    authed_pubkeys: &[String],
     space_memberships: &mut HashSet<String>,
-    last_refresh: &mut Instant,
-) -> bool {
-    let pk = match authed_pubkey {
-        Some(pk) => pk,
-        None => return false,
-    };
-    if last_refresh.elapsed() < MEMBERSHIP_TTL {
-        return false;
+    subscriptions: &Arc<Mutex<crate::protocol::subscription::SubscriptionManager>>,
+) -> Vec<String> {
+    if !change.concerns(authed_pubkeys, space_memberships) {
+        return Vec::new();
     }
-    match state.pool.members_of(pk).await {
-        Ok(set) => {
-            *space_memberships = set;
-            *last_refresh = Instant::now();
-            true
-        }
-        Err(e) => {
-            tracing::warn!(
-                pubkey = &pk[..12.min(pk.len())],
-                error = %e,
-                "Membership refresh failed; keeping stale cache"
-            );
-            false
+    let mut still_member = false;
+    for pk in authed_pubkeys {
+        match state.pool.is_member(&change.group_id, pk).await {
+            Ok(true) => {
+                still_member = true;
+                break;
+            }
+            Ok(false) => {}
+            Err(e) => {
+                tracing::warn!(
+                    pubkey = handler::log_prefix(pk),
+                    error = %e,
+                    "Membership re-check failed; keeping cached set"
+                );
+                return Vec::new();
+            }
         }
     }
+    if still_member {
+        space_memberships.insert(change.group_id.clone());
+        return Vec::new();
+    }
+    if !space_memberships.remove(&change.group_id) {
+        return Vec::new();
+    }
+    let closed = subscriptions
+        .lock()
+        .await
+        .remove_targeting_group(&change.group_id);
+    tracing::info!(
+        group_id = %change.group_id,
+        closed = closed.len(),
+        "Membership revoked; closing group subscriptions"
+    );
+    closed.iter().map(|sub_id| revoked_frame(sub_id)).collect()
+}
+
+/// Re-resolve the full membership union of every authenticated pubkey (used
+/// when the bus receiver lagged and individual changes were lost). `None` if
+/// any lookup failed — the caller keeps its cached set.
+async fn reload_memberships(state: &Arc<AppState>, authed_pubkeys: &[String]) -> Option<HashSet<String>> {
+    let mut union = HashSet::new();
+    for pk in authed_pubkeys {
+        union.extend(state.pool.members_of(pk).await.ok()?);
+    }
+    Some(union)
+}
+
+/// Replay REQs held until AUTH (`auth_hold`) through the normal handler — the
+/// historical query and subscription registration now see the connection's
+/// authenticated pubkeys (or, after the window expired, none).
+async fn replay_held_reqs(
+    held: &mut HeldReqs,
+    state: &Arc<AppState>,
+    subscriptions: &Arc<Mutex<crate::protocol::subscription::SubscriptionManager>>,
+    authed_pubkeys: &mut Vec<String>,
+    space_memberships: &mut HashSet<String>,
+    auth_challenge: &str,
+) -> Vec<String> {
+    let mut responses = Vec::new();
+    for raw in held.drain() {
+        responses.extend(
+            handler::handle_message(
+                &raw,
+                state,
+                subscriptions,
+                authed_pubkeys,
+                space_memberships,
+                auth_challenge,
+                &state.broadcast_tx,
+            )
+            .await,
+        );
+    }
+    responses
 }
 
 /// Maximum incoming WebSocket message size (128 KiB)
 const MAX_MESSAGE_SIZE: usize = 128 * 1024;
 
-/// Per-connection message-rate cap for restricted (embedded) relays, which have
-/// no rate-limiting gateway in front of them. Generous enough never to bother a
-/// real client (30 msg/s sustained) but stops a flood from a public tunnel.
+/// Per-connection message-rate cap for restricted (embedded) relays, counting
+/// every frame (CLOSE and garbage included) on top of the shared per-action
+/// buckets in `rate_limit`. Generous enough never to bother a real client
+/// (30 msg/s sustained) but stops a flood from a public tunnel.
 const RATE_WINDOW: Duration = Duration::from_secs(10);
 const RATE_MAX_MSGS: u32 = 300;
 
 /// Visibility check for broadcast events (no per-broadcast DB query).
 /// Order:
+///   0. Gift wraps (NIP-59 kinds 1059/1060) → only an authed `p` target, never
+///      the author (a throwaway key); none of the rules below apply.
 ///   1. Public events (no visibility, no h-tag) → visible to everyone.
 ///   2. Unauthenticated clients → never see protected events.
 ///   3. Author always sees own events.
 ///   4. Explicit p-tagged collaborators always see the event.
-///   5. h-tagged (space-scoped) events: visible if the authed pubkey is a
+///   5. h-tagged (space-scoped) events: visible if an authed pubkey is a
 ///      member of that space, per the cached set populated on AUTH from
-///      `app.space_members`. Without this, members of a space never receive
-///      live broadcasts of kind:9 from other members — only history via
-///      REQ — so chat appears frozen until you switch and re-enter.
+///      `app.space_members` and kept current by the membership bus. Without
+///      this, members of a space never receive live broadcasts of kind:9 from
+///      other members — only history via REQ — so chat appears frozen until
+///      you switch and re-enter.
+///
+/// A connection may AUTH as several pubkeys (multi-account clients); steps 3–5
+/// pass if they hold for ANY of them, and `space_memberships` is their union.
 fn is_event_visible_to(
     event: &Event,
-    authed_pubkey: &Option<String>,
+    authed_pubkeys: &[String],
     space_memberships: &HashSet<String>,
 ) -> bool {
+    let p_tagged = event.tags.iter().any(|t| {
+        t.first().is_some_and(|k| k == "p") && t.get(1).is_some_and(|v| authed_pubkeys.contains(v))
+    });
+    if crate::nostr::nip59::is_recipient_only(event.kind) {
+        return p_tagged;
+    }
+
     let visibility = event.get_tag_value("visibility");
     let h_tag = event.get_tag_value("h");
 
@@ -90,20 +164,16 @@ fn is_event_visible_to(
     }
 
     // Protected event — must be authenticated
-    let pk = match authed_pubkey {
-        Some(pk) => pk,
-        None => return false,
-    };
+    if authed_pubkeys.is_empty() {
+        return false;
+    }
 
     // Author always sees own events
-    if event.pubkey == *pk {
+    if authed_pubkeys.contains(&event.pubkey) {
         return true;
     }
 
     // p-tag: collaborator access
-    let p_tagged = event.tags.iter().any(|t| {
-        t.first().is_some_and(|k| k == "p") && t.get(1).is_some_and(|v| v == pk)
-    });
     if p_tagged {
         return true;
     }
@@ -118,12 +188,16 @@ fn is_event_visible_to(
     false
 }
 
-/// Per-client WebSocket connection handler
+/// Per-client WebSocket connection handler. `client_ip` is what the shared
+/// rate limiter charges (the proxied client when `X-Forwarded-For` is trusted,
+/// else the socket peer `addr`).
 pub async fn handle_connection(
     socket: WebSocket,
     state: Arc<AppState>,
     mut broadcast_rx: broadcast::Receiver<Event>,
+    mut membership_rx: broadcast::Receiver<MembershipChange>,
     addr: SocketAddr,
+    client_ip: IpAddr,
 ) {
     let conn_count = state.active_connections.fetch_add(1, Ordering::Relaxed) + 1;
     let connected_at = std::time::Instant::now();
@@ -140,11 +214,8 @@ pub async fn handle_connection(
     let subscriptions = Arc::new(Mutex::new(
         crate::protocol::subscription::SubscriptionManager::new(),
     ));
-    let mut authed_pubkey: Option<String> = None;
+    let mut authed_pubkeys: Vec<String> = Vec::new();
     let mut space_memberships: HashSet<String> = HashSet::new();
-    // Last time `space_memberships` was refreshed from the DB. AUTH populates
-    // the cache and stamps this; lazy refresh in the broadcast path bumps it.
-    let mut memberships_refreshed_at: Instant = Instant::now();
     // Per-connection rate window (only enforced when `state.hosted_only`).
     let mut rate_window_start: Instant = Instant::now();
     let mut msgs_in_window: u32 = 0;
@@ -153,6 +224,9 @@ pub async fn handle_connection(
     // Send NIP-42 AUTH challenge on connect
     let auth_msg = format!(r#"["AUTH","{}"]"#, auth_challenge);
     let _ = sender.send(Message::Text(auth_msg.into())).await;
+    // Private REQs that arrive before the client answers are held, not
+    // answered as anonymous (NIP29_CHAT Phase 3).
+    let mut held_reqs = HeldReqs::new(state.config.auth_req_hold);
 
     loop {
         tokio::select! {
@@ -187,24 +261,55 @@ pub async fn handle_connection(
                                 continue;
                             }
                         }
+                        // Shared per-IP / per-pubkey budgets, charged before a
+                        // REQ can be held so held REQs pay on arrival.
+                        if let Some(charge) = rate_limit::charge_for(&text, &authed_pubkeys) {
+                            if !state.rate_limiter.allow(&charge, client_ip) {
+                                tracing::debug!(remote = %addr, ip = %client_ip, action = ?charge.action, "Rate limited");
+                                let _ = sender.send(Message::Text(charge.refusal.into())).await;
+                                continue;
+                            }
+                        }
                         events_received += 1;
-                        let was_authed = authed_pubkey.is_some();
-                        let responses = handler::handle_message(
+                        if authed_pubkeys.is_empty() && held_reqs.is_open() {
+                            if let Some(sub_id) = auth_hold::holdable_req(
+                                &text,
+                                state.config.auth_policy.is_required(),
+                            ) {
+                                if held_reqs.hold(sub_id, text.to_string()) {
+                                    continue;
+                                }
+                            }
+                        }
+                        if !held_reqs.is_empty() {
+                            if let Some(sub_id) = auth_hold::close_sub_id(&text) {
+                                held_reqs.release(&sub_id);
+                            }
+                        }
+                        let was_anonymous = authed_pubkeys.is_empty();
+                        let mut responses = handler::handle_message(
                             &text,
                             &state,
                             &subscriptions,
-                            &mut authed_pubkey,
+                            &mut authed_pubkeys,
                             &mut space_memberships,
                             &auth_challenge,
                             &state.broadcast_tx,
                         )
                         .await;
-                        // AUTH success transitions None → Some(pubkey) AND
-                        // populates `space_memberships` from the DB. Bump the
-                        // refresh timestamp so the lazy refresh logic doesn't
-                        // immediately re-query the row we just fetched.
-                        if !was_authed && authed_pubkey.is_some() {
-                            memberships_refreshed_at = Instant::now();
+                        // First successful AUTH: serve the REQs it raced.
+                        if was_anonymous && !authed_pubkeys.is_empty() && !held_reqs.is_empty() {
+                            responses.extend(
+                                replay_held_reqs(
+                                    &mut held_reqs,
+                                    &state,
+                                    &subscriptions,
+                                    &mut authed_pubkeys,
+                                    &mut space_memberships,
+                                    &auth_challenge,
+                                )
+                                .await,
+                            );
                         }
 
                         for response in responses {
@@ -222,21 +327,8 @@ pub async fn handle_connection(
             broadcast_result = broadcast_rx.recv() => {
                 match broadcast_result {
                     Ok(event) => {
-                        // For h-tagged (space-scoped) events, lazily refresh the
-                        // membership cache if it's older than MEMBERSHIP_TTL —
-                        // otherwise a kicked user holding this socket keeps
-                        // receiving the channel until they reconnect.
-                        if event.get_tag_value("h").is_some() {
-                            maybe_refresh_memberships(
-                                &state,
-                                &authed_pubkey,
-                                &mut space_memberships,
-                                &mut memberships_refreshed_at,
-                            ).await;
-                        }
-
                         // Visibility check: don't send protected events to unauthorized clients
-                        if !is_event_visible_to(&event, &authed_pubkey, &space_memberships) {
+                        if !is_event_visible_to(&event, &authed_pubkeys, &space_memberships) {
                             continue;
                         }
 
@@ -261,6 +353,66 @@ pub async fn handle_connection(
                     }
                 }
             }
+
+            // Hold window over without AUTH: answer the held REQs as anonymous.
+            _ = tokio::time::sleep_until(held_reqs.deadline().into()), if !held_reqs.is_empty() => {
+                let responses = replay_held_reqs(
+                    &mut held_reqs,
+                    &state,
+                    &subscriptions,
+                    &mut authed_pubkeys,
+                    &mut space_memberships,
+                    &auth_challenge,
+                )
+                .await;
+                for response in responses {
+                    if sender.send(Message::Text(response.into())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+
+            // Membership changes (NIP-29 ops on any connection, or backend
+            // `app.space_members` writes via NOTIFY) — update the cache now and
+            // CLOSE subscriptions to groups this pubkey can no longer read.
+            membership_result = membership_rx.recv() => {
+                match membership_result {
+                    Ok(change) => {
+                        let closed = apply_membership_change(
+                            &state,
+                            &change,
+                            &authed_pubkeys,
+                            &mut space_memberships,
+                            &subscriptions,
+                        )
+                        .await;
+                        for frame in closed {
+                            if sender.send(Message::Text(frame.into())).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        // Missed changes: fall back to a full re-resolve.
+                        tracing::warn!(remote = %addr, skipped = n, "Membership receiver lagged; reloading");
+                        if !authed_pubkeys.is_empty() {
+                            if let Some(set) = reload_memberships(&state, &authed_pubkeys).await {
+                                let lost: Vec<String> =
+                                    space_memberships.difference(&set).cloned().collect();
+                                space_memberships = set;
+                                let mut subs = subscriptions.lock().await;
+                                for group_id in lost {
+                                    for sub_id in subs.remove_targeting_group(&group_id) {
+                                        let frame = revoked_frame(&sub_id);
+                                        let _ = sender.send(Message::Text(frame.into())).await;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
         }
     }
 
@@ -303,7 +455,7 @@ mod tests {
     #[test]
     fn public_events_visible_to_anonymous() {
         let evt = event_with(1, "alice", vec![]);
-        assert!(is_event_visible_to(&evt, &None, &empty_set()));
+        assert!(is_event_visible_to(&evt, &[], &empty_set()));
     }
 
     /// Anonymous clients never see protected events (h-tagged or visibility-tagged).
@@ -315,8 +467,8 @@ mod tests {
             "alice",
             vec![vec!["visibility".into(), "private".into()]],
         );
-        assert!(!is_event_visible_to(&h_tagged, &None, &empty_set()));
-        assert!(!is_event_visible_to(&visibility_tagged, &None, &empty_set()));
+        assert!(!is_event_visible_to(&h_tagged, &[], &empty_set()));
+        assert!(!is_event_visible_to(&visibility_tagged, &[], &empty_set()));
     }
 
     /// Authors always see their own protected events even if not space-members.
@@ -325,7 +477,7 @@ mod tests {
         let evt = event_with(9, "alice", vec![vec!["h".into(), "space_x".into()]]);
         assert!(is_event_visible_to(
             &evt,
-            &Some("alice".into()),
+            &["alice".into()],
             &empty_set()
         ));
     }
@@ -341,7 +493,7 @@ mod tests {
                 vec!["p".into(), "bob".into()],
             ],
         );
-        assert!(is_event_visible_to(&evt, &Some("bob".into()), &empty_set()));
+        assert!(is_event_visible_to(&evt, &["bob".into()], &empty_set()));
     }
 
     /// THE PHASE 2 FIX: h-tagged events reach members of the space via broadcast.
@@ -353,7 +505,7 @@ mod tests {
         let memberships = set_with(&["space_x"]);
         assert!(is_event_visible_to(
             &evt,
-            &Some("bob".into()),
+            &["bob".into()],
             &memberships
         ));
     }
@@ -365,7 +517,7 @@ mod tests {
         let memberships = set_with(&["space_y"]); // bob is in space_y, not space_x
         assert!(!is_event_visible_to(
             &evt,
-            &Some("bob".into()),
+            &["bob".into()],
             &memberships
         ));
     }
@@ -377,25 +529,11 @@ mod tests {
         let evt = event_with(9, "alice", vec![vec!["h".into(), "space_x".into()]]);
         assert!(!is_event_visible_to(
             &evt,
-            &Some("bob".into()),
+            &["bob".into()],
             &empty_set()
         ));
     }
 
-    /// TTL guard: the lazy refresh should only fire when the cache exceeds
-    /// `MEMBERSHIP_TTL`. We can't drive the actual `maybe_refresh_memberships`
-    /// here without a DB, but we can sanity-check the arithmetic that decides
-    /// whether to skip vs. refresh.
-    #[test]
-    fn ttl_guard_skips_recent_refresh() {
-        let recent = Instant::now();
-        assert!(
-            recent.elapsed() < MEMBERSHIP_TTL,
-            "a just-stamped Instant must be considered fresh — \
-             otherwise we'd refresh on every broadcast event after AUTH"
-        );
-    }
-
     /// Visibility-tagged events without an h-tag still respect author / p-tag.
     /// Membership cache doesn't apply when there's no h-tag.
     #[test]
@@ -409,12 +547,42 @@ mod tests {
             ],
         );
         // Bob is p-tagged → visible
-        assert!(is_event_visible_to(&evt, &Some("bob".into()), &empty_set()));
+        assert!(is_event_visible_to(&evt, &["bob".into()], &empty_set()));
         // Carol is not author, not p-tagged, no h-tag to fall back to → hidden
         assert!(!is_event_visible_to(
             &evt,
-            &Some("carol".into()),
+            &["carol".into()],
             &set_with(&["any_space"])
         ));
     }
+
+    /// Gift wraps reach only their authenticated p target — not anonymous
+    /// clients, not the throwaway author key, not space members.
+    #[test]
+    fn gift_wrap_visible_only_to_recipient() {
+        let wrap = event_with(1059, "throwaway", vec![vec!["p".into(), "bob".into()]]);
+        assert!(!is_event_visible_to(&wrap, &[], &empty_set()));
+        assert!(!is_event_visible_to(&wrap, &["throwaway".into()], &empty_set()));
+        assert!(!is_event_visible_to(&wrap, &["carol".into()], &set_with(&["space_x"])));
+        assert!(is_event_visible_to(&wrap, &["carol".into(), "bob".into()], &empty_set()));
+    }
+
+    /// Multi-account connection: access granted to ANY authed pubkey counts.
+    #[test]
+    fn any_of_several_authed_pubkeys_grants_visibility() {
+        let authed: Vec<String> = vec!["carol".into(), "bob".into()];
+        let own = event_with(9, "bob", vec![vec!["h".into(), "space_x".into()]]);
+        assert!(is_event_visible_to(&own, &authed, &empty_set()));
+        let tagged = event_with(
+            1,
+            "alice",
+            vec![
+                vec!["visibility".into(), "private".into()],
+                vec!["p".into(), "bob".into()],
+            ],
+        );
+        assert!(is_event_visible_to(&tagged, &authed, &empty_set()));
+        let other = event_with(1, "alice", vec![vec!["visibility".into(), "private".into()]]);
+        assert!(!is_event_visible_to(&other, &authed, &empty_set()));
+    }
 }
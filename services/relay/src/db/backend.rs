@@ -21,19 +21,25 @@
 //!     `embedded` Cargo feature, so the production binary never links the SQLite
 //!     driver and `match self { Db::Pg(p) => … }` stays exhaustive.
 
+use crate::music::catalog::CatalogQuery;
+use crate::music::integrity::DanglingRef;
+use crate::music::kinds::Coordinate;
 use crate::nostr::event::Event;
 use crate::nostr::filter::Filter;
+use crate::nostr::nip29::audit::ModerationAction;
+use crate::nostr::nip29::policy::GroupPolicy;
+use crate::nostr::nip56::FiledReport;
 use sqlx::PgPool;
 use std::collections::HashSet;
 
 #[cfg(feature = "embedded")]
 use sqlx::SqlitePool;
 
-use super::{event_store, group_store, membership_source};
+use super::{event_store, group_store, management_store, membership_source};
 use crate::protocol::nip50;
 
 #[cfg(feature = "embedded")]
-use super::{sqlite, sqlite_groups};
+use super::{sqlite, sqlite_groups, sqlite_management};
 
 /// A relay storage backend: multi-tenant Postgres, or embedded single-file
 /// SQLite.
@@ -45,6 +51,15 @@ pub enum Db {
 }
 
 impl Db {
+    /// The Postgres pool, for production-only machinery (e.g. LISTEN).
+    pub fn as_pg(&self) -> Option<&PgPool> {
+        match self {
+            Db::Pg(p) => Some(p),
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(_) => None,
+        }
+    }
+
     // ---- event store -----------------------------------------------------
 
     /// Store an event (handles replaceable/addressable supersession). Returns
@@ -58,16 +73,17 @@ impl Db {
     }
 
     /// Query events matching a filter, with visibility/membership gating driven
-    /// by `authed_pubkey` on BOTH backends (#18).
+    /// by `authed_pubkeys` on BOTH backends (#18). An event is visible if it is
+    /// visible to ANY of them; an empty slice means an anonymous reader.
     pub async fn query_events(
         &self,
         filter: &Filter,
-        authed_pubkey: Option<&str>,
+        authed_pubkeys: &[String],
     ) -> anyhow::Result<Vec<Event>> {
         match self {
-            Db::Pg(p) => event_store::query_events(p, filter, authed_pubkey).await,
+            Db::Pg(p) => event_store::query_events(p, filter, authed_pubkeys).await,
             #[cfg(feature = "embedded")]
-            Db::Sqlite(p) => sqlite::query_events(p, filter, authed_pubkey).await,
+            Db::Sqlite(p) => sqlite::query_events(p, filter, authed_pubkeys).await,
         }
     }
 
@@ -80,6 +96,15 @@ impl Db {
         }
     }
 
+    /// Ids of the `limit` newest events in a group (NIP-29 `previous` checks).
+    pub async fn recent_group_event_ids(&self, group_id: &str, limit: i64) -> anyhow::Result<Vec<String>> {
+        match self {
+            Db::Pg(p) => event_store::recent_group_event_ids(p, group_id, limit).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::recent_group_event_ids(p, group_id, limit).await,
+        }
+    }
+
     /// Delete an event by id.
     pub async fn delete_event(&self, event_id: &str) -> anyhow::Result<bool> {
         match self {
@@ -89,17 +114,78 @@ impl Db {
         }
     }
 
-    /// NIP-50 full-text search, visibility-gated by `authed_pubkey` (#18).
+    /// NIP-50 full-text search, visibility-gated by `authed_pubkeys` (#18).
     pub async fn search_events(
         &self,
         query: &str,
         limit: i64,
-        authed_pubkey: Option<&str>,
+        authed_pubkeys: &[String],
     ) -> anyhow::Result<Vec<Event>> {
         match self {
-            Db::Pg(p) => nip50::search_events(p, query, limit, authed_pubkey).await,
+            Db::Pg(p) => nip50::search_events(p, query, limit, authed_pubkeys).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::search_events(p, query, limit, authed_pubkeys).await,
+        }
+    }
+
+    /// Per-event aggregate counters (`nostr::aggregates`) of `target_ids`, as
+    /// `(target, metric, key, count, total)`.
+    pub async fn event_aggregates(
+        &self,
+        target_ids: &[String],
+    ) -> anyhow::Result<Vec<(String, String, String, i64, i64)>> {
+        match self {
+            Db::Pg(p) => event_store::event_aggregates(p, target_ids).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::event_aggregates(p, target_ids).await,
+        }
+    }
+
+    /// Contributions to `target_ids` left out of the counters because their
+    /// event is gated, as `(event, target, metric, key, amount)`.
+    pub async fn gated_contributions(
+        &self,
+        target_ids: &[String],
+    ) -> anyhow::Result<Vec<(String, String, String, String, i64)>> {
+        match self {
+            Db::Pg(p) => event_store::gated_contributions(p, target_ids).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::gated_contributions(p, target_ids).await,
+        }
+    }
+
+    /// One page of ids answering a music catalog query (`music::catalog`),
+    /// best first, not yet visibility-gated.
+    pub async fn music_catalog_ids(
+        &self,
+        query: &CatalogQuery,
+        kinds: &[i32],
+        authors: &[String],
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<String>> {
+        match self {
+            Db::Pg(p) => event_store::music_catalog_ids(p, query, kinds, authors, limit, offset).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::music_catalog_ids(p, query, kinds, authors, limit, offset).await,
+        }
+    }
+
+    /// An album's or playlist's dangling entries (`music::integrity`).
+    pub async fn music_dangling_refs(&self, source: &Coordinate) -> anyhow::Result<Vec<DanglingRef>> {
+        match self {
+            Db::Pg(p) => event_store::music_dangling_refs(p, source).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::music_dangling_refs(p, source).await,
+        }
+    }
+
+    /// The albums and playlists listing `target`.
+    pub async fn music_dependents(&self, target: &Coordinate) -> anyhow::Result<Vec<Coordinate>> {
+        match self {
+            Db::Pg(p) => event_store::music_dependents(p, target).await,
             #[cfg(feature = "embedded")]
-            Db::Sqlite(p) => sqlite::search_events(p, query, limit, authed_pubkey).await,
+            Db::Sqlite(p) => sqlite::music_dependents(p, target).await,
         }
     }
 
@@ -128,6 +214,15 @@ impl Db {
         }
     }
 
+    /// Every group the relay hosts (e.g. to re-sign their state).
+    pub async fn group_ids(&self) -> anyhow::Result<Vec<String>> {
+        match self {
+            Db::Pg(p) => group_store::group_ids(p).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::group_ids(p).await,
+        }
+    }
+
     /// Is the pubkey an admin of the group?
     pub async fn is_admin(&self, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
         match self {
@@ -137,6 +232,16 @@ impl Db {
         }
     }
 
+    /// May the pubkey moderate the group: an admin of it (as [`Db::is_admin`])
+    /// or, on Postgres, a backend space admin or message moderator?
+    pub async fn can_moderate(&self, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
+        match self {
+            Db::Pg(p) => group_store::can_moderate(p, group_id, pubkey).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::is_admin(p, group_id, pubkey).await,
+        }
+    }
+
     /// Relay-native membership check (`relay.group_members` only — no UNION).
     /// Used by NIP-29 op handlers (add/remove/edit) where the authority is the
     /// relay's own group table.
@@ -230,6 +335,96 @@ impl Db {
         }
     }
 
+    /// The group's NIP-13 minimum difficulty (0 = none, or no such group).
+    pub async fn group_min_pow(&self, group_id: &str) -> anyhow::Result<u32> {
+        match self {
+            Db::Pg(p) => group_store::min_pow(p, group_id).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::min_pow(p, group_id).await,
+        }
+    }
+
+    /// Set the group's NIP-13 minimum difficulty (9002 `min_pow` tag).
+    pub async fn set_group_min_pow(&self, group_id: &str, bits: u32) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => group_store::set_min_pow(p, group_id, bits).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::set_min_pow(p, group_id, bits).await,
+        }
+    }
+
+    /// The group's slow-mode interval in seconds (0 = off, or no such group).
+    pub async fn group_slow_mode(&self, group_id: &str) -> anyhow::Result<u32> {
+        match self {
+            Db::Pg(p) => group_store::slow_mode(p, group_id).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::slow_mode(p, group_id).await,
+        }
+    }
+
+    /// Set the group's slow-mode interval (9002 `slow_mode` tag).
+    pub async fn set_group_slow_mode(&self, group_id: &str, secs: u32) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => group_store::set_slow_mode(p, group_id, secs).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::set_slow_mode(p, group_id, secs).await,
+        }
+    }
+
+    /// The group's write/read policy (announcement, allowed kinds, restricted).
+    pub async fn group_policy(&self, group_id: &str) -> anyhow::Result<GroupPolicy> {
+        match self {
+            Db::Pg(p) => group_store::policy(p, group_id).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::policy(p, group_id).await,
+        }
+    }
+
+    /// Replace the group's write/read policy (9007/9002 policy tags).
+    pub async fn set_group_policy(&self, group_id: &str, policy: &GroupPolicy) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => group_store::set_policy(p, group_id, policy).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::set_policy(p, group_id, policy).await,
+        }
+    }
+
+    /// Make a (new) group a sub-group of `parent_id` (9007 `parent` tag).
+    pub async fn set_group_parent(&self, group_id: &str, parent_id: &str, inherits: bool) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => group_store::set_parent(p, group_id, parent_id, inherits).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::set_parent(p, group_id, parent_id, inherits).await,
+        }
+    }
+
+    /// The group's parent and whether it inherits from it.
+    pub async fn group_parent(&self, group_id: &str) -> anyhow::Result<Option<(String, bool)>> {
+        match self {
+            Db::Pg(p) => group_store::parent(p, group_id).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::parent(p, group_id).await,
+        }
+    }
+
+    /// The group's direct sub-groups.
+    pub async fn group_children(&self, group_id: &str) -> anyhow::Result<Vec<String>> {
+        match self {
+            Db::Pg(p) => group_store::children(p, group_id).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::children(p, group_id).await,
+        }
+    }
+
+    /// Sub-groups whose membership follows this group's (transitively).
+    pub async fn inheriting_descendants(&self, group_id: &str) -> anyhow::Result<Vec<String>> {
+        match self {
+            Db::Pg(p) => group_store::inheriting_descendants(p, group_id).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::inheriting_descendants(p, group_id).await,
+        }
+    }
+
     /// COALESCE-update group metadata (9002 edit; only `Some` fields change).
     pub async fn edit_group_metadata(
         &self,
@@ -295,6 +490,286 @@ impl Db {
         }
     }
 
+    // ---- timed mutes -----------------------------------------------------
+
+    /// Mute `pubkey` in a group until `muted_until` (unix seconds).
+    pub async fn mute_member(
+        &self,
+        group_id: &str,
+        pubkey: &str,
+        muted_until: i64,
+        muted_by: &str,
+        reason: Option<&str>,
+    ) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => group_store::mute_member(p, group_id, pubkey, muted_until, muted_by, reason).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::mute_member(p, group_id, pubkey, muted_until, muted_by, reason).await,
+        }
+    }
+
+    /// Lift a mute. Returns whether one existed.
+    pub async fn unmute_member(&self, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
+        match self {
+            Db::Pg(p) => group_store::unmute_member(p, group_id, pubkey).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::unmute_member(p, group_id, pubkey).await,
+        }
+    }
+
+    /// When `pubkey`'s mute in the group ends, if it is muted at `now`.
+    pub async fn muted_until(&self, group_id: &str, pubkey: &str, now: i64) -> anyhow::Result<Option<i64>> {
+        match self {
+            Db::Pg(p) => group_store::muted_until(p, group_id, pubkey, now).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::muted_until(p, group_id, pubkey, now).await,
+        }
+    }
+
+    /// Mutes in effect at `now`, as (pubkey, muted_until).
+    pub async fn active_mutes(&self, group_id: &str, now: i64) -> anyhow::Result<Vec<(String, i64)>> {
+        match self {
+            Db::Pg(p) => group_store::active_mutes(p, group_id, now).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::active_mutes(p, group_id, now).await,
+        }
+    }
+
+    // ---- moderation audit log --------------------------------------------
+
+    /// Append a signed audit log entry for a management op.
+    pub async fn append_moderation_log(
+        &self,
+        action: &ModerationAction,
+        entry: &Event,
+    ) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => group_store::append_moderation_log(p, action, entry).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::append_moderation_log(p, action, entry).await,
+        }
+    }
+
+    /// A group's audit log entries (signed kind:9080 events), newest first.
+    pub async fn moderation_log(
+        &self,
+        group_id: &str,
+        since: Option<i64>,
+        until: Option<i64>,
+        limit: i64,
+    ) -> anyhow::Result<Vec<Event>> {
+        match self {
+            Db::Pg(p) => group_store::moderation_log(p, group_id, since, until, limit).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::moderation_log(p, group_id, since, until, limit).await,
+        }
+    }
+
+    // ---- NIP-56 moderation queue ----------------------------------------
+
+    /// Queue reports against group events (idempotent).
+    pub async fn file_reports(&self, reports: &[FiledReport]) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => group_store::file_reports(p, reports).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::file_reports(p, reports).await,
+        }
+    }
+
+    /// Drop every queue row of a report.
+    pub async fn withdraw_report(&self, report_id: &str) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => group_store::withdraw_report(p, report_id).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::withdraw_report(p, report_id).await,
+        }
+    }
+
+    /// Open reports — all, or only in groups `admin` administers.
+    pub async fn open_reports(&self, admin: Option<&str>) -> anyhow::Result<Vec<FiledReport>> {
+        match self {
+            Db::Pg(p) => group_store::open_reports(p, admin).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_groups::open_reports(p, admin).await,
+        }
+    }
+
+    /// Resolve the open reports naming `target_event_id`.
+    pub async fn resolve_reports(
+        &self,
+        target_event_id: &str,
+        resolved_by: &str,
+        resolution: &str,
+    ) -> anyhow::Result<u64> {
+        match self {
+            Db::Pg(p) => group_store::resolve_reports(p, target_event_id, resolved_by, resolution).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => {
+                sqlite_groups::resolve_reports(p, target_event_id, resolved_by, resolution).await
+            }
+        }
+    }
+
+    // ---- deletion tombstones (NIP-09 / NIP-29 9005) ----------------------
+
+    /// Record that `event_id` by `pubkey` was deleted; `store_event` then
+    /// refuses it on both backends.
+    pub async fn tombstone_event(&self, event_id: &str, pubkey: &str) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => event_store::tombstone_event(p, event_id, pubkey).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::tombstone_event(p, event_id, pubkey).await,
+        }
+    }
+
+    /// Record that the coordinate `(kind, pubkey, d_tag)` was deleted up to
+    /// `until`; older versions are refused, newer ones accepted.
+    pub async fn tombstone_address(
+        &self,
+        kind: i32,
+        pubkey: &str,
+        d_tag: &str,
+        until: i64,
+    ) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => event_store::tombstone_address(p, kind, pubkey, d_tag, until).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::tombstone_address(p, kind, pubkey, d_tag, until).await,
+        }
+    }
+
+    /// Is this event refused by a deletion tombstone?
+    pub async fn is_tombstoned(&self, event: &Event) -> anyhow::Result<bool> {
+        match self {
+            Db::Pg(p) => event_store::is_tombstoned(p, event).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::is_tombstoned(p, event).await,
+        }
+    }
+
+    // ---- NIP-62 request to vanish ----------------------------------------
+
+    /// Purge `pubkey` (events up to `until`, gift wraps to them, relay-native
+    /// memberships/roles) and tombstone it so older re-broadcasts are refused.
+    /// Returns (events deleted, groups the pubkey was removed from).
+    pub async fn vanish(&self, pubkey: &str, until: i64) -> anyhow::Result<(u64, Vec<String>)> {
+        match self {
+            Db::Pg(p) => event_store::vanish(p, pubkey, until).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::vanish(p, pubkey, until).await,
+        }
+    }
+
+    /// The vanish cutoff recorded for `pubkey`, if any.
+    pub async fn vanished_until(&self, pubkey: &str) -> anyhow::Result<Option<i64>> {
+        match self {
+            Db::Pg(p) => event_store::vanished_until(p, pubkey).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite::vanished_until(p, pubkey).await,
+        }
+    }
+
+    // ---- NIP-86 relay management ----------------------------------------
+
+    /// Ban `pubkey` from publishing (lifts any allowlist entry).
+    pub async fn ban_pubkey(&self, pubkey: &str, reason: Option<&str>) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => management_store::ban_pubkey(p, pubkey, reason).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::ban_pubkey(p, pubkey, reason).await,
+        }
+    }
+
+    /// Allowlist `pubkey` (lifts any ban).
+    pub async fn allow_pubkey(&self, pubkey: &str, reason: Option<&str>) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => management_store::allow_pubkey(p, pubkey, reason).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::allow_pubkey(p, pubkey, reason).await,
+        }
+    }
+
+    /// Lift a ban on `pubkey` (the allowlist is untouched).
+    pub async fn unban_pubkey(&self, pubkey: &str) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => management_store::unban_pubkey(p, pubkey).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::unban_pubkey(p, pubkey).await,
+        }
+    }
+
+    /// Banned pubkeys with their reasons.
+    pub async fn list_banned_pubkeys(&self) -> anyhow::Result<Vec<(String, Option<String>)>> {
+        match self {
+            Db::Pg(p) => management_store::list_banned_pubkeys(p).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::list_banned_pubkeys(p).await,
+        }
+    }
+
+    /// Allowlisted pubkeys with their reasons.
+    pub async fn list_allowed_pubkeys(&self) -> anyhow::Result<Vec<(String, Option<String>)>> {
+        match self {
+            Db::Pg(p) => management_store::list_allowed_pubkeys(p).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::list_allowed_pubkeys(p).await,
+        }
+    }
+
+    /// Ban and delete `event_id`. Returns whether a stored event was deleted.
+    pub async fn ban_event(&self, event_id: &str, reason: Option<&str>) -> anyhow::Result<bool> {
+        match self {
+            Db::Pg(p) => management_store::ban_event(p, event_id, reason).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::ban_event(p, event_id, reason).await,
+        }
+    }
+
+    /// Lift an event ban. Returns whether one existed.
+    pub async fn allow_event(&self, event_id: &str) -> anyhow::Result<bool> {
+        match self {
+            Db::Pg(p) => management_store::allow_event(p, event_id).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::allow_event(p, event_id).await,
+        }
+    }
+
+    /// Banned event ids with their reasons.
+    pub async fn list_banned_events(&self) -> anyhow::Result<Vec<(String, Option<String>)>> {
+        match self {
+            Db::Pg(p) => management_store::list_banned_events(p).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::list_banned_events(p).await,
+        }
+    }
+
+    /// Is `event_id` banned?
+    pub async fn is_event_banned(&self, event_id: &str) -> anyhow::Result<bool> {
+        match self {
+            Db::Pg(p) => management_store::is_event_banned(p, event_id).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::is_event_banned(p, event_id).await,
+        }
+    }
+
+    /// An operator setting (NIP-11 overrides), if stored.
+    pub async fn get_setting(&self, key: &str) -> anyhow::Result<Option<String>> {
+        match self {
+            Db::Pg(p) => management_store::get_setting(p, key).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::get_setting(p, key).await,
+        }
+    }
+
+    /// Store an operator setting.
+    pub async fn set_setting(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        match self {
+            Db::Pg(p) => management_store::set_setting(p, key, value).await,
+            #[cfg(feature = "embedded")]
+            Db::Sqlite(p) => sqlite_management::set_setting(p, key, value).await,
+        }
+    }
+
     // ---- unified membership (broadcast cache + read gate) ----------------
 
     /// Membership for read-gating / broadcast visibility. **This is where the
@@ -1,8 +1,15 @@
 use serde_json::Value;
-use sqlx::{PgPool, Postgres};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
 
+use super::group_store;
+use crate::music::catalog::{self, CatalogEntry, CatalogQuery};
+use crate::music::integrity::DanglingRef;
+use crate::music::kinds::Coordinate;
+use crate::nostr::aggregates;
 use crate::nostr::event::Event;
 use crate::nostr::filter::Filter;
+use crate::nostr::nip56::KIND_REPORT;
+use crate::nostr::nip59::RECIPIENT_ONLY_KINDS_SQL;
 
 /// Hard cap on rows returned per query, matching strfry's 500 (RELAY_OPTIMIZATIONS
 /// §1). A client cannot tie up a DB connection with `limit: 5000`.
@@ -47,7 +54,10 @@ pub async fn store_event(pool: &PgPool, event: &Event) -> anyhow::Result<bool> {
     // Only deletes if the new event is strictly newer (created_at >).
     // If the existing event is newer or same age, the delete matches nothing
     // and the subsequent insert will fail on the unique constraint — that's fine,
-    // we return Ok(false) to indicate "duplicate/superseded".
+    // we return Ok(false) to indicate "duplicate/superseded". Both run in one
+    // transaction, so the delete only sticks if the insert goes through: a
+    // newer version refused by a tombstone leaves the current one in place.
+    let mut tx = pool.begin().await?;
     if is_replaceable(event.kind) {
         sqlx::query(
             "DELETE FROM relay.events WHERE pubkey = $1 AND kind = $2 AND created_at < $3",
@@ -55,7 +65,7 @@ pub async fn store_event(pool: &PgPool, event: &Event) -> anyhow::Result<bool> {
         .bind(&event.pubkey)
         .bind(event.kind)
         .bind(event.created_at)
-        .execute(pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| {
             tracing::error!(kind = event.kind, error = %e, "Failed to delete old replaceable event");
@@ -70,7 +80,7 @@ pub async fn store_event(pool: &PgPool, event: &Event) -> anyhow::Result<bool> {
             .bind(event.kind)
             .bind(d)
             .bind(event.created_at)
-            .execute(pool)
+            .execute(&mut *tx)
             .await
             .map_err(|e| {
                 tracing::error!(kind = event.kind, error = %e, "Failed to delete old addressable event");
@@ -79,10 +89,23 @@ pub async fn store_event(pool: &PgPool, event: &Event) -> anyhow::Result<bool> {
         }
     }
 
+    // Tombstones refuse the insert: a vanished pubkey's events at or before
+    // its cutoff (NIP-62; further vanish requests are always kept), deleted
+    // event ids, deleted addressable coordinates up to their cutoff, and event
+    // ids banned through NIP-86.
     let result = sqlx::query(
         r#"
         INSERT INTO relay.events (id, pubkey, created_at, kind, tags, content, sig, d_tag, h_tag, visibility, p_tags, e_tags)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12
+        WHERE ($4 = 62 OR NOT EXISTS (
+            SELECT 1 FROM relay.vanished_pubkeys WHERE pubkey = $2 AND vanished_until >= $3
+        ))
+        AND NOT EXISTS (SELECT 1 FROM relay.deleted_events WHERE event_id = $1 AND pubkey = $2)
+        AND NOT EXISTS (
+            SELECT 1 FROM relay.deleted_addresses
+            WHERE kind = $4 AND pubkey = $2 AND d_tag = $8 AND deleted_until >= $3
+        )
+        AND NOT EXISTS (SELECT 1 FROM relay.banned_events WHERE event_id = $1)
         ON CONFLICT (id) DO NOTHING
         "#,
     )
@@ -98,11 +121,18 @@ pub async fn store_event(pool: &PgPool, event: &Event) -> anyhow::Result<bool> {
     .bind(&visibility)
     .bind(&p_tags)
     .bind(&e_tags)
-    .execute(pool)
+    .execute(&mut *tx)
     .await;
 
+    // Anything but a stored event drops `tx`, rolling the delete back.
     match result {
-        Ok(r) => Ok(r.rows_affected() > 0),
+        Ok(r) if r.rows_affected() > 0 => {
+            record_contribution(&mut tx, event).await?;
+            index_music(&mut tx, event).await?;
+            tx.commit().await?;
+            Ok(true)
+        }
+        Ok(_) => Ok(false),
         Err(e) => {
             // Unique constraint violation for replaceable/addressable means
             // the existing event is newer — not an error, just a no-op.
@@ -129,14 +159,209 @@ pub async fn store_event(pool: &PgPool, event: &Event) -> anyhow::Result<bool> {
     }
 }
 
+/// Record what a just-stored event adds to another event's aggregates; the
+/// `event_contributions` trigger updates the counters (migration 014).
+async fn record_contribution(conn: &mut PgConnection, event: &Event) -> anyhow::Result<()> {
+    let Some(contribution) = aggregates::contribution(event) else {
+        return Ok(());
+    };
+    let (metric, key, amount) = contribution.columns();
+    sqlx::query(
+        "INSERT INTO relay.event_contributions (event_id, target_id, metric, key, amount, gated) \
+         VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (event_id) DO NOTHING",
+    )
+    .bind(&event.id)
+    .bind(&contribution.target)
+    .bind(metric)
+    .bind(key)
+    .bind(amount)
+    .bind(aggregates::is_gated(event))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Index a just-stored track, album or playlist in the music catalog
+/// (migration 015).
+async fn index_music(conn: &mut PgConnection, event: &Event) -> anyhow::Result<()> {
+    if let Some(entry) = CatalogEntry::from_event(event) {
+        sqlx::query(
+            "INSERT INTO relay.music_catalog (event_id, title, artist, genre, album, duration, released_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (event_id) DO NOTHING",
+        )
+        .bind(&event.id)
+        .bind(&entry.title)
+        .bind(&entry.artist)
+        .bind(&entry.genre)
+        .bind(&entry.album)
+        .bind(entry.duration)
+        .bind(entry.released_at)
+        .execute(&mut *conn)
+        .await?;
+    }
+    for (position, coordinate) in catalog::refs(event) {
+        sqlx::query(
+            "INSERT INTO relay.music_refs (event_id, position, ref_kind, ref_pubkey, ref_d) \
+             VALUES ($1, $2, $3, $4, $5) ON CONFLICT (event_id, position) DO NOTHING",
+        )
+        .bind(&event.id)
+        .bind(position as i32)
+        .bind(coordinate.kind)
+        .bind(&coordinate.pubkey)
+        .bind(&coordinate.d)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Ids of the events answering a music catalog query, best first (release
+/// date, or the album's/playlist's order). Not visibility-gated: callers
+/// pass them through `query_events`.
+pub async fn music_catalog_ids(
+    pool: &PgPool,
+    query: &CatalogQuery,
+    kinds: &[i32],
+    authors: &[String],
+    limit: i64,
+    offset: i64,
+) -> anyhow::Result<Vec<String>> {
+    let mut qb: QueryBuilder<Postgres> = QueryBuilder::new("SELECT t.id FROM ");
+    match &query.source {
+        Some(source) => {
+            qb.push(
+                "relay.music_refs r \
+                 JOIN relay.events src ON src.id = r.event_id \
+                 JOIN relay.events t ON t.pubkey = r.ref_pubkey AND t.kind = r.ref_kind AND t.d_tag = r.ref_d \
+                 LEFT JOIN relay.music_catalog c ON c.event_id = t.id \
+                 WHERE src.kind = ",
+            )
+            .push_bind(source.kind)
+            .push(" AND src.pubkey = ")
+            .push_bind(source.pubkey.clone())
+            .push(" AND src.d_tag = ")
+            .push_bind(source.d.clone());
+        }
+        None => {
+            qb.push("relay.music_catalog c JOIN relay.events t ON t.id = c.event_id WHERE TRUE");
+        }
+    }
+    if let Some(ref artist) = query.artist {
+        qb.push(" AND lower(c.artist) = lower(").push_bind(artist.clone()).push(")");
+    }
+    if let Some(ref genre) = query.genre {
+        qb.push(" AND c.genre = ").push_bind(genre.clone());
+    }
+    for word in &query.words {
+        qb.push(" AND lower(c.title) LIKE ")
+            .push_bind(catalog::contains_pattern(word))
+            .push(r" ESCAPE '\'");
+    }
+    if !kinds.is_empty() {
+        qb.push(" AND t.kind = ANY(").push_bind(kinds.to_vec()).push(")");
+    }
+    if !authors.is_empty() {
+        qb.push(" AND t.pubkey = ANY(").push_bind(authors.to_vec()).push(")");
+    }
+    qb.push(if query.source.is_some() {
+        " ORDER BY r.position"
+    } else {
+        " ORDER BY c.released_at DESC, t.id"
+    });
+    qb.push(" LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+    let ids: Vec<(String,)> = qb.build_query_as().fetch_all(pool).await?;
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
+/// An album's or playlist's entries with no event stored at their coordinate,
+/// in order. Entries whose address was tombstoned by a NIP-09 deletion are
+/// `removed`.
+pub async fn music_dangling_refs(pool: &PgPool, source: &Coordinate) -> anyhow::Result<Vec<DanglingRef>> {
+    let rows: Vec<(i32, i32, String, String, bool)> = sqlx::query_as(
+        "SELECT r.position, r.ref_kind, r.ref_pubkey, r.ref_d, \
+                EXISTS (SELECT 1 FROM relay.deleted_addresses da \
+                        WHERE da.kind = r.ref_kind AND da.pubkey = r.ref_pubkey AND da.d_tag = r.ref_d) \
+         FROM relay.music_refs r JOIN relay.events src ON src.id = r.event_id \
+         WHERE src.kind = $1 AND src.pubkey = $2 AND src.d_tag = $3 \
+           AND NOT EXISTS (SELECT 1 FROM relay.events t \
+                           WHERE t.pubkey = r.ref_pubkey AND t.kind = r.ref_kind AND t.d_tag = r.ref_d) \
+         ORDER BY r.position",
+    )
+    .bind(source.kind)
+    .bind(&source.pubkey)
+    .bind(&source.d)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(position, kind, pubkey, d, removed)| DanglingRef {
+            position: position.into(),
+            coordinate: Coordinate { kind, pubkey, d },
+            removed,
+        })
+        .collect())
+}
+
+/// The albums and playlists listing `target`.
+pub async fn music_dependents(pool: &PgPool, target: &Coordinate) -> anyhow::Result<Vec<Coordinate>> {
+    let rows: Vec<(i32, String, String)> = sqlx::query_as(
+        "SELECT DISTINCT src.kind, src.pubkey, src.d_tag \
+         FROM relay.music_refs r JOIN relay.events src ON src.id = r.event_id \
+         WHERE r.ref_kind = $1 AND r.ref_pubkey = $2 AND r.ref_d = $3 AND src.d_tag IS NOT NULL",
+    )
+    .bind(target.kind)
+    .bind(&target.pubkey)
+    .bind(&target.d)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(kind, pubkey, d)| Coordinate { kind, pubkey, d }).collect())
+}
+
+/// Aggregate counters of `target_ids` as `(target, metric, key, count, total)`.
+pub async fn event_aggregates(
+    pool: &PgPool,
+    target_ids: &[String],
+) -> anyhow::Result<Vec<(String, String, String, i64, i64)>> {
+    if target_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rows = sqlx::query_as(
+        "SELECT target_id, metric, key, count, total FROM relay.event_aggregates \
+         WHERE target_id = ANY($1) ORDER BY target_id, metric, key",
+    )
+    .bind(target_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Gated contributions to `target_ids` as `(event, target, metric, key,
+/// amount)`, for the caller to count per reader.
+pub async fn gated_contributions(
+    pool: &PgPool,
+    target_ids: &[String],
+) -> anyhow::Result<Vec<(String, String, String, String, i64)>> {
+    if target_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rows = sqlx::query_as(
+        "SELECT event_id, target_id, metric, key, amount FROM relay.event_contributions \
+         WHERE gated AND target_id = ANY($1)",
+    )
+    .bind(target_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
 /// Query events matching a filter with dynamic WHERE clauses
-pub async fn query_events(pool: &PgPool, filter: &Filter, authed_pubkey: Option<&str>) -> anyhow::Result<Vec<Event>> {
+pub async fn query_events(pool: &PgPool, filter: &Filter, authed_pubkeys: &[String]) -> anyhow::Result<Vec<Event>> {
     // Delegate NIP-50 full-text search to the dedicated handler
     if let Some(ref search_query) = filter.search {
         // Clamp to MAX_LIMIT so a search REQ can't tie up a DB connection (strfry
         // caps at 500). See RELAY_OPTIMIZATIONS §1.
         let limit = filter.limit.unwrap_or(100).clamp(0, MAX_QUERY_LIMIT);
-        return crate::protocol::nip50::search_events(pool, search_query, limit, authed_pubkey).await;
+        return crate::protocol::nip50::search_events(pool, search_query, limit, authed_pubkeys).await;
     }
 
     let start = std::time::Instant::now();
@@ -240,38 +465,61 @@ pub async fn query_events(pool: &PgPool, filter: &Filter, authed_pubkey: Option<
         ));
     }
 
-    // Visibility access control: filter protected events based on authenticated pubkey.
+    // Visibility access control: filter protected events based on the
+    // authenticated pubkeys (several on a multi-account connection — an event
+    // passes if it is visible to ANY of them).
     // - Private/unlisted events: only visible to author or p-tagged collaborators
     // - Space-scoped events (h_tag): only visible to author or space members
+    // - Gift wraps (NIP-59): only the p-tagged recipient, never the author
+    // - Reports filed in a group's queue (NIP-56): author or group admins
     // - Public events (no visibility, no h_tag): visible to everyone
-    match authed_pubkey {
-        Some(pk) => {
-            param_counter += 1;
-            let auth_param = param_counter;
-            binds.push(BindValue::StringVec(vec![pk.to_string()]));
-
-            // Private/unlisted: author or p-tagged collaborator. Use the indexed
-            // `p_tags` text[] column instead of a per-row jsonb_array_elements
-            // scan (#114).
-            conditions.push(format!(
-                "(visibility IS NULL OR pubkey = ${auth_param}[1] OR ${auth_param}[1] = ANY(p_tags))"
-            ));
-
-            // Space-scoped: author or member of EITHER the backend space
-            // (app.space_members) OR the relay-native group (relay.group_members).
-            // The native-group UNION was missing, so NIP-29-native members couldn't
-            // read their own group's history (#18 verifier).
-            conditions.push(format!(
-                "(h_tag IS NULL OR pubkey = ${auth_param}[1] \
-                 OR EXISTS (SELECT 1 FROM app.space_members WHERE space_id = h_tag AND pubkey = ${auth_param}[1]) \
-                 OR EXISTS (SELECT 1 FROM relay.group_members WHERE group_id = h_tag AND pubkey = ${auth_param}[1]))"
-            ));
-        }
-        None => {
-            // Unauthenticated: only public events (no visibility tag, no h_tag)
-            conditions.push("visibility IS NULL".to_string());
-            conditions.push("h_tag IS NULL".to_string());
-        }
+    if authed_pubkeys.is_empty() {
+        // Unauthenticated: only public events (no visibility tag, no h_tag)
+        conditions.push("visibility IS NULL".to_string());
+        conditions.push("h_tag IS NULL".to_string());
+        conditions.push(format!("kind NOT IN {RECIPIENT_ONLY_KINDS_SQL}"));
+        conditions.push(format!(
+            "(kind <> {KIND_REPORT} OR NOT EXISTS (SELECT 1 FROM relay.reports r WHERE r.report_id = relay.events.id))"
+        ));
+    } else {
+        param_counter += 1;
+        let auth_param = param_counter;
+        binds.push(BindValue::StringVec(authed_pubkeys.to_vec()));
+
+        // Private/unlisted: author or p-tagged collaborator. Use the indexed
+        // `p_tags` text[] column instead of a per-row jsonb_array_elements
+        // scan (#114).
+        conditions.push(format!(
+            "(visibility IS NULL OR pubkey = ANY(${auth_param}) OR p_tags && ${auth_param})"
+        ));
+
+        // Space-scoped: author or member of EITHER the backend space
+        // (app.space_members) OR the relay-native group (relay.group_members,
+        // including sub-groups inheriting from a group the pubkey is in).
+        // The native-group UNION was missing, so NIP-29-native members couldn't
+        // read their own group's history (#18 verifier).
+        let native_groups = group_store::with_descendants_sql(&format!(
+            "SELECT group_id, 0 FROM relay.group_members WHERE pubkey = ANY(${auth_param})"
+        ));
+        conditions.push(format!(
+            "(h_tag IS NULL OR pubkey = ANY(${auth_param}) \
+             OR EXISTS (SELECT 1 FROM app.space_members WHERE space_id = h_tag AND pubkey = ANY(${auth_param})) \
+             OR h_tag IN ({native_groups}))"
+        ));
+
+        // Gift wraps: the recipient only — the signing key is a throwaway.
+        conditions.push(format!(
+            "(kind NOT IN {RECIPIENT_ONLY_KINDS_SQL} OR p_tags && ${auth_param})"
+        ));
+
+        // Group reports: the reporter or a moderator of a group it was filed in.
+        let moderated = group_store::moderated_groups_sql(&format!("${auth_param}"));
+        conditions.push(format!(
+            "(kind <> {KIND_REPORT} OR pubkey = ANY(${auth_param}) \
+             OR NOT EXISTS (SELECT 1 FROM relay.reports r WHERE r.report_id = relay.events.id) \
+             OR EXISTS (SELECT 1 FROM relay.reports r \
+                        WHERE r.report_id = relay.events.id AND r.group_id IN ({moderated})))"
+        ));
     }
 
     let where_clause = if conditions.is_empty() {
@@ -352,6 +600,18 @@ pub async fn get_event_by_id(pool: &PgPool, event_id: &str) -> anyhow::Result<Op
     }))
 }
 
+/// Ids of the `limit` newest events tagged into `group_id`, newest first.
+pub async fn recent_group_event_ids(pool: &PgPool, group_id: &str, limit: i64) -> anyhow::Result<Vec<String>> {
+    let ids: Vec<(String,)> = sqlx::query_as(
+        "SELECT id FROM relay.events WHERE h_tag = $1 ORDER BY created_at DESC, id LIMIT $2",
+    )
+    .bind(group_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
 /// Delete an event by ID
 pub async fn delete_event(pool: &PgPool, event_id: &str) -> anyhow::Result<bool> {
     let result = sqlx::query("DELETE FROM relay.events WHERE id = $1")
@@ -401,6 +661,123 @@ pub async fn delete_addressable_upto(
     Ok(result.rows_affected())
 }
 
+/// Remember that `event_id` (by `pubkey`) was deleted so `store_event`
+/// refuses it from now on.
+pub async fn tombstone_event(pool: &PgPool, event_id: &str, pubkey: &str) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO relay.deleted_events (event_id, pubkey) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+    .bind(event_id)
+    .bind(pubkey)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Remember that the addressable coordinate `(kind, pubkey, d_tag)` was
+/// deleted up to `until` (the latest cutoff wins).
+pub async fn tombstone_address(
+    pool: &PgPool,
+    kind: i32,
+    pubkey: &str,
+    d_tag: &str,
+    until: i64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO relay.deleted_addresses (kind, pubkey, d_tag, deleted_until) VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (kind, pubkey, d_tag) DO UPDATE \
+         SET deleted_until = GREATEST(relay.deleted_addresses.deleted_until, EXCLUDED.deleted_until)",
+    )
+    .bind(kind)
+    .bind(pubkey)
+    .bind(d_tag)
+    .bind(until)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Is `event` covered by a deletion tombstone (by id or by coordinate)?
+pub async fn is_tombstoned(pool: &PgPool, event: &Event) -> anyhow::Result<bool> {
+    let d_tag = event.get_tag_value("d");
+    let row: (bool,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM relay.deleted_events WHERE event_id = $1 AND pubkey = $2) \
+             OR EXISTS (SELECT 1 FROM relay.deleted_addresses \
+                        WHERE kind = $3 AND pubkey = $2 AND d_tag = $4 AND deleted_until >= $5)",
+    )
+    .bind(&event.id)
+    .bind(&event.pubkey)
+    .bind(event.kind)
+    .bind(&d_tag)
+    .bind(event.created_at)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// NIP-62 vanish: record the tombstone (keeping the latest cutoff), delete
+/// every event by `pubkey` up to `until` plus every gift wrap addressed to
+/// them, and drop their relay-native group memberships, roles and queued
+/// reports (NIP-56). Backend `app.space_members` rows are the backend's to
+/// remove. Returns the number of events deleted and the groups the pubkey was
+/// removed from.
+pub async fn vanish(pool: &PgPool, pubkey: &str, until: i64) -> anyhow::Result<(u64, Vec<String>)> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO relay.vanished_pubkeys (pubkey, vanished_until) VALUES ($1, $2) \
+         ON CONFLICT (pubkey) DO UPDATE \
+         SET vanished_until = GREATEST(relay.vanished_pubkeys.vanished_until, EXCLUDED.vanished_until), \
+             requested_at = NOW()",
+    )
+    .bind(pubkey)
+    .bind(until)
+    .execute(&mut *tx)
+    .await?;
+
+    let own = sqlx::query("DELETE FROM relay.events WHERE pubkey = $1 AND created_at <= $2")
+        .bind(pubkey)
+        .bind(until)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    let wraps = sqlx::query(&format!(
+        "DELETE FROM relay.events WHERE kind IN {RECIPIENT_ONLY_KINDS_SQL} AND $1 = ANY(p_tags)"
+    ))
+    .bind(pubkey)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let groups: Vec<(String,)> =
+        sqlx::query_as("DELETE FROM relay.group_members WHERE pubkey = $1 RETURNING group_id")
+            .bind(pubkey)
+            .fetch_all(&mut *tx)
+            .await?;
+    sqlx::query("DELETE FROM relay.group_roles WHERE pubkey = $1")
+        .bind(pubkey)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM relay.reports WHERE reporter = $1 AND created_at <= $2")
+        .bind(pubkey)
+        .bind(until)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok((own + wraps, groups.into_iter().map(|g| g.0).collect()))
+}
+
+/// The NIP-62 cutoff recorded for `pubkey`, if it has vanished.
+pub async fn vanished_until(pool: &PgPool, pubkey: &str) -> anyhow::Result<Option<i64>> {
+    let row: Option<(i64,)> =
+        sqlx::query_as("SELECT vanished_until FROM relay.vanished_pubkeys WHERE pubkey = $1")
+            .bind(pubkey)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map(|r| r.0))
+}
+
 #[derive(sqlx::FromRow)]
 struct EventRow {
     id: String,
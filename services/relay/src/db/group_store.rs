@@ -1,5 +1,11 @@
 use sqlx::PgPool;
 
+use crate::nostr::event::Event;
+use crate::nostr::nip29::audit::ModerationAction;
+use crate::nostr::nip29::policy::GroupPolicy;
+use crate::nostr::nip29::subgroups::MAX_DEPTH;
+use crate::nostr::nip56::FiledReport;
+
 /// Create a new NIP-29 group
 pub async fn create_group(
     pool: &PgPool,
@@ -46,11 +52,50 @@ pub async fn group_exists(pool: &PgPool, group_id: &str) -> anyhow::Result<bool>
     Ok(row.is_some())
 }
 
-/// Check if a pubkey is an admin of a group
-pub async fn is_admin(pool: &PgPool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
-    let row: Option<(bool,)> = sqlx::query_as(
-        "SELECT EXISTS(SELECT 1 FROM relay.group_roles WHERE group_id = $1 AND pubkey = $2 AND role = 'admin')",
+/// Every relay-native group id.
+pub async fn group_ids(pool: &PgPool) -> anyhow::Result<Vec<String>> {
+    let rows: Vec<(String,)> = sqlx::query_as("SELECT group_id FROM relay.groups ORDER BY group_id")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// `chain`: the group `$1` plus the ancestors it inherits from (sub-groups;
+/// the walk stops at a group that doesn't inherit).
+pub(crate) fn ancestry_cte() -> String {
+    format!(
+        "WITH RECURSIVE chain(group_id, parent_id, inherits, depth) AS (\
+            SELECT group_id, parent_id, inherits, 0 FROM relay.groups WHERE group_id = $1 \
+            UNION ALL \
+            SELECT g.group_id, g.parent_id, g.inherits, c.depth + 1 \
+            FROM relay.groups g JOIN chain c ON g.group_id = c.parent_id \
+            WHERE c.inherits AND c.depth < {MAX_DEPTH}\
+         ) "
     )
+}
+
+/// The groups reachable from the seed rows `seed` (a `SELECT group_id, 0 ...`)
+/// plus every descendant that inherits from them: the groups a member of the
+/// seed groups belongs to.
+pub(crate) fn with_descendants_sql(seed: &str) -> String {
+    format!(
+        "WITH RECURSIVE reach(group_id, depth) AS (\
+            {seed} \
+            UNION ALL \
+            SELECT g.group_id, r.depth + 1 FROM relay.groups g JOIN reach r ON g.parent_id = r.group_id \
+            WHERE g.inherits AND r.depth < {MAX_DEPTH}\
+         ) SELECT DISTINCT group_id FROM reach"
+    )
+}
+
+/// Check if a pubkey is an admin of a group, directly or by inheritance from a
+/// parent group.
+pub async fn is_admin(pool: &PgPool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
+    let row: Option<(bool,)> = sqlx::query_as(&format!(
+        "{}SELECT EXISTS(SELECT 1 FROM relay.group_roles r JOIN chain c ON r.group_id = c.group_id \
+                        WHERE r.pubkey = $2 AND r.role = 'admin')",
+        ancestry_cte()
+    ))
     .bind(group_id)
     .bind(pubkey)
     .fetch_optional(pool)
@@ -59,6 +104,34 @@ pub async fn is_admin(pool: &PgPool, group_id: &str, pubkey: &str) -> anyhow::Re
     Ok(row.map(|r| r.0).unwrap_or(false))
 }
 
+/// The groups any of `pubkeys` (an SQL `text[]` expression) moderates: those
+/// it administers, directly or by inheritance (as [`is_admin`]), and backend
+/// spaces where it holds an admin role or one granting `MANAGE_MESSAGES`.
+pub(crate) fn moderated_groups_sql(pubkeys: &str) -> String {
+    format!(
+        "{} UNION \
+         SELECT mr.space_id FROM app.member_roles mr JOIN app.space_roles sr ON sr.id = mr.role_id \
+         WHERE mr.pubkey = ANY({pubkeys}) AND (sr.is_admin OR EXISTS (\
+             SELECT 1 FROM app.role_permissions rp WHERE rp.role_id = sr.id AND rp.permission = 'MANAGE_MESSAGES'))",
+        with_descendants_sql(&format!(
+            "SELECT group_id, 0 FROM relay.group_roles WHERE role = 'admin' AND pubkey = ANY({pubkeys})"
+        ))
+    )
+}
+
+/// May the pubkey moderate the group (see [`moderated_groups_sql`])?
+pub async fn can_moderate(pool: &PgPool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
+    let row: (bool,) = sqlx::query_as(&format!(
+        "SELECT $1 IN ({})",
+        moderated_groups_sql("ARRAY[$2::text]")
+    ))
+    .bind(group_id)
+    .bind(pubkey)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
 /// Check if a pubkey is a member of a group
 pub async fn is_member(pool: &PgPool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
     let row: Option<(bool,)> = sqlx::query_as(
@@ -163,6 +236,120 @@ pub async fn is_closed(pool: &PgPool, group_id: &str) -> anyhow::Result<Option<b
     Ok(row.map(|r| r.0))
 }
 
+/// The group's NIP-13 minimum difficulty (0 = none, or no such group).
+pub async fn min_pow(pool: &PgPool, group_id: &str) -> anyhow::Result<u32> {
+    let row: Option<(i32,)> = sqlx::query_as("SELECT min_pow FROM relay.groups WHERE group_id = $1")
+        .bind(group_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map_or(0, |r| r.0.max(0) as u32))
+}
+
+/// Set the group's NIP-13 minimum difficulty.
+pub async fn set_min_pow(pool: &PgPool, group_id: &str, bits: u32) -> anyhow::Result<()> {
+    sqlx::query("UPDATE relay.groups SET min_pow = $2 WHERE group_id = $1")
+        .bind(group_id)
+        .bind(bits as i32)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The group's slow-mode interval in seconds (0 = off, or no such group).
+pub async fn slow_mode(pool: &PgPool, group_id: &str) -> anyhow::Result<u32> {
+    let row: Option<(i32,)> =
+        sqlx::query_as("SELECT slow_mode_secs FROM relay.groups WHERE group_id = $1")
+            .bind(group_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map_or(0, |r| r.0.max(0) as u32))
+}
+
+/// Set the group's slow-mode interval in seconds.
+pub async fn set_slow_mode(pool: &PgPool, group_id: &str, secs: u32) -> anyhow::Result<()> {
+    sqlx::query("UPDATE relay.groups SET slow_mode_secs = $2 WHERE group_id = $1")
+        .bind(group_id)
+        .bind(secs as i32)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The group's write/read policy (the default for no such group).
+pub async fn policy(pool: &PgPool, group_id: &str) -> anyhow::Result<GroupPolicy> {
+    let row: Option<(bool, Vec<i32>, bool)> = sqlx::query_as(
+        "SELECT announcement, allowed_kinds, restricted FROM relay.groups WHERE group_id = $1",
+    )
+    .bind(group_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map_or_else(GroupPolicy::default, |(announcement, allowed_kinds, restricted)| {
+        GroupPolicy {
+            announcement,
+            allowed_kinds,
+            restricted,
+        }
+    }))
+}
+
+/// Replace the group's write/read policy.
+pub async fn set_policy(pool: &PgPool, group_id: &str, policy: &GroupPolicy) -> anyhow::Result<()> {
+    sqlx::query(
+        "UPDATE relay.groups SET announcement = $2, allowed_kinds = $3, restricted = $4 WHERE group_id = $1",
+    )
+    .bind(group_id)
+    .bind(policy.announcement)
+    .bind(&policy.allowed_kinds)
+    .bind(policy.restricted)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Make a (new) group a sub-group of `parent_id`.
+pub async fn set_parent(pool: &PgPool, group_id: &str, parent_id: &str, inherits: bool) -> anyhow::Result<()> {
+    sqlx::query("UPDATE relay.groups SET parent_id = $2, inherits = $3 WHERE group_id = $1")
+        .bind(group_id)
+        .bind(parent_id)
+        .bind(inherits)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The group's parent and whether it inherits from it (`None` for a root
+/// group or no such group).
+pub async fn parent(pool: &PgPool, group_id: &str) -> anyhow::Result<Option<(String, bool)>> {
+    let row: Option<(Option<String>, bool)> =
+        sqlx::query_as("SELECT parent_id, inherits FROM relay.groups WHERE group_id = $1")
+            .bind(group_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.and_then(|(parent, inherits)| parent.map(|p| (p, inherits))))
+}
+
+/// The group's direct sub-groups, for the 39000 event.
+pub async fn children(pool: &PgPool, group_id: &str) -> anyhow::Result<Vec<String>> {
+    let rows: Vec<(String,)> =
+        sqlx::query_as("SELECT group_id FROM relay.groups WHERE parent_id = $1 ORDER BY group_id")
+            .bind(group_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+/// Every sub-group below `group_id` that (transitively) inherits its
+/// membership — whose members change when the group's do.
+pub async fn inheriting_descendants(pool: &PgPool, group_id: &str) -> anyhow::Result<Vec<String>> {
+    let rows: Vec<(String,)> = sqlx::query_as(&with_descendants_sql(
+        "SELECT group_id, 0 FROM relay.groups WHERE group_id = $1",
+    ))
+    .bind(group_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.0).filter(|g| g != group_id).collect())
+}
+
 /// Set the private/closed access flags.
 pub async fn set_flags(
     pool: &PgPool,
@@ -222,3 +409,197 @@ pub async fn platform_space_exists(pool: &PgPool, group_id: &str) -> anyhow::Res
         .unwrap_or(None);
     Ok(collides.is_some())
 }
+
+type ReportRow = (String, String, String, String, Option<String>, i64);
+
+fn report_from_row(
+    (report_id, target_event_id, group_id, reporter, report_type, created_at): ReportRow,
+) -> FiledReport {
+    FiledReport {
+        report_id,
+        target_event_id,
+        group_id,
+        reporter,
+        report_type,
+        created_at,
+    }
+}
+
+/// Queue reports (one row per report/target; re-filing is a no-op).
+pub async fn file_reports(pool: &PgPool, reports: &[FiledReport]) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    for r in reports {
+        sqlx::query(
+            "INSERT INTO relay.reports (report_id, target_event_id, group_id, reporter, report_type, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING",
+        )
+        .bind(&r.report_id)
+        .bind(&r.target_event_id)
+        .bind(&r.group_id)
+        .bind(&r.reporter)
+        .bind(&r.report_type)
+        .bind(r.created_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Drop a report's queue rows (the report event was refused).
+pub async fn withdraw_report(pool: &PgPool, report_id: &str) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM relay.reports WHERE report_id = $1")
+        .bind(report_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Open reports, oldest first: all of them, or only those in groups `admin`
+/// moderates.
+pub async fn open_reports(pool: &PgPool, admin: Option<&str>) -> anyhow::Result<Vec<FiledReport>> {
+    let rows: Vec<ReportRow> = sqlx::query_as(&format!(
+        "SELECT report_id, target_event_id, group_id, reporter, report_type, created_at \
+         FROM relay.reports r \
+         WHERE status = 'open' \
+           AND ($1::text IS NULL OR r.group_id IN ({})) \
+         ORDER BY created_at, report_id, target_event_id",
+        moderated_groups_sql("ARRAY[$1::text]")
+    ))
+    .bind(admin)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(report_from_row).collect())
+}
+
+/// Resolve every open report naming `target_event_id`. Returns how many were.
+pub async fn resolve_reports(
+    pool: &PgPool,
+    target_event_id: &str,
+    resolved_by: &str,
+    resolution: &str,
+) -> anyhow::Result<u64> {
+    let result = sqlx::query(
+        "UPDATE relay.reports \
+         SET status = 'resolved', resolution = $3, resolved_by = $2, resolved_at = NOW() \
+         WHERE target_event_id = $1 AND status = 'open'",
+    )
+    .bind(target_event_id)
+    .bind(resolved_by)
+    .bind(resolution)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Append an audit log entry (`entry` is the signed kind:9080 event).
+pub async fn append_moderation_log(
+    pool: &PgPool,
+    action: &ModerationAction,
+    entry: &Event,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO relay.moderation_log (id, group_id, actor, action, targets, reason, created_at, event) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT DO NOTHING",
+    )
+    .bind(&entry.id)
+    .bind(&action.group_id)
+    .bind(&action.actor)
+    .bind(action.action)
+    .bind(&action.targets)
+    .bind(&action.reason)
+    .bind(entry.created_at)
+    .bind(serde_json::to_value(entry)?)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// A group's audit log entries, newest first.
+pub async fn moderation_log(
+    pool: &PgPool,
+    group_id: &str,
+    since: Option<i64>,
+    until: Option<i64>,
+    limit: i64,
+) -> anyhow::Result<Vec<Event>> {
+    let rows: Vec<(serde_json::Value,)> = sqlx::query_as(
+        "SELECT event FROM relay.moderation_log \
+         WHERE group_id = $1 AND ($2::bigint IS NULL OR created_at >= $2) \
+           AND ($3::bigint IS NULL OR created_at <= $3) \
+         ORDER BY created_at DESC, id LIMIT $4",
+    )
+    .bind(group_id)
+    .bind(since)
+    .bind(until)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(v,)| serde_json::from_value(v).ok())
+        .collect())
+}
+
+/// Mute `pubkey` in a group until `muted_until` (unix seconds), replacing any
+/// existing mute.
+pub async fn mute_member(
+    pool: &PgPool,
+    group_id: &str,
+    pubkey: &str,
+    muted_until: i64,
+    muted_by: &str,
+    reason: Option<&str>,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO relay.group_mutes (group_id, pubkey, muted_until, muted_by, reason) \
+         VALUES ($1, $2, $3, $4, $5) \
+         ON CONFLICT (group_id, pubkey) DO UPDATE SET \
+            muted_until = EXCLUDED.muted_until, muted_by = EXCLUDED.muted_by, \
+            reason = EXCLUDED.reason, created_at = NOW()",
+    )
+    .bind(group_id)
+    .bind(pubkey)
+    .bind(muted_until)
+    .bind(muted_by)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Lift a mute. Returns whether one existed.
+pub async fn unmute_member(pool: &PgPool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM relay.group_mutes WHERE group_id = $1 AND pubkey = $2")
+        .bind(group_id)
+        .bind(pubkey)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// When `pubkey`'s mute in the group ends, if it is muted at `now`.
+pub async fn muted_until(pool: &PgPool, group_id: &str, pubkey: &str, now: i64) -> anyhow::Result<Option<i64>> {
+    let row: Option<(i64,)> = sqlx::query_as(
+        "SELECT muted_until FROM relay.group_mutes \
+         WHERE group_id = $1 AND pubkey = $2 AND muted_until > $3",
+    )
+    .bind(group_id)
+    .bind(pubkey)
+    .bind(now)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.0))
+}
+
+/// Mutes in effect at `now`, as (pubkey, muted_until), soonest-ending first.
+pub async fn active_mutes(pool: &PgPool, group_id: &str, now: i64) -> anyhow::Result<Vec<(String, i64)>> {
+    Ok(sqlx::query_as(
+        "SELECT pubkey, muted_until FROM relay.group_mutes \
+         WHERE group_id = $1 AND muted_until > $2 ORDER BY muted_until, pubkey",
+    )
+    .bind(group_id)
+    .bind(now)
+    .fetch_all(pool)
+    .await?)
+}
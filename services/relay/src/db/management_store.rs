@@ -0,0 +1,142 @@
+//! Postgres store behind the NIP-86 relay management API
+//! (`protocol::nip86`): pubkey bans and allowlist, event bans, and operator
+//! overrides of NIP-11 fields. Tables come from `migrations/006_management.sql`.
+
+use sqlx::PgPool;
+
+/// Ban `pubkey` from publishing. Lifts any allowlist entry for it.
+pub async fn ban_pubkey(pool: &PgPool, pubkey: &str, reason: Option<&str>) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO relay.banned_pubkeys (pubkey, reason) VALUES ($1, $2) \
+         ON CONFLICT (pubkey) DO UPDATE SET reason = EXCLUDED.reason",
+    )
+    .bind(pubkey)
+    .bind(reason)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM relay.allowed_pubkeys WHERE pubkey = $1")
+        .bind(pubkey)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Allow `pubkey` to publish: lifts any ban and adds it to the allowlist.
+pub async fn allow_pubkey(pool: &PgPool, pubkey: &str, reason: Option<&str>) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO relay.allowed_pubkeys (pubkey, reason) VALUES ($1, $2) \
+         ON CONFLICT (pubkey) DO UPDATE SET reason = EXCLUDED.reason",
+    )
+    .bind(pubkey)
+    .bind(reason)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM relay.banned_pubkeys WHERE pubkey = $1")
+        .bind(pubkey)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Lift a ban on `pubkey`, leaving the allowlist alone.
+pub async fn unban_pubkey(pool: &PgPool, pubkey: &str) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM relay.banned_pubkeys WHERE pubkey = $1")
+        .bind(pubkey)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Banned pubkeys with their reasons, oldest ban first.
+pub async fn list_banned_pubkeys(pool: &PgPool) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    Ok(
+        sqlx::query_as("SELECT pubkey, reason FROM relay.banned_pubkeys ORDER BY banned_at, pubkey")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+/// Allowlisted pubkeys with their reasons, oldest first.
+pub async fn list_allowed_pubkeys(pool: &PgPool) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    Ok(
+        sqlx::query_as("SELECT pubkey, reason FROM relay.allowed_pubkeys ORDER BY allowed_at, pubkey")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+/// Ban `event_id`: delete it and refuse it from now on. Returns whether a
+/// stored event was deleted.
+pub async fn ban_event(pool: &PgPool, event_id: &str, reason: Option<&str>) -> anyhow::Result<bool> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO relay.banned_events (event_id, reason) VALUES ($1, $2) \
+         ON CONFLICT (event_id) DO UPDATE SET reason = EXCLUDED.reason",
+    )
+    .bind(event_id)
+    .bind(reason)
+    .execute(&mut *tx)
+    .await?;
+    let deleted = sqlx::query("DELETE FROM relay.events WHERE id = $1")
+        .bind(event_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+    Ok(deleted > 0)
+}
+
+/// Lift an event ban (the event itself stays deleted until re-published).
+/// Returns whether a ban existed.
+pub async fn allow_event(pool: &PgPool, event_id: &str) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM relay.banned_events WHERE event_id = $1")
+        .bind(event_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Banned event ids with their reasons, oldest ban first.
+pub async fn list_banned_events(pool: &PgPool) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    Ok(
+        sqlx::query_as("SELECT event_id, reason FROM relay.banned_events ORDER BY banned_at, event_id")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+/// Is `event_id` banned?
+pub async fn is_event_banned(pool: &PgPool, event_id: &str) -> anyhow::Result<bool> {
+    let row: (bool,) =
+        sqlx::query_as("SELECT EXISTS (SELECT 1 FROM relay.banned_events WHERE event_id = $1)")
+            .bind(event_id)
+            .fetch_one(pool)
+            .await?;
+    Ok(row.0)
+}
+
+/// An operator setting, if one was stored.
+pub async fn get_setting(pool: &PgPool, key: &str) -> anyhow::Result<Option<String>> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT value FROM relay.settings WHERE key = $1")
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|(v,)| v))
+}
+
+/// Store an operator setting (last write wins).
+pub async fn set_setting(pool: &PgPool, key: &str, value: &str) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO relay.settings (key, value) VALUES ($1, $2) \
+         ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()",
+    )
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
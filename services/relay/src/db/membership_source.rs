@@ -14,20 +14,28 @@
 //!
 //! For the embedded SQLite relay (M6) there is no `app.space_members`; that
 //! build will swap in a relay-native-only implementation behind this same API.
+//!
+//! Relay-native groups may be sub-groups (`nip29::subgroups`): a member of a
+//! group is also a member of every descendant that inherits from it, so the
+//! relay-native leg walks the hierarchy.
 
 use sqlx::PgPool;
 use std::collections::HashSet;
 
+use super::group_store;
+
 /// Member if the pubkey is in `app.space_members` (backend) OR
-/// `relay.group_members` (relay-native) for this group id.
+/// `relay.group_members` (relay-native) for this group id or an ancestor it
+/// inherits from.
 pub async fn is_member(pool: &PgPool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
-    let row: Option<(i32,)> = sqlx::query_as(
-        "SELECT 1 WHERE EXISTS (\
+    let row: Option<(i32,)> = sqlx::query_as(&format!(
+        "{}SELECT 1 WHERE EXISTS (\
             SELECT 1 FROM app.space_members WHERE space_id = $1 AND pubkey = $2\
          ) OR EXISTS (\
-            SELECT 1 FROM relay.group_members WHERE group_id = $1 AND pubkey = $2\
+            SELECT 1 FROM relay.group_members m JOIN chain c ON m.group_id = c.group_id WHERE m.pubkey = $2\
          )",
-    )
+        group_store::ancestry_cte()
+    ))
     .bind(group_id)
     .bind(pubkey)
     .fetch_optional(pool)
@@ -38,11 +46,12 @@ pub async fn is_member(pool: &PgPool, group_id: &str, pubkey: &str) -> anyhow::R
 /// The set of group ids the pubkey belongs to, unioned across both worlds.
 /// Used to populate the per-connection broadcast-visibility cache on AUTH.
 pub async fn members_of(pool: &PgPool, pubkey: &str) -> anyhow::Result<HashSet<String>> {
-    let rows: Vec<(String,)> = sqlx::query_as(
+    let rows: Vec<(String,)> = sqlx::query_as(&format!(
         "SELECT space_id FROM app.space_members WHERE pubkey = $1 \
          UNION \
-         SELECT group_id FROM relay.group_members WHERE pubkey = $1",
-    )
+         SELECT group_id FROM ({}) native",
+        group_store::with_descendants_sql("SELECT group_id, 0 FROM relay.group_members WHERE pubkey = $1")
+    ))
     .bind(pubkey)
     .fetch_all(pool)
     .await?;
@@ -1,6 +1,7 @@
 pub mod backend;
 pub mod event_store;
 pub mod group_store;
+pub mod management_store;
 pub mod membership_source;
 pub mod pool;
 pub mod space_membership;
@@ -14,3 +15,7 @@ pub mod sqlite;
 /// SQLite-backed NIP-29 group store for the embedded in-process relay (M6).
 #[cfg(feature = "embedded")]
 pub mod sqlite_groups;
+
+/// SQLite-backed NIP-86 management store for the embedded in-process relay.
+#[cfg(feature = "embedded")]
+pub mod sqlite_management;
@@ -18,6 +18,18 @@ pub async fn run_migrations(pool: &PgPool) -> anyhow::Result<()> {
         include_str!("../../migrations/001_initial.sql"),
         include_str!("../../migrations/002_visibility_column.sql"),
         include_str!("../../migrations/003_tag_columns.sql"),
+        include_str!("../../migrations/004_vanish.sql"),
+        include_str!("../../migrations/005_deletion_tombstones.sql"),
+        include_str!("../../migrations/006_management.sql"),
+        include_str!("../../migrations/007_reports.sql"),
+        include_str!("../../migrations/008_moderation_log.sql"),
+        include_str!("../../migrations/009_group_min_pow.sql"),
+        include_str!("../../migrations/010_group_slow_mode.sql"),
+        include_str!("../../migrations/011_group_mutes.sql"),
+        include_str!("../../migrations/012_group_policies.sql"),
+        include_str!("../../migrations/013_group_hierarchy.sql"),
+        include_str!("../../migrations/014_event_aggregates.sql"),
+        include_str!("../../migrations/015_music_catalog.sql"),
     ];
     for migration in &migrations {
         sqlx::raw_sql(migration).execute(pool).await?;
@@ -13,8 +13,14 @@
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 
+use crate::music::catalog::{self, CatalogEntry, CatalogQuery};
+use crate::music::integrity::DanglingRef;
+use crate::music::kinds::{Coordinate, KIND_ALBUM, KIND_PLAYLIST, KIND_TRACK};
+use crate::nostr::aggregates;
 use crate::nostr::event::Event;
 use crate::nostr::filter::Filter;
+use crate::nostr::nip56::KIND_REPORT;
+use crate::nostr::nip59::RECIPIENT_ONLY_KINDS_SQL;
 
 /// Hard cap on rows per query (matches the Postgres store / strfry).
 const MAX_QUERY_LIMIT: i64 = 500;
@@ -65,6 +71,73 @@ CREATE TRIGGER IF NOT EXISTS events_ad AFTER DELETE ON events BEGIN
     INSERT INTO events_fts(events_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
 END;
 
+-- NIP-62 request-to-vanish tombstones (see migrations/004_vanish.sql).
+CREATE TABLE IF NOT EXISTS vanished_pubkeys (
+    pubkey         TEXT PRIMARY KEY,
+    vanished_until INTEGER NOT NULL
+);
+
+-- NIP-09 / NIP-29 deletion tombstones (see migrations/005_deletion_tombstones.sql).
+CREATE TABLE IF NOT EXISTS deleted_events (
+    event_id TEXT PRIMARY KEY,
+    pubkey   TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS deleted_addresses (
+    kind          INTEGER NOT NULL,
+    pubkey        TEXT NOT NULL,
+    d_tag         TEXT NOT NULL,
+    deleted_until INTEGER NOT NULL,
+    PRIMARY KEY (kind, pubkey, d_tag)
+);
+
+-- NIP-86 relay management (see migrations/006_management.sql).
+CREATE TABLE IF NOT EXISTS banned_pubkeys (
+    pubkey TEXT PRIMARY KEY,
+    reason TEXT
+);
+CREATE TABLE IF NOT EXISTS allowed_pubkeys (
+    pubkey TEXT PRIMARY KEY,
+    reason TEXT
+);
+CREATE TABLE IF NOT EXISTS banned_events (
+    event_id TEXT PRIMARY KEY,
+    reason   TEXT
+);
+CREATE TABLE IF NOT EXISTS settings (
+    key   TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+-- NIP-56 moderation queue (see migrations/007_reports.sql).
+CREATE TABLE IF NOT EXISTS reports (
+    report_id       TEXT NOT NULL,
+    target_event_id TEXT NOT NULL,
+    group_id        TEXT NOT NULL,
+    reporter        TEXT NOT NULL,
+    report_type     TEXT,
+    status          TEXT NOT NULL DEFAULT 'open',
+    resolution      TEXT,
+    resolved_by     TEXT,
+    created_at      INTEGER NOT NULL,
+    PRIMARY KEY (report_id, target_event_id)
+);
+CREATE INDEX IF NOT EXISTS idx_reports_group_status ON reports (group_id, status);
+CREATE INDEX IF NOT EXISTS idx_reports_target ON reports (target_event_id);
+
+-- Moderation audit log (see migrations/008_moderation_log.sql). `targets` and
+-- `event` are JSON text.
+CREATE TABLE IF NOT EXISTS moderation_log (
+    id         TEXT PRIMARY KEY,
+    group_id   TEXT NOT NULL,
+    actor      TEXT NOT NULL,
+    action     INTEGER NOT NULL,
+    targets    TEXT NOT NULL DEFAULT '[]',
+    reason     TEXT,
+    created_at INTEGER NOT NULL,
+    event      TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_moderation_log_group ON moderation_log (group_id, created_at DESC);
+
 -- NIP-29 group state (relay-authoritative; the embedded relay owns membership).
 CREATE TABLE IF NOT EXISTS groups (
     group_id   TEXT PRIMARY KEY,
@@ -72,7 +145,14 @@ CREATE TABLE IF NOT EXISTS groups (
     picture    TEXT,
     about      TEXT,
     is_private INTEGER NOT NULL DEFAULT 0,
-    is_closed  INTEGER NOT NULL DEFAULT 0
+    is_closed  INTEGER NOT NULL DEFAULT 0,
+    min_pow    INTEGER NOT NULL DEFAULT 0,
+    slow_mode_secs INTEGER NOT NULL DEFAULT 0,
+    announcement  INTEGER NOT NULL DEFAULT 0,
+    allowed_kinds TEXT NOT NULL DEFAULT '[]',
+    restricted    INTEGER NOT NULL DEFAULT 0,
+    parent_id     TEXT REFERENCES groups(group_id) ON DELETE CASCADE,
+    inherits      INTEGER NOT NULL DEFAULT 1
 );
 CREATE TABLE IF NOT EXISTS group_members (
     group_id TEXT NOT NULL REFERENCES groups(group_id) ON DELETE CASCADE,
@@ -85,6 +165,69 @@ CREATE TABLE IF NOT EXISTS group_roles (
     role     TEXT NOT NULL DEFAULT 'member',
     PRIMARY KEY (group_id, pubkey, role)
 );
+-- Timed mutes (see migrations/011_group_mutes.sql).
+CREATE TABLE IF NOT EXISTS group_mutes (
+    group_id    TEXT NOT NULL REFERENCES groups(group_id) ON DELETE CASCADE,
+    pubkey      TEXT NOT NULL,
+    muted_until INTEGER NOT NULL,
+    muted_by    TEXT NOT NULL,
+    reason      TEXT,
+    PRIMARY KEY (group_id, pubkey)
+);
+
+-- Per-event aggregates (see migrations/014_event_aggregates.sql).
+CREATE TABLE IF NOT EXISTS event_contributions (
+    event_id  TEXT PRIMARY KEY REFERENCES events(id) ON DELETE CASCADE,
+    target_id TEXT NOT NULL,
+    metric    TEXT NOT NULL,
+    key       TEXT NOT NULL DEFAULT '',
+    amount    INTEGER NOT NULL DEFAULT 0,
+    gated     INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS idx_event_contributions_gated ON event_contributions (target_id) WHERE gated;
+CREATE TABLE IF NOT EXISTS event_aggregates (
+    target_id TEXT NOT NULL,
+    metric    TEXT NOT NULL,
+    key       TEXT NOT NULL DEFAULT '',
+    count     INTEGER NOT NULL DEFAULT 0,
+    total     INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (target_id, metric, key)
+);
+CREATE TRIGGER IF NOT EXISTS event_contributions_ai AFTER INSERT ON event_contributions WHEN NOT new.gated BEGIN
+    INSERT INTO event_aggregates (target_id, metric, key, count, total)
+    VALUES (new.target_id, new.metric, new.key, 1, new.amount)
+    ON CONFLICT (target_id, metric, key) DO UPDATE
+    SET count = count + 1, total = total + excluded.total;
+END;
+CREATE TRIGGER IF NOT EXISTS event_contributions_ad AFTER DELETE ON event_contributions WHEN NOT old.gated BEGIN
+    UPDATE event_aggregates SET count = count - 1, total = total - old.amount
+    WHERE target_id = old.target_id AND metric = old.metric AND key = old.key;
+    DELETE FROM event_aggregates
+    WHERE target_id = old.target_id AND metric = old.metric AND key = old.key AND count <= 0;
+END;
+
+-- Music catalog (see migrations/015_music_catalog.sql).
+CREATE TABLE IF NOT EXISTS music_catalog (
+    event_id    TEXT PRIMARY KEY REFERENCES events(id) ON DELETE CASCADE,
+    title       TEXT,
+    artist      TEXT,
+    genre       TEXT,
+    album       TEXT,
+    duration    REAL,
+    released_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_music_catalog_artist ON music_catalog (lower(artist));
+CREATE INDEX IF NOT EXISTS idx_music_catalog_genre ON music_catalog (genre);
+CREATE INDEX IF NOT EXISTS idx_music_catalog_album ON music_catalog (album);
+CREATE TABLE IF NOT EXISTS music_refs (
+    event_id   TEXT NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    position   INTEGER NOT NULL,
+    ref_kind   INTEGER NOT NULL,
+    ref_pubkey TEXT NOT NULL,
+    ref_d      TEXT NOT NULL,
+    PRIMARY KEY (event_id, position)
+);
+CREATE INDEX IF NOT EXISTS idx_music_refs_target ON music_refs (ref_pubkey, ref_kind, ref_d);
 "#;
 
 /// Open (and create) a file-backed SQLite database at filesystem `path` and
@@ -97,10 +240,44 @@ pub async fn connect(path: &str) -> anyhow::Result<SqlitePool> {
         .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal);
     let pool = SqlitePoolOptions::new().connect_with(opts).await?;
     sqlx::raw_sql(SCHEMA).execute(&pool).await?;
+    add_missing_columns(&pool).await?;
     backfill_event_tags(&pool).await?;
+    backfill_music_catalog(&pool).await?;
     Ok(pool)
 }
 
+/// Columns added to existing tables after their first release, as
+/// `(table, column, definition)`. `CREATE TABLE IF NOT EXISTS` leaves a
+/// database created by an older build without them, so [`connect`] adds any
+/// that are missing.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("groups", "min_pow", "INTEGER NOT NULL DEFAULT 0"),
+    ("groups", "slow_mode_secs", "INTEGER NOT NULL DEFAULT 0"),
+    ("groups", "announcement", "INTEGER NOT NULL DEFAULT 0"),
+    ("groups", "allowed_kinds", "TEXT NOT NULL DEFAULT '[]'"),
+    ("groups", "restricted", "INTEGER NOT NULL DEFAULT 0"),
+    ("groups", "parent_id", "TEXT REFERENCES groups(group_id) ON DELETE CASCADE"),
+    ("groups", "inherits", "INTEGER NOT NULL DEFAULT 1"),
+];
+
+async fn add_missing_columns(pool: &SqlitePool) -> anyhow::Result<()> {
+    for (table, column, definition) in ADDED_COLUMNS {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?) WHERE name = ?)",
+        )
+        .bind(table)
+        .bind(column)
+        .fetch_one(pool)
+        .await?;
+        if !exists {
+            sqlx::raw_sql(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"))
+                .execute(pool)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
 /// One-time backfill: older builds indexed only p/e tags into `event_tags`, so
 /// generic `#x` filters would miss historical events. Re-derive the index to
 /// cover ALL single-letter tags, gated on `user_version` so it runs once (#69).
@@ -127,6 +304,33 @@ async fn backfill_event_tags(pool: &SqlitePool) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// One-time backfill: index music events stored before the catalog existed,
+/// gated on `user_version` 2.
+async fn backfill_music_catalog(pool: &SqlitePool) -> anyhow::Result<()> {
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await
+        .unwrap_or(0);
+    if version >= 2 {
+        return Ok(());
+    }
+    let rows: Vec<EventRow> = sqlx::query_as(
+        "SELECT id, pubkey, created_at, kind, tags, content, sig FROM events WHERE kind IN (?, ?, ?)",
+    )
+    .bind(KIND_TRACK)
+    .bind(KIND_ALBUM)
+    .bind(KIND_PLAYLIST)
+    .fetch_all(pool)
+    .await?;
+    let mut tx = pool.begin().await?;
+    for event in rows.into_iter().map(row_to_event) {
+        index_music(&mut tx, &event).await?;
+    }
+    sqlx::query("PRAGMA user_version = 2").execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
 /// Open a fresh in-memory database with the schema applied. Pinned to a single
 /// connection so the db persists across queries (each `:memory:` connection is
 /// otherwise an isolated database). For tests and ephemeral use.
@@ -137,6 +341,7 @@ pub async fn connect_memory() -> anyhow::Result<SqlitePool> {
         .await?;
     sqlx::raw_sql(SCHEMA).execute(&pool).await?;
     backfill_event_tags(&pool).await?;
+    backfill_music_catalog(&pool).await?;
     Ok(pool)
 }
 
@@ -144,7 +349,7 @@ fn is_replaceable(kind: i32) -> bool {
     kind == 0 || kind == 3 || (kind >= 10000 && kind < 20000)
 }
 fn is_addressable(kind: i32) -> bool {
-    (30000..40000).contains(&kind)
+    kind >= 30000 && kind < 40000
 }
 
 
@@ -179,9 +384,18 @@ pub async fn store_event(pool: &SqlitePool, event: &Event) -> anyhow::Result<boo
         }
     }
 
+    // Tombstones refuse the insert (same rules as the Postgres store): NIP-62
+    // vanish cutoff (vanish requests exempt), deleted ids, deleted coordinates,
+    // and NIP-86 banned ids.
     let inserted = sqlx::query(
         "INSERT OR IGNORE INTO events (id, pubkey, created_at, kind, tags, content, sig, d_tag, h_tag, visibility) \
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+         SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10 \
+         WHERE (?4 = 62 OR NOT EXISTS \
+                 (SELECT 1 FROM vanished_pubkeys WHERE pubkey = ?2 AND vanished_until >= ?3)) \
+           AND NOT EXISTS (SELECT 1 FROM deleted_events WHERE event_id = ?1 AND pubkey = ?2) \
+           AND NOT EXISTS (SELECT 1 FROM deleted_addresses \
+                           WHERE kind = ?4 AND pubkey = ?2 AND d_tag = ?8 AND deleted_until >= ?3) \
+           AND NOT EXISTS (SELECT 1 FROM banned_events WHERE event_id = ?1)",
     )
     .bind(&event.id)
     .bind(&event.pubkey)
@@ -213,12 +427,200 @@ pub async fn store_event(pool: &SqlitePool, event: &Event) -> anyhow::Result<boo
                 }
             }
         }
+        // What it adds to another event's aggregates; triggers update the
+        // counters.
+        if let Some(contribution) = aggregates::contribution(event) {
+            let (metric, key, amount) = contribution.columns();
+            sqlx::query(
+                "INSERT OR IGNORE INTO event_contributions (event_id, target_id, metric, key, amount, gated) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&event.id)
+            .bind(&contribution.target)
+            .bind(metric)
+            .bind(key)
+            .bind(amount)
+            .bind(aggregates::is_gated(event))
+            .execute(&mut *tx)
+            .await?;
+        }
+        index_music(&mut tx, event).await?;
+        tx.commit().await?;
     }
-
-    tx.commit().await?;
+    // Otherwise dropping `tx` rolls the delete back: a newer version refused
+    // by a tombstone leaves the current one in place.
     Ok(inserted)
 }
 
+/// Index a track, album or playlist in the music catalog.
+async fn index_music(tx: &mut sqlx::SqliteConnection, event: &Event) -> anyhow::Result<()> {
+    if let Some(entry) = CatalogEntry::from_event(event) {
+        sqlx::query(
+            "INSERT OR IGNORE INTO music_catalog (event_id, title, artist, genre, album, duration, released_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&event.id)
+        .bind(&entry.title)
+        .bind(&entry.artist)
+        .bind(&entry.genre)
+        .bind(&entry.album)
+        .bind(entry.duration)
+        .bind(entry.released_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for (position, coordinate) in catalog::refs(event) {
+        sqlx::query(
+            "INSERT OR IGNORE INTO music_refs (event_id, position, ref_kind, ref_pubkey, ref_d) \
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&event.id)
+        .bind(position)
+        .bind(coordinate.kind)
+        .bind(&coordinate.pubkey)
+        .bind(&coordinate.d)
+        .execute(&mut *tx)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Ids of the events answering a music catalog query, best first (release
+/// date, or the album's/playlist's order). Not visibility-gated: callers
+/// pass them through `query_events`.
+pub async fn music_catalog_ids(
+    pool: &SqlitePool,
+    query: &CatalogQuery,
+    kinds: &[i32],
+    authors: &[String],
+    limit: i64,
+    offset: i64,
+) -> anyhow::Result<Vec<String>> {
+    let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT t.id FROM ");
+    match &query.source {
+        Some(source) => {
+            qb.push(
+                "music_refs r \
+                 JOIN events src ON src.id = r.event_id \
+                 JOIN events t ON t.pubkey = r.ref_pubkey AND t.kind = r.ref_kind AND t.d_tag = r.ref_d \
+                 LEFT JOIN music_catalog c ON c.event_id = t.id \
+                 WHERE src.kind = ",
+            )
+            .push_bind(source.kind)
+            .push(" AND src.pubkey = ")
+            .push_bind(source.pubkey.clone())
+            .push(" AND src.d_tag = ")
+            .push_bind(source.d.clone());
+        }
+        None => {
+            qb.push("music_catalog c JOIN events t ON t.id = c.event_id WHERE 1 = 1");
+        }
+    }
+    if let Some(ref artist) = query.artist {
+        qb.push(" AND lower(c.artist) = lower(").push_bind(artist.clone()).push(")");
+    }
+    if let Some(ref genre) = query.genre {
+        qb.push(" AND c.genre = ").push_bind(genre.clone());
+    }
+    for word in &query.words {
+        qb.push(" AND lower(c.title) LIKE ")
+            .push_bind(catalog::contains_pattern(word))
+            .push(r" ESCAPE '\'");
+    }
+    push_in_i32(&mut qb, "t.kind", kinds);
+    push_in(&mut qb, "t.pubkey", authors);
+    qb.push(if query.source.is_some() {
+        " ORDER BY r.position"
+    } else {
+        " ORDER BY c.released_at DESC, t.id"
+    });
+    qb.push(" LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+    let ids: Vec<(String,)> = qb.build_query_as().fetch_all(pool).await?;
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
+/// An album's or playlist's entries with no event stored at their coordinate,
+/// in order. Entries whose address was tombstoned by a NIP-09 deletion are
+/// `removed`.
+pub async fn music_dangling_refs(pool: &SqlitePool, source: &Coordinate) -> anyhow::Result<Vec<DanglingRef>> {
+    let rows: Vec<(i64, i32, String, String, bool)> = sqlx::query_as(
+        "SELECT r.position, r.ref_kind, r.ref_pubkey, r.ref_d, \
+                EXISTS (SELECT 1 FROM deleted_addresses da \
+                        WHERE da.kind = r.ref_kind AND da.pubkey = r.ref_pubkey AND da.d_tag = r.ref_d) \
+         FROM music_refs r JOIN events src ON src.id = r.event_id \
+         WHERE src.kind = ? AND src.pubkey = ? AND src.d_tag = ? \
+           AND NOT EXISTS (SELECT 1 FROM events t \
+                           WHERE t.pubkey = r.ref_pubkey AND t.kind = r.ref_kind AND t.d_tag = r.ref_d) \
+         ORDER BY r.position",
+    )
+    .bind(source.kind)
+    .bind(&source.pubkey)
+    .bind(&source.d)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(position, kind, pubkey, d, removed)| DanglingRef {
+            position,
+            coordinate: Coordinate { kind, pubkey, d },
+            removed,
+        })
+        .collect())
+}
+
+/// The albums and playlists listing `target`.
+pub async fn music_dependents(pool: &SqlitePool, target: &Coordinate) -> anyhow::Result<Vec<Coordinate>> {
+    let rows: Vec<(i32, String, String)> = sqlx::query_as(
+        "SELECT DISTINCT src.kind, src.pubkey, src.d_tag \
+         FROM music_refs r JOIN events src ON src.id = r.event_id \
+         WHERE r.ref_kind = ? AND r.ref_pubkey = ? AND r.ref_d = ? AND src.d_tag IS NOT NULL",
+    )
+    .bind(target.kind)
+    .bind(&target.pubkey)
+    .bind(&target.d)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(kind, pubkey, d)| Coordinate { kind, pubkey, d }).collect())
+}
+
+/// Aggregate counters of `target_ids` as `(target, metric, key, count, total)`.
+pub async fn event_aggregates(
+    pool: &SqlitePool,
+    target_ids: &[String],
+) -> anyhow::Result<Vec<(String, String, String, i64, i64)>> {
+    if target_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut qb: QueryBuilder<Sqlite> =
+        QueryBuilder::new("SELECT target_id, metric, key, count, total FROM event_aggregates WHERE target_id IN (");
+    let mut sep = qb.separated(", ");
+    for id in target_ids {
+        sep.push_bind(id);
+    }
+    qb.push(") ORDER BY target_id, metric, key");
+    Ok(qb.build_query_as().fetch_all(pool).await?)
+}
+
+/// Gated contributions to `target_ids` as `(event, target, metric, key,
+/// amount)`, for the caller to count per reader.
+pub async fn gated_contributions(
+    pool: &SqlitePool,
+    target_ids: &[String],
+) -> anyhow::Result<Vec<(String, String, String, String, i64)>> {
+    if target_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT event_id, target_id, metric, key, amount FROM event_contributions WHERE gated AND target_id IN (",
+    );
+    let mut sep = qb.separated(", ");
+    for id in target_ids {
+        sep.push_bind(id);
+    }
+    qb.push(")");
+    Ok(qb.build_query_as().fetch_all(pool).await?)
+}
+
 #[derive(sqlx::FromRow)]
 struct EventRow {
     id: String,
@@ -248,10 +650,10 @@ fn row_to_event(r: EventRow) -> Event {
 pub async fn query_events(
     pool: &SqlitePool,
     filter: &Filter,
-    authed_pubkey: Option<&str>,
+    authed_pubkeys: &[String],
 ) -> anyhow::Result<Vec<Event>> {
     if let Some(ref q) = filter.search {
-        return search_events(pool, q, filter.limit.unwrap_or(100), authed_pubkey).await;
+        return search_events(pool, q, filter.limit.unwrap_or(100), authed_pubkeys).await;
     }
 
     let mut qb: QueryBuilder<Sqlite> =
@@ -275,7 +677,7 @@ pub async fn query_events(
         qb.push(" AND created_at <= ").push_bind(until);
     }
 
-    push_visibility_gate(&mut qb, authed_pubkey);
+    push_visibility_gate(&mut qb, "", authed_pubkeys);
 
     // Clamp to [0, MAX] so a negative limit can't return the whole table (#70).
     let limit = filter.limit.unwrap_or(MAX_QUERY_LIMIT).clamp(0, MAX_QUERY_LIMIT);
@@ -286,27 +688,59 @@ pub async fn query_events(
 }
 
 /// Append the visibility/membership predicates to a query over the `events`
-/// table (or an aliased copy via `col_prefix`, e.g. "e.").
-fn push_visibility_gate(qb: &mut QueryBuilder<Sqlite>, authed_pubkey: Option<&str>) {
-    match authed_pubkey {
-        Some(pk) => {
-            // private/unlisted: author or p-tagged collaborator
-            qb.push(" AND (visibility IS NULL OR pubkey = ")
-                .push_bind(pk.to_string())
-                .push(" OR id IN (SELECT event_id FROM event_tags WHERE tag_name = 'p' AND tag_value = ")
-                .push_bind(pk.to_string())
-                .push("))");
-            // h-tagged: author or relay-native group member
-            qb.push(" AND (h_tag IS NULL OR pubkey = ")
-                .push_bind(pk.to_string())
-                .push(" OR EXISTS (SELECT 1 FROM group_members WHERE group_id = events.h_tag AND pubkey = ")
-                .push_bind(pk.to_string())
-                .push("))");
-        }
-        None => {
-            qb.push(" AND visibility IS NULL AND h_tag IS NULL");
-        }
+/// table (or an aliased copy via `col_prefix`, e.g. "e."). An event passes if
+/// it is visible to ANY of `authed_pubkeys`; empty means anonymous.
+fn push_visibility_gate(qb: &mut QueryBuilder<Sqlite>, col_prefix: &str, authed_pubkeys: &[String]) {
+    let table = if col_prefix.is_empty() { "events." } else { col_prefix };
+    if authed_pubkeys.is_empty() {
+        qb.push(format!(
+            " AND {col_prefix}visibility IS NULL AND {col_prefix}h_tag IS NULL \
+             AND {col_prefix}kind NOT IN {RECIPIENT_ONLY_KINDS_SQL} \
+             AND ({col_prefix}kind <> {KIND_REPORT} \
+                  OR NOT EXISTS (SELECT 1 FROM reports r WHERE r.report_id = {table}id))"
+        ));
+        return;
+    }
+    // private/unlisted: author or p-tagged collaborator
+    qb.push(format!(" AND ({col_prefix}visibility IS NULL OR {col_prefix}pubkey IN "));
+    push_list(qb, authed_pubkeys);
+    qb.push(format!(
+        " OR {col_prefix}id IN (SELECT event_id FROM event_tags WHERE tag_name = 'p' AND tag_value IN "
+    ));
+    push_list(qb, authed_pubkeys);
+    qb.push("))");
+    // h-tagged: author or relay-native group member (sub-groups inherit)
+    qb.push(format!(" AND ({col_prefix}h_tag IS NULL OR {col_prefix}pubkey IN "));
+    push_list(qb, authed_pubkeys);
+    qb.push(format!(" OR {table}h_tag IN ("));
+    super::sqlite_groups::push_member_groups(qb, authed_pubkeys);
+    qb.push("))");
+    // gift wraps: p-tagged recipient only, never the (throwaway) author
+    qb.push(format!(
+        " AND ({col_prefix}kind NOT IN {RECIPIENT_ONLY_KINDS_SQL} \
+         OR {col_prefix}id IN (SELECT event_id FROM event_tags WHERE tag_name = 'p' AND tag_value IN "
+    ));
+    push_list(qb, authed_pubkeys);
+    qb.push("))");
+    // group reports (NIP-56): the reporter or a moderator of a group it was filed in
+    qb.push(format!(" AND ({col_prefix}kind <> {KIND_REPORT} OR {col_prefix}pubkey IN "));
+    push_list(qb, authed_pubkeys);
+    qb.push(format!(
+        " OR NOT EXISTS (SELECT 1 FROM reports r WHERE r.report_id = {table}id) \
+         OR EXISTS (SELECT 1 FROM reports r WHERE r.report_id = {table}id AND r.group_id IN ("
+    ));
+    super::sqlite_groups::push_moderated_groups(qb, authed_pubkeys);
+    qb.push(")))");
+}
+
+/// `(?, ?, …)` bound list for an `IN` predicate. Callers guarantee non-empty.
+fn push_list(qb: &mut QueryBuilder<Sqlite>, values: &[String]) {
+    qb.push("(");
+    let mut sep = qb.separated(", ");
+    for v in values {
+        sep.push_bind(v.clone());
     }
+    qb.push(")");
 }
 
 fn push_in(qb: &mut QueryBuilder<Sqlite>, col: &str, values: &[String]) {
@@ -353,30 +787,14 @@ pub async fn search_events(
     pool: &SqlitePool,
     query: &str,
     limit: i64,
-    authed_pubkey: Option<&str>,
+    authed_pubkeys: &[String],
 ) -> anyhow::Result<Vec<Event>> {
     let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new(
         "SELECT e.id, e.pubkey, e.created_at, e.kind, e.tags, e.content, e.sig \
          FROM events_fts f JOIN events e ON e.rowid = f.rowid WHERE events_fts MATCH ",
     );
     qb.push_bind(query.to_string());
-    match authed_pubkey {
-        Some(pk) => {
-            qb.push(" AND (e.visibility IS NULL OR e.pubkey = ")
-                .push_bind(pk.to_string())
-                .push(" OR e.id IN (SELECT event_id FROM event_tags WHERE tag_name = 'p' AND tag_value = ")
-                .push_bind(pk.to_string())
-                .push("))");
-            qb.push(" AND (e.h_tag IS NULL OR e.pubkey = ")
-                .push_bind(pk.to_string())
-                .push(" OR EXISTS (SELECT 1 FROM group_members WHERE group_id = e.h_tag AND pubkey = ")
-                .push_bind(pk.to_string())
-                .push("))");
-        }
-        None => {
-            qb.push(" AND e.visibility IS NULL AND e.h_tag IS NULL");
-        }
-    }
+    push_visibility_gate(&mut qb, "e.", authed_pubkeys);
     qb.push(" ORDER BY bm25(events_fts) LIMIT ").push_bind(limit.clamp(0, MAX_QUERY_LIMIT));
     let rows: Vec<EventRow> = qb.build_query_as().fetch_all(pool).await?;
     Ok(rows.into_iter().map(row_to_event).collect())
@@ -409,6 +827,17 @@ pub async fn count_groups(pool: &SqlitePool) -> anyhow::Result<i64> {
     Ok(n)
 }
 
+/// Ids of the `limit` newest events tagged into `group_id`, newest first.
+pub async fn recent_group_event_ids(pool: &SqlitePool, group_id: &str, limit: i64) -> anyhow::Result<Vec<String>> {
+    let ids: Vec<(String,)> =
+        sqlx::query_as("SELECT id FROM events WHERE h_tag = ? ORDER BY created_at DESC, id LIMIT ?")
+            .bind(group_id)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
 /// Delete an event by id.
 pub async fn delete_event(pool: &SqlitePool, event_id: &str) -> anyhow::Result<bool> {
     let r = sqlx::query("DELETE FROM events WHERE id = ?")
@@ -456,6 +885,116 @@ pub async fn delete_addressable_upto(
     Ok(r.rows_affected())
 }
 
+/// Tombstone a deleted event id (mirrors `event_store::tombstone_event`).
+pub async fn tombstone_event(pool: &SqlitePool, event_id: &str, pubkey: &str) -> anyhow::Result<()> {
+    sqlx::query("INSERT OR IGNORE INTO deleted_events (event_id, pubkey) VALUES (?, ?)")
+        .bind(event_id)
+        .bind(pubkey)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Tombstone a deleted addressable coordinate up to `until` (latest wins).
+pub async fn tombstone_address(
+    pool: &SqlitePool,
+    kind: i32,
+    pubkey: &str,
+    d_tag: &str,
+    until: i64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO deleted_addresses (kind, pubkey, d_tag, deleted_until) VALUES (?, ?, ?, ?) \
+         ON CONFLICT (kind, pubkey, d_tag) DO UPDATE \
+         SET deleted_until = MAX(deleted_until, excluded.deleted_until)",
+    )
+    .bind(kind)
+    .bind(pubkey)
+    .bind(d_tag)
+    .bind(until)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Is `event` covered by a deletion tombstone (by id or by coordinate)?
+pub async fn is_tombstoned(pool: &SqlitePool, event: &Event) -> anyhow::Result<bool> {
+    let d_tag = event.get_tag_value("d");
+    let (hit,): (bool,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM deleted_events WHERE event_id = ?1 AND pubkey = ?2) \
+             OR EXISTS (SELECT 1 FROM deleted_addresses \
+                        WHERE kind = ?3 AND pubkey = ?2 AND d_tag = ?4 AND deleted_until >= ?5)",
+    )
+    .bind(&event.id)
+    .bind(&event.pubkey)
+    .bind(event.kind)
+    .bind(&d_tag)
+    .bind(event.created_at)
+    .fetch_one(pool)
+    .await?;
+    Ok(hit)
+}
+
+/// NIP-62 vanish (mirrors `event_store::vanish`): tombstone, delete the
+/// pubkey's events up to `until` and gift wraps addressed to them, drop their
+/// group memberships/roles and queued reports. Returns (events deleted,
+/// groups left).
+pub async fn vanish(pool: &SqlitePool, pubkey: &str, until: i64) -> anyhow::Result<(u64, Vec<String>)> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO vanished_pubkeys (pubkey, vanished_until) VALUES (?, ?) \
+         ON CONFLICT (pubkey) DO UPDATE SET vanished_until = MAX(vanished_until, excluded.vanished_until)",
+    )
+    .bind(pubkey)
+    .bind(until)
+    .execute(&mut *tx)
+    .await?;
+
+    let own = sqlx::query("DELETE FROM events WHERE pubkey = ? AND created_at <= ?")
+        .bind(pubkey)
+        .bind(until)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    let wraps = sqlx::query(&format!(
+        "DELETE FROM events WHERE kind IN {RECIPIENT_ONLY_KINDS_SQL} \
+         AND id IN (SELECT event_id FROM event_tags WHERE tag_name = 'p' AND tag_value = ?)"
+    ))
+    .bind(pubkey)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let groups: Vec<(String,)> =
+        sqlx::query_as("DELETE FROM group_members WHERE pubkey = ? RETURNING group_id")
+            .bind(pubkey)
+            .fetch_all(&mut *tx)
+            .await?;
+    sqlx::query("DELETE FROM group_roles WHERE pubkey = ?")
+        .bind(pubkey)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM reports WHERE reporter = ? AND created_at <= ?")
+        .bind(pubkey)
+        .bind(until)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok((own + wraps, groups.into_iter().map(|g| g.0).collect()))
+}
+
+/// The NIP-62 cutoff recorded for `pubkey`, if it has vanished.
+pub async fn vanished_until(pool: &SqlitePool, pubkey: &str) -> anyhow::Result<Option<i64>> {
+    let row: Option<(i64,)> =
+        sqlx::query_as("SELECT vanished_until FROM vanished_pubkeys WHERE pubkey = ?")
+            .bind(pubkey)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map(|r| r.0))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -494,16 +1033,16 @@ mod tests {
         assert!(store_event(&p, &ev("a", "alice", 1, 100, vec![], "hi")).await.unwrap());
         assert!(store_event(&p, &ev("b", "bob", 1, 101, vec![], "yo")).await.unwrap());
 
-        let by_id = query_events(&p, &filter(serde_json::json!({"ids": ["a"]})), None).await.unwrap();
+        let by_id = query_events(&p, &filter(serde_json::json!({"ids": ["a"]})), &[]).await.unwrap();
         assert_eq!(by_id.len(), 1);
         assert_eq!(by_id[0].id, "a");
 
-        let by_author = query_events(&p, &filter(serde_json::json!({"authors": ["bob"]})), None).await.unwrap();
+        let by_author = query_events(&p, &filter(serde_json::json!({"authors": ["bob"]})), &[]).await.unwrap();
         assert_eq!(by_author.len(), 1);
         assert_eq!(by_author[0].pubkey, "bob");
 
         // Ordered newest-first.
-        let by_kind = query_events(&p, &filter(serde_json::json!({"kinds": [1]})), None).await.unwrap();
+        let by_kind = query_events(&p, &filter(serde_json::json!({"kinds": [1]})), &[]).await.unwrap();
         assert_eq!(by_kind.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), vec!["b", "a"]);
     }
 
@@ -512,7 +1051,7 @@ mod tests {
         let p = pool().await;
         assert!(store_event(&p, &ev("a", "alice", 1, 100, vec![], "hi")).await.unwrap());
         assert!(!store_event(&p, &ev("a", "alice", 1, 100, vec![], "hi")).await.unwrap());
-        let rows = query_events(&p, &filter(serde_json::json!({"ids": ["a"]})), None).await.unwrap();
+        let rows = query_events(&p, &filter(serde_json::json!({"ids": ["a"]})), &[]).await.unwrap();
         assert_eq!(rows.len(), 1);
     }
 
@@ -525,7 +1064,7 @@ mod tests {
         // An older one must be rejected (unique index conflict after no-op delete).
         assert!(!store_event(&p, &ev("v3", "alice", 0, 50, vec![], "older")).await.unwrap());
 
-        let rows = query_events(&p, &filter(serde_json::json!({"kinds": [0]})), None).await.unwrap();
+        let rows = query_events(&p, &filter(serde_json::json!({"kinds": [0]})), &[]).await.unwrap();
         assert_eq!(rows.len(), 1);
         assert_eq!(rows[0].content, "new");
     }
@@ -538,10 +1077,10 @@ mod tests {
         // Different d-tag coexists.
         store_event(&p, &ev("a3", "alice", 30023, 150, vec![vec!["d", "other"]], "second")).await.unwrap();
 
-        let rows = query_events(&p, &filter(serde_json::json!({"kinds": [30023], "#d": ["post"]})), None).await.unwrap();
+        let rows = query_events(&p, &filter(serde_json::json!({"kinds": [30023], "#d": ["post"]})), &[]).await.unwrap();
         assert_eq!(rows.len(), 1);
         assert_eq!(rows[0].content, "final");
-        let all = query_events(&p, &filter(serde_json::json!({"kinds": [30023]})), None).await.unwrap();
+        let all = query_events(&p, &filter(serde_json::json!({"kinds": [30023]})), &[]).await.unwrap();
         assert_eq!(all.len(), 2);
     }
 
@@ -553,25 +1092,36 @@ mod tests {
 
         // Read as the author (alice) so the group-scoped events are visible —
         // the tag filters, not visibility, are under test here.
-        let h = query_events(&p, &filter(serde_json::json!({"#h": ["groupA"]})), Some("alice")).await.unwrap();
+        let h = query_events(&p, &filter(serde_json::json!({"#h": ["groupA"]})), &["alice".into()]).await.unwrap();
         assert_eq!(h.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), vec!["c1"]);
 
-        let p_tag = query_events(&p, &filter(serde_json::json!({"#p": ["bob"]})), Some("alice")).await.unwrap();
+        let p_tag = query_events(&p, &filter(serde_json::json!({"#p": ["bob"]})), &["alice".into()]).await.unwrap();
         assert_eq!(p_tag.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), vec!["c1"]);
 
-        let e_tag = query_events(&p, &filter(serde_json::json!({"#e": ["evt1"]})), Some("alice")).await.unwrap();
+        let e_tag = query_events(&p, &filter(serde_json::json!({"#e": ["evt1"]})), &["alice".into()]).await.unwrap();
         assert_eq!(e_tag.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), vec!["c2"]);
     }
 
+    #[tokio::test]
+    async fn recent_group_event_ids_newest_first() {
+        let p = pool().await;
+        for i in 0..4 {
+            store_event(&p, &ev(&format!("g{i}"), "alice", 9, 100 + i, vec![vec!["h", "groupA"]], "x")).await.unwrap();
+        }
+        store_event(&p, &ev("other", "alice", 9, 200, vec![vec!["h", "groupB"]], "x")).await.unwrap();
+        assert_eq!(recent_group_event_ids(&p, "groupA", 2).await.unwrap(), vec!["g3", "g2"]);
+        assert!(recent_group_event_ids(&p, "none", 10).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn since_until_and_limit() {
         let p = pool().await;
         for i in 0..10 {
             store_event(&p, &ev(&format!("e{i}"), "alice", 1, 100 + i, vec![], "x")).await.unwrap();
         }
-        let windowed = query_events(&p, &filter(serde_json::json!({"since": 103, "until": 105})), None).await.unwrap();
+        let windowed = query_events(&p, &filter(serde_json::json!({"since": 103, "until": 105})), &[]).await.unwrap();
         assert_eq!(windowed.len(), 3); // 103, 104, 105
-        let limited = query_events(&p, &filter(serde_json::json!({"kinds": [1], "limit": 2})), None).await.unwrap();
+        let limited = query_events(&p, &filter(serde_json::json!({"kinds": [1], "limit": 2})), &[]).await.unwrap();
         assert_eq!(limited.len(), 2);
     }
 
@@ -585,10 +1135,10 @@ mod tests {
         store_event(&p, &ev("m1", "alice", 9, 100, vec![vec!["h", "g"]], "secret")).await.unwrap();
 
         let f = || filter(serde_json::json!({"kinds": [9]}));
-        assert_eq!(query_events(&p, &f(), None).await.unwrap().len(), 0, "anon must not read group content");
-        assert_eq!(query_events(&p, &f(), Some("carol")).await.unwrap().len(), 0, "stranger must not read");
-        assert_eq!(query_events(&p, &f(), Some("bob")).await.unwrap().len(), 1, "member reads");
-        assert_eq!(query_events(&p, &f(), Some("alice")).await.unwrap().len(), 1, "author reads");
+        assert_eq!(query_events(&p, &f(), &[]).await.unwrap().len(), 0, "anon must not read group content");
+        assert_eq!(query_events(&p, &f(), &["carol".into()]).await.unwrap().len(), 0, "stranger must not read");
+        assert_eq!(query_events(&p, &f(), &["bob".into()]).await.unwrap().len(), 1, "member reads");
+        assert_eq!(query_events(&p, &f(), &["alice".into()]).await.unwrap().len(), 1, "author reads");
     }
 
     #[tokio::test]
@@ -598,10 +1148,89 @@ mod tests {
         let p = pool().await;
         store_event(&p, &ev("pv", "alice", 1, 100, vec![vec!["visibility", "private"], vec!["p", "bob"]], "dm")).await.unwrap();
         let f = || filter(serde_json::json!({"kinds": [1]}));
-        assert_eq!(query_events(&p, &f(), None).await.unwrap().len(), 0, "anon hidden");
-        assert_eq!(query_events(&p, &f(), Some("carol")).await.unwrap().len(), 0, "stranger hidden");
-        assert_eq!(query_events(&p, &f(), Some("bob")).await.unwrap().len(), 1, "collaborator reads");
-        assert_eq!(query_events(&p, &f(), Some("alice")).await.unwrap().len(), 1, "author reads");
+        assert_eq!(query_events(&p, &f(), &[]).await.unwrap().len(), 0, "anon hidden");
+        assert_eq!(query_events(&p, &f(), &["carol".into()]).await.unwrap().len(), 0, "stranger hidden");
+        assert_eq!(query_events(&p, &f(), &["bob".into()]).await.unwrap().len(), 1, "collaborator reads");
+        assert_eq!(query_events(&p, &f(), &["alice".into()]).await.unwrap().len(), 1, "author reads");
+    }
+
+    #[tokio::test]
+    async fn visibility_gating_unions_several_authed_pubkeys() {
+        // A multi-account connection sees what ANY of its authed pubkeys may see.
+        let p = pool().await;
+        sqlx::query("INSERT INTO groups (group_id, name) VALUES ('g', 'G')").execute(&p).await.unwrap();
+        sqlx::query("INSERT INTO group_members (group_id, pubkey) VALUES ('g', 'bob')").execute(&p).await.unwrap();
+        store_event(&p, &ev("m1", "alice", 9, 100, vec![vec!["h", "g"]], "group")).await.unwrap();
+        store_event(&p, &ev("pv", "alice", 1, 101, vec![vec!["visibility", "private"], vec!["p", "dave"]], "dm")).await.unwrap();
+
+        let f = || filter(serde_json::json!({"kinds": [1, 9]}));
+        assert_eq!(query_events(&p, &f(), &["carol".into()]).await.unwrap().len(), 0);
+        let both = query_events(&p, &f(), &["carol".into(), "bob".into(), "dave".into()]).await.unwrap();
+        assert_eq!(both.len(), 2, "member of g + p-tagged collaborator");
+        let hits = search_events(&p, "group", 50, &["carol".into(), "bob".into()]).await.unwrap();
+        assert_eq!(hits.len(), 1, "search unions too");
+    }
+
+    #[tokio::test]
+    async fn gift_wraps_only_reach_their_recipient() {
+        let p = pool().await;
+        store_event(&p, &ev("gw", "throwaway", 1059, 100, vec![vec!["p", "bob"]], "sealed")).await.unwrap();
+        let f = || filter(serde_json::json!({"kinds": [1059]}));
+        assert_eq!(query_events(&p, &f(), &[]).await.unwrap().len(), 0, "anon hidden");
+        assert_eq!(query_events(&p, &f(), &["carol".into()]).await.unwrap().len(), 0, "stranger hidden");
+        assert_eq!(query_events(&p, &f(), &["throwaway".into()]).await.unwrap().len(), 0, "author hidden");
+        assert_eq!(query_events(&p, &f(), &["carol".into(), "bob".into()]).await.unwrap().len(), 1, "recipient reads");
+        assert_eq!(search_events(&p, "sealed", 50, &["throwaway".into()]).await.unwrap().len(), 0, "search gated");
+    }
+
+    #[tokio::test]
+    async fn vanish_purges_and_blocks_older_events() {
+        let p = pool().await;
+        sqlx::query("INSERT INTO groups (group_id, name) VALUES ('g', 'G')").execute(&p).await.unwrap();
+        sqlx::query("INSERT INTO group_members (group_id, pubkey) VALUES ('g', 'alice')").execute(&p).await.unwrap();
+        sqlx::query("INSERT INTO group_roles (group_id, pubkey, role) VALUES ('g', 'alice', 'admin')").execute(&p).await.unwrap();
+        store_event(&p, &ev("n1", "alice", 1, 100, vec![], "old note")).await.unwrap();
+        store_event(&p, &ev("gw", "throwaway", 1059, 50, vec![vec!["p", "alice"]], "wrap")).await.unwrap();
+        store_event(&p, &ev("n2", "bob", 1, 100, vec![], "bob stays")).await.unwrap();
+
+        let (deleted, groups) = vanish(&p, "alice", 200).await.unwrap();
+        assert_eq!(deleted, 2, "own note + gift wrap to alice");
+        assert_eq!(groups, vec!["g".to_string()]);
+        assert_eq!(vanished_until(&p, "alice").await.unwrap(), Some(200));
+        let (roles,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM group_roles").fetch_one(&p).await.unwrap();
+        assert_eq!(roles, 0);
+
+        // Re-broadcast of the old note is refused; the vanish request and
+        // anything newer than the cutoff are accepted.
+        assert!(!store_event(&p, &ev("n1", "alice", 1, 100, vec![], "old note")).await.unwrap());
+        assert!(store_event(&p, &ev("v", "alice", 62, 200, vec![vec!["relay", "ALL_RELAYS"]], "")).await.unwrap());
+        assert!(store_event(&p, &ev("n3", "alice", 1, 201, vec![], "back")).await.unwrap());
+        // An older cutoff never lowers the tombstone.
+        vanish(&p, "alice", 150).await.unwrap();
+        assert_eq!(vanished_until(&p, "alice").await.unwrap(), Some(200));
+        assert_eq!(count_events(&p).await.unwrap(), 3, "bob's note, the request, alice's new note");
+    }
+
+    #[tokio::test]
+    async fn tombstones_block_reinsertion() {
+        let p = pool().await;
+        let note = ev("n1", "alice", 1, 100, vec![], "oops");
+        store_event(&p, &note).await.unwrap();
+        delete_event(&p, "n1").await.unwrap();
+        tombstone_event(&p, "n1", "alice").await.unwrap();
+        assert!(!store_event(&p, &note).await.unwrap(), "deleted id re-inserted");
+        assert!(is_tombstoned(&p, &note).await.unwrap());
+        // A tombstone recorded under another author blocks nothing.
+        tombstone_event(&p, "n2", "mallory").await.unwrap();
+        assert!(store_event(&p, &ev("n2", "alice", 1, 100, vec![], "mine")).await.unwrap());
+
+        let old = ev("a1", "alice", 30023, 100, vec![vec!["d", "post"]], "v1");
+        store_event(&p, &old).await.unwrap();
+        delete_addressable_upto(&p, 30023, "alice", "post", 150).await.unwrap();
+        tombstone_address(&p, 30023, "alice", "post", 150).await.unwrap();
+        assert!(!store_event(&p, &old).await.unwrap(), "deleted coordinate re-inserted");
+        // A newer version supersedes the deletion.
+        assert!(store_event(&p, &ev("a2", "alice", 30023, 200, vec![vec!["d", "post"]], "v2")).await.unwrap());
     }
 
     #[tokio::test]
@@ -611,7 +1240,7 @@ mod tests {
         for i in 0..5 {
             store_event(&p, &ev(&format!("n{i}"), "alice", 1, 100 + i, vec![], "x")).await.unwrap();
         }
-        let rows = query_events(&p, &filter(serde_json::json!({"kinds": [1], "limit": -1})), None).await.unwrap();
+        let rows = query_events(&p, &filter(serde_json::json!({"kinds": [1], "limit": -1})), &[]).await.unwrap();
         assert!(rows.len() <= MAX_QUERY_LIMIT as usize, "negative limit bypassed the cap");
         assert_eq!(rows.len(), 0, "negative limit clamps to 0");
     }
@@ -622,9 +1251,9 @@ mod tests {
         let p = pool().await;
         sqlx::query("INSERT INTO groups (group_id, name) VALUES ('g', 'G')").execute(&p).await.unwrap();
         store_event(&p, &ev("sg", "alice", 9, 100, vec![vec!["h", "g"]], "quick brown fox")).await.unwrap();
-        let anon = search_events(&p, "fox", 50, None).await.unwrap();
+        let anon = search_events(&p, "fox", 50, &[]).await.unwrap();
         assert_eq!(anon.len(), 0, "anon search must not surface group content");
-        let author = search_events(&p, "fox", 50, Some("alice")).await.unwrap();
+        let author = search_events(&p, "fox", 50, &["alice".into()]).await.unwrap();
         assert_eq!(author.len(), 1, "author search finds it");
     }
 
@@ -635,10 +1264,10 @@ mod tests {
         store_event(&p, &ev("g1", "alice", 1, 100, vec![vec!["a", "31683:pk:album"], vec!["t", "nostr"]], "x")).await.unwrap();
         store_event(&p, &ev("g2", "alice", 1, 101, vec![vec!["t", "other"]], "y")).await.unwrap();
 
-        let by_a = query_events(&p, &filter(serde_json::json!({"#a": ["31683:pk:album"]})), None).await.unwrap();
+        let by_a = query_events(&p, &filter(serde_json::json!({"#a": ["31683:pk:album"]})), &[]).await.unwrap();
         assert_eq!(by_a.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), vec!["g1"]);
 
-        let by_t = query_events(&p, &filter(serde_json::json!({"#t": ["nostr"]})), None).await.unwrap();
+        let by_t = query_events(&p, &filter(serde_json::json!({"#t": ["nostr"]})), &[]).await.unwrap();
         assert_eq!(by_t.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), vec!["g1"]);
 
         // displacement guard: a tagged event isn't lost behind a low limit of
@@ -646,7 +1275,7 @@ mod tests {
         for i in 0..20 {
             store_event(&p, &ev(&format!("u{i}"), "alice", 1, 200 + i, vec![], "z")).await.unwrap();
         }
-        let tagged = query_events(&p, &filter(serde_json::json!({"#t": ["nostr"], "limit": 100})), None).await.unwrap();
+        let tagged = query_events(&p, &filter(serde_json::json!({"#t": ["nostr"], "limit": 100})), &[]).await.unwrap();
         assert!(tagged.iter().any(|e| e.id == "g1"));
     }
 
@@ -656,13 +1285,70 @@ mod tests {
         store_event(&p, &ev("s1", "alice", 1, 100, vec![], "the quick brown fox")).await.unwrap();
         store_event(&p, &ev("s2", "alice", 1, 101, vec![], "lazy dog sleeps")).await.unwrap();
 
-        let hits = query_events(&p, &filter(serde_json::json!({"search": "fox"})), None).await.unwrap();
+        let hits = query_events(&p, &filter(serde_json::json!({"search": "fox"})), &[]).await.unwrap();
         assert_eq!(hits.len(), 1);
         assert_eq!(hits[0].id, "s1");
 
         // FTS index stays consistent after a delete.
         delete_event(&p, "s1").await.unwrap();
-        let after = query_events(&p, &filter(serde_json::json!({"search": "fox"})), None).await.unwrap();
+        let after = query_events(&p, &filter(serde_json::json!({"search": "fox"})), &[]).await.unwrap();
         assert_eq!(after.len(), 0);
     }
+
+    #[tokio::test]
+    async fn adds_columns_missing_from_an_older_schema() {
+        let p = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        // `groups` as created by a build that predates `min_pow`.
+        sqlx::raw_sql("CREATE TABLE groups (group_id TEXT PRIMARY KEY, name TEXT NOT NULL DEFAULT ''); \
+                       INSERT INTO groups (group_id) VALUES ('g');")
+            .execute(&p)
+            .await
+            .unwrap();
+        sqlx::raw_sql(SCHEMA).execute(&p).await.unwrap();
+        add_missing_columns(&p).await.unwrap();
+        add_missing_columns(&p).await.unwrap();
+        let min_pow: i64 = sqlx::query_scalar("SELECT min_pow FROM groups WHERE group_id = 'g'")
+            .fetch_one(&p)
+            .await
+            .unwrap();
+        assert_eq!(min_pow, 0);
+    }
+
+    #[tokio::test]
+    async fn music_catalog_indexes_and_resolves_releases() {
+        let p = pool().await;
+        let artist = "aa00000000000000000000000000000000000000000000000000000000000000";
+        let catalog = |search: String| CatalogQuery::from_filter(&filter(serde_json::json!({ "search": search }))).unwrap();
+        let lp = format!("33123:{artist}:lp");
+        let track = |id: &str, d: &str, title: &str, genre: &str, at: i64| {
+            ev(id, artist, 31683, at, vec![vec!["d", d], vec!["title", title], vec!["artist", "Someone"],
+                vec!["genre", genre], vec!["a", lp.as_str()]], "")
+        };
+        store_event(&p, &track("t1", "one", "Opening", "Ambient", 10)).await.unwrap();
+        store_event(&p, &track("t2", "two", "Closing Time", "techno", 20)).await.unwrap();
+        let (one, two) = (format!("31683:{artist}:one"), format!("31683:{artist}:two"));
+        store_event(&p, &ev("lp1", artist, 33123, 30, vec![vec!["d", "lp"], vec!["title", "LP"],
+            vec!["a", two.as_str()], vec!["a", one.as_str()]], "")).await.unwrap();
+
+        let by_artist = music_catalog_ids(&p, &catalog("artist:someone".into()), &[31683], &[], 50, 0).await.unwrap();
+        assert_eq!(by_artist, vec!["t2", "t1"]);
+        let by_genre = music_catalog_ids(&p, &catalog("genre:AMBIENT".into()), &[], &[], 50, 0).await.unwrap();
+        assert_eq!(by_genre, vec!["t1"]);
+        let titled = music_catalog_ids(&p, &catalog("artist:Someone time".into()), &[], &[], 50, 0).await.unwrap();
+        assert_eq!(titled, vec!["t2"]);
+        // The album resolves to its tracks in its own order.
+        let tracklist = music_catalog_ids(&p, &catalog(format!("album:{lp}")), &[], &[], 50, 0).await.unwrap();
+        assert_eq!(tracklist, vec!["t2", "t1"]);
+
+        // A newer version replaces the old one in the catalog.
+        store_event(&p, &track("t1b", "one", "Opening", "drone", 40)).await.unwrap();
+        let by_genre = music_catalog_ids(&p, &catalog("genre:ambient".into()), &[], &[], 50, 0).await.unwrap();
+        assert!(by_genre.is_empty());
+        let tracklist = music_catalog_ids(&p, &catalog(format!("album:{lp}")), &[], &[], 50, 0).await.unwrap();
+        assert_eq!(tracklist, vec!["t2", "t1b"]);
+    }
 }
@@ -17,6 +17,12 @@
 use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 use std::collections::HashSet;
 
+use crate::nostr::event::Event;
+use crate::nostr::nip29::audit::ModerationAction;
+use crate::nostr::nip29::policy::GroupPolicy;
+use crate::nostr::nip29::subgroups::MAX_DEPTH;
+use crate::nostr::nip56::FiledReport;
+
 /// Create a new NIP-29 group; the creator becomes a member + admin.
 pub async fn create_group(
     pool: &SqlitePool,
@@ -59,6 +65,14 @@ pub async fn group_exists(pool: &SqlitePool, group_id: &str) -> anyhow::Result<b
     Ok(row.is_some())
 }
 
+/// Every group id.
+pub async fn group_ids(pool: &SqlitePool) -> anyhow::Result<Vec<String>> {
+    let rows: Vec<(String,)> = sqlx::query_as("SELECT group_id FROM groups ORDER BY group_id")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
 /// Update group metadata, only overwriting fields that are `Some` (COALESCE
 /// parity with the Postgres 9002 edit-metadata path).
 pub async fn set_metadata(
@@ -84,6 +98,74 @@ pub async fn set_metadata(
     Ok(())
 }
 
+/// The group's NIP-13 minimum difficulty (0 = none, or no such group).
+pub async fn min_pow(pool: &SqlitePool, group_id: &str) -> anyhow::Result<u32> {
+    let row: Option<(i64,)> = sqlx::query_as("SELECT min_pow FROM groups WHERE group_id = ?")
+        .bind(group_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map_or(0, |r| r.0.max(0) as u32))
+}
+
+/// Set the group's NIP-13 minimum difficulty.
+pub async fn set_min_pow(pool: &SqlitePool, group_id: &str, bits: u32) -> anyhow::Result<()> {
+    sqlx::query("UPDATE groups SET min_pow = ? WHERE group_id = ?")
+        .bind(bits as i64)
+        .bind(group_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The group's slow-mode interval in seconds (0 = off, or no such group).
+pub async fn slow_mode(pool: &SqlitePool, group_id: &str) -> anyhow::Result<u32> {
+    let row: Option<(i64,)> = sqlx::query_as("SELECT slow_mode_secs FROM groups WHERE group_id = ?")
+        .bind(group_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map_or(0, |r| r.0.max(0) as u32))
+}
+
+/// Set the group's slow-mode interval in seconds.
+pub async fn set_slow_mode(pool: &SqlitePool, group_id: &str, secs: u32) -> anyhow::Result<()> {
+    sqlx::query("UPDATE groups SET slow_mode_secs = ? WHERE group_id = ?")
+        .bind(secs as i64)
+        .bind(group_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The group's write/read policy (the default for no such group).
+/// `allowed_kinds` is a JSON array.
+pub async fn policy(pool: &SqlitePool, group_id: &str) -> anyhow::Result<GroupPolicy> {
+    let row: Option<(i64, String, i64)> = sqlx::query_as(
+        "SELECT announcement, allowed_kinds, restricted FROM groups WHERE group_id = ?",
+    )
+    .bind(group_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map_or_else(GroupPolicy::default, |(announcement, allowed_kinds, restricted)| {
+        GroupPolicy {
+            announcement: announcement != 0,
+            allowed_kinds: serde_json::from_str(&allowed_kinds).unwrap_or_default(),
+            restricted: restricted != 0,
+        }
+    }))
+}
+
+/// Replace the group's write/read policy.
+pub async fn set_policy(pool: &SqlitePool, group_id: &str, policy: &GroupPolicy) -> anyhow::Result<()> {
+    sqlx::query("UPDATE groups SET announcement = ?, allowed_kinds = ?, restricted = ? WHERE group_id = ?")
+        .bind(policy.announcement as i64)
+        .bind(serde_json::to_string(&policy.allowed_kinds)?)
+        .bind(policy.restricted as i64)
+        .bind(group_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 /// Set the private/closed access flags (M5 gated reads).
 pub async fn set_flags(
     pool: &SqlitePool,
@@ -100,11 +182,65 @@ pub async fn set_flags(
     Ok(())
 }
 
-/// Is the pubkey an admin of the group?
-pub async fn is_admin(pool: &SqlitePool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
-    let row: Option<(i64,)> = sqlx::query_as(
-        "SELECT 1 FROM group_roles WHERE group_id = ? AND pubkey = ? AND role = 'admin'",
+/// `chain`: the group (first bind) plus the ancestors it inherits from.
+fn ancestry_cte() -> String {
+    format!(
+        "WITH RECURSIVE chain(group_id, parent_id, inherits, depth) AS (\
+            SELECT group_id, parent_id, inherits, 0 FROM groups WHERE group_id = ? \
+            UNION ALL \
+            SELECT g.group_id, g.parent_id, g.inherits, c.depth + 1 \
+            FROM groups g JOIN chain c ON g.group_id = c.parent_id \
+            WHERE c.inherits AND c.depth < {MAX_DEPTH}\
+         ) "
     )
+}
+
+/// Close a `reach` CTE seeded with `SELECT group_id, 0 ...` rows: adds every
+/// descendant inheriting from the seed groups and selects them all.
+fn descendants_sql() -> String {
+    format!(
+        " UNION ALL \
+          SELECT g.group_id, r.depth + 1 FROM groups g JOIN reach r ON g.parent_id = r.group_id \
+          WHERE g.inherits AND r.depth < {MAX_DEPTH}\
+         ) SELECT DISTINCT group_id FROM reach"
+    )
+}
+
+fn push_descendants(qb: &mut QueryBuilder<Sqlite>) {
+    qb.push(descendants_sql());
+}
+
+/// Push a subquery selecting the groups any of `pubkeys` belongs to, directly
+/// or through an inheriting parent (the visibility gate's member check).
+pub(crate) fn push_member_groups(qb: &mut QueryBuilder<Sqlite>, pubkeys: &[String]) {
+    qb.push("WITH RECURSIVE reach(group_id, depth) AS (SELECT group_id, 0 FROM group_members WHERE pubkey IN (");
+    let mut sep = qb.separated(", ");
+    for pk in pubkeys {
+        sep.push_bind(pk.clone());
+    }
+    qb.push(")");
+    push_descendants(qb);
+}
+
+/// Push a subquery selecting the groups any of `pubkeys` moderates: those it
+/// administers, directly or by inheritance (as [`is_admin`]).
+pub(crate) fn push_moderated_groups(qb: &mut QueryBuilder<Sqlite>, pubkeys: &[String]) {
+    qb.push("WITH RECURSIVE reach(group_id, depth) AS (SELECT group_id, 0 FROM group_roles WHERE role = 'admin' AND pubkey IN (");
+    let mut sep = qb.separated(", ");
+    for pk in pubkeys {
+        sep.push_bind(pk.clone());
+    }
+    qb.push(")");
+    push_descendants(qb);
+}
+
+/// Is the pubkey an admin of the group, directly or by inheritance?
+pub async fn is_admin(pool: &SqlitePool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
+    let row: Option<(i64,)> = sqlx::query_as(&format!(
+        "{}SELECT 1 FROM group_roles r JOIN chain c ON r.group_id = c.group_id \
+           WHERE r.pubkey = ? AND r.role = 'admin' LIMIT 1",
+        ancestry_cte()
+    ))
     .bind(group_id)
     .bind(pubkey)
     .fetch_optional(pool)
@@ -129,15 +265,18 @@ pub async fn add_admin(pool: &SqlitePool, group_id: &str, pubkey: &str) -> anyho
     Ok(())
 }
 
-/// Is the pubkey a member of the group? (relay-native only — no
-/// `app.space_members` to UNION against on the embedded relay.)
+/// Is the pubkey a member of the group, or of an ancestor it inherits from?
+/// (relay-native only — no `app.space_members` to UNION against on the
+/// embedded relay.)
 pub async fn is_member(pool: &SqlitePool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
-    let row: Option<(i64,)> =
-        sqlx::query_as("SELECT 1 FROM group_members WHERE group_id = ? AND pubkey = ?")
-            .bind(group_id)
-            .bind(pubkey)
-            .fetch_optional(pool)
-            .await?;
+    let row: Option<(i64,)> = sqlx::query_as(&format!(
+        "{}SELECT 1 FROM group_members m JOIN chain c ON m.group_id = c.group_id WHERE m.pubkey = ? LIMIT 1",
+        ancestry_cte()
+    ))
+    .bind(group_id)
+    .bind(pubkey)
+    .fetch_optional(pool)
+    .await?;
     Ok(row.is_some())
 }
 
@@ -239,17 +378,250 @@ pub async fn delete_group(pool: &SqlitePool, group_id: &str) -> anyhow::Result<(
     Ok(())
 }
 
-/// The set of group ids the pubkey belongs to (relay-native arm of
-/// `membership_source::members_of`). Populates the broadcast-visibility cache.
+/// The set of group ids the pubkey belongs to, inherited sub-groups included
+/// (relay-native arm of `membership_source::members_of`). Populates the
+/// broadcast-visibility cache.
 pub async fn members_of(pool: &SqlitePool, pubkey: &str) -> anyhow::Result<HashSet<String>> {
+    let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("");
+    push_member_groups(&mut qb, &[pubkey.to_string()]);
+    let rows: Vec<(String,)> = qb.build_query_as().fetch_all(pool).await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+/// Make a (new) group a sub-group of `parent_id`.
+pub async fn set_parent(pool: &SqlitePool, group_id: &str, parent_id: &str, inherits: bool) -> anyhow::Result<()> {
+    sqlx::query("UPDATE groups SET parent_id = ?, inherits = ? WHERE group_id = ?")
+        .bind(parent_id)
+        .bind(inherits as i64)
+        .bind(group_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The group's parent and whether it inherits from it (`None` for a root
+/// group or no such group).
+pub async fn parent(pool: &SqlitePool, group_id: &str) -> anyhow::Result<Option<(String, bool)>> {
+    let row: Option<(Option<String>, i64)> =
+        sqlx::query_as("SELECT parent_id, inherits FROM groups WHERE group_id = ?")
+            .bind(group_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.and_then(|(parent, inherits)| parent.map(|p| (p, inherits != 0))))
+}
+
+/// The group's direct sub-groups, for the 39000 event.
+pub async fn children(pool: &SqlitePool, group_id: &str) -> anyhow::Result<Vec<String>> {
     let rows: Vec<(String,)> =
-        sqlx::query_as("SELECT group_id FROM group_members WHERE pubkey = ?")
-            .bind(pubkey)
+        sqlx::query_as("SELECT group_id FROM groups WHERE parent_id = ? ORDER BY group_id")
+            .bind(group_id)
             .fetch_all(pool)
             .await?;
     Ok(rows.into_iter().map(|r| r.0).collect())
 }
 
+/// Every sub-group below `group_id` that (transitively) inherits its
+/// membership.
+pub async fn inheriting_descendants(pool: &SqlitePool, group_id: &str) -> anyhow::Result<Vec<String>> {
+    let mut qb: QueryBuilder<Sqlite> =
+        QueryBuilder::new("WITH RECURSIVE reach(group_id, depth) AS (SELECT group_id, 0 FROM groups WHERE group_id = ");
+    qb.push_bind(group_id);
+    push_descendants(&mut qb);
+    let rows: Vec<(String,)> = qb.build_query_as().fetch_all(pool).await?;
+    Ok(rows.into_iter().map(|r| r.0).filter(|g| g != group_id).collect())
+}
+
+type ReportRow = (String, String, String, String, Option<String>, i64);
+
+fn report_from_row(
+    (report_id, target_event_id, group_id, reporter, report_type, created_at): ReportRow,
+) -> FiledReport {
+    FiledReport {
+        report_id,
+        target_event_id,
+        group_id,
+        reporter,
+        report_type,
+        created_at,
+    }
+}
+
+/// Queue reports (one row per report/target; re-filing is a no-op).
+pub async fn file_reports(pool: &SqlitePool, reports: &[FiledReport]) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    for r in reports {
+        sqlx::query(
+            "INSERT OR IGNORE INTO reports (report_id, target_event_id, group_id, reporter, report_type, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&r.report_id)
+        .bind(&r.target_event_id)
+        .bind(&r.group_id)
+        .bind(&r.reporter)
+        .bind(&r.report_type)
+        .bind(r.created_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Drop a report's queue rows (the report event was refused).
+pub async fn withdraw_report(pool: &SqlitePool, report_id: &str) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM reports WHERE report_id = ?")
+        .bind(report_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Open reports, oldest first: all of them, or only those in groups `admin`
+/// moderates.
+pub async fn open_reports(pool: &SqlitePool, admin: Option<&str>) -> anyhow::Result<Vec<FiledReport>> {
+    let rows: Vec<ReportRow> = sqlx::query_as(&format!(
+        "SELECT report_id, target_event_id, group_id, reporter, report_type, created_at \
+         FROM reports r \
+         WHERE status = 'open' \
+           AND (?1 IS NULL OR r.group_id IN (\
+                WITH RECURSIVE reach(group_id, depth) AS (\
+                    SELECT group_id, 0 FROM group_roles WHERE role = 'admin' AND pubkey = ?1{})) \
+         ORDER BY created_at, report_id, target_event_id",
+        descendants_sql()
+    ))
+    .bind(admin)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(report_from_row).collect())
+}
+
+/// Resolve every open report naming `target_event_id`. Returns how many were.
+pub async fn resolve_reports(
+    pool: &SqlitePool,
+    target_event_id: &str,
+    resolved_by: &str,
+    resolution: &str,
+) -> anyhow::Result<u64> {
+    let result = sqlx::query(
+        "UPDATE reports \
+         SET status = 'resolved', resolution = ?3, resolved_by = ?2 \
+         WHERE target_event_id = ?1 AND status = 'open'",
+    )
+    .bind(target_event_id)
+    .bind(resolved_by)
+    .bind(resolution)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Append an audit log entry (`entry` is the signed kind:9080 event).
+pub async fn append_moderation_log(
+    pool: &SqlitePool,
+    action: &ModerationAction,
+    entry: &Event,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT OR IGNORE INTO moderation_log (id, group_id, actor, action, targets, reason, created_at, event) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&entry.id)
+    .bind(&action.group_id)
+    .bind(&action.actor)
+    .bind(action.action)
+    .bind(serde_json::to_string(&action.targets)?)
+    .bind(&action.reason)
+    .bind(entry.created_at)
+    .bind(serde_json::to_string(entry)?)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// A group's audit log entries, newest first.
+pub async fn moderation_log(
+    pool: &SqlitePool,
+    group_id: &str,
+    since: Option<i64>,
+    until: Option<i64>,
+    limit: i64,
+) -> anyhow::Result<Vec<Event>> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT event FROM moderation_log \
+         WHERE group_id = ?1 AND (?2 IS NULL OR created_at >= ?2) AND (?3 IS NULL OR created_at <= ?3) \
+         ORDER BY created_at DESC, id LIMIT ?4",
+    )
+    .bind(group_id)
+    .bind(since)
+    .bind(until)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(v,)| serde_json::from_str(&v).ok())
+        .collect())
+}
+
+/// Mute `pubkey` in a group until `muted_until` (unix seconds), replacing any
+/// existing mute.
+pub async fn mute_member(
+    pool: &SqlitePool,
+    group_id: &str,
+    pubkey: &str,
+    muted_until: i64,
+    muted_by: &str,
+    reason: Option<&str>,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO group_mutes (group_id, pubkey, muted_until, muted_by, reason) \
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(group_id)
+    .bind(pubkey)
+    .bind(muted_until)
+    .bind(muted_by)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Lift a mute. Returns whether one existed.
+pub async fn unmute_member(pool: &SqlitePool, group_id: &str, pubkey: &str) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM group_mutes WHERE group_id = ? AND pubkey = ?")
+        .bind(group_id)
+        .bind(pubkey)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// When `pubkey`'s mute in the group ends, if it is muted at `now`.
+pub async fn muted_until(pool: &SqlitePool, group_id: &str, pubkey: &str, now: i64) -> anyhow::Result<Option<i64>> {
+    let row: Option<(i64,)> = sqlx::query_as(
+        "SELECT muted_until FROM group_mutes WHERE group_id = ? AND pubkey = ? AND muted_until > ?",
+    )
+    .bind(group_id)
+    .bind(pubkey)
+    .bind(now)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.0))
+}
+
+/// Mutes in effect at `now`, as (pubkey, muted_until), soonest-ending first.
+pub async fn active_mutes(pool: &SqlitePool, group_id: &str, now: i64) -> anyhow::Result<Vec<(String, i64)>> {
+    Ok(sqlx::query_as(
+        "SELECT pubkey, muted_until FROM group_mutes \
+         WHERE group_id = ? AND muted_until > ? ORDER BY muted_until, pubkey",
+    )
+    .bind(group_id)
+    .bind(now)
+    .fetch_all(pool)
+    .await?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -342,4 +714,73 @@ mod tests {
         assert_eq!(row.1.as_deref(), Some("https://img/x.png"));
         assert_eq!(row.2.as_deref(), Some("about text"));
     }
+
+    #[tokio::test]
+    async fn mutes_expire_and_can_be_lifted() {
+        let p = pool().await;
+        create_group(&p, "g1", "G", "alice").await.unwrap();
+        mute_member(&p, "g1", "bob", 200, "alice", Some("spam")).await.unwrap();
+        mute_member(&p, "g1", "carol", 150, "alice", None).await.unwrap();
+
+        assert_eq!(muted_until(&p, "g1", "bob", 100).await.unwrap(), Some(200));
+        assert_eq!(muted_until(&p, "g1", "bob", 200).await.unwrap(), None);
+        assert_eq!(
+            active_mutes(&p, "g1", 100).await.unwrap(),
+            vec![("carol".to_string(), 150), ("bob".to_string(), 200)]
+        );
+
+        // Re-muting replaces the expiry.
+        mute_member(&p, "g1", "bob", 120, "alice", None).await.unwrap();
+        assert_eq!(muted_until(&p, "g1", "bob", 100).await.unwrap(), Some(120));
+        assert!(unmute_member(&p, "g1", "bob").await.unwrap());
+        assert!(!unmute_member(&p, "g1", "bob").await.unwrap());
+        assert_eq!(muted_until(&p, "g1", "bob", 100).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn policy_round_trips() {
+        let p = pool().await;
+        create_group(&p, "g1", "G", "alice").await.unwrap();
+        assert_eq!(policy(&p, "g1").await.unwrap(), GroupPolicy::default());
+        let announcement = GroupPolicy {
+            announcement: true,
+            allowed_kinds: vec![9, 11],
+            restricted: true,
+        };
+        set_policy(&p, "g1", &announcement).await.unwrap();
+        assert_eq!(policy(&p, "g1").await.unwrap(), announcement);
+        assert_eq!(policy(&p, "nope").await.unwrap(), GroupPolicy::default());
+    }
+
+    #[tokio::test]
+    async fn sub_groups_inherit_members_and_admins() {
+        let p = pool().await;
+        create_group(&p, "root", "R", "alice").await.unwrap();
+        add_member(&p, "root", "bob").await.unwrap();
+        create_group(&p, "chan", "C", "alice").await.unwrap();
+        set_parent(&p, "chan", "root", true).await.unwrap();
+        create_group(&p, "sub", "S", "alice").await.unwrap();
+        set_parent(&p, "sub", "chan", true).await.unwrap();
+        create_group(&p, "own", "O", "carol").await.unwrap();
+        set_parent(&p, "own", "root", false).await.unwrap();
+
+        // Inherited two levels down, but not into a group that opted out.
+        assert!(is_member(&p, "sub", "bob").await.unwrap());
+        assert!(!is_member(&p, "own", "bob").await.unwrap());
+        assert!(!is_member(&p, "root", "carol").await.unwrap());
+        assert!(is_admin(&p, "sub", "alice").await.unwrap());
+        assert!(!is_admin(&p, "sub", "bob").await.unwrap());
+
+        let bobs: HashSet<String> = ["root", "chan", "sub"].map(String::from).into();
+        assert_eq!(members_of(&p, "bob").await.unwrap(), bobs);
+        assert_eq!(parent(&p, "own").await.unwrap(), Some(("root".to_string(), false)));
+        assert_eq!(children(&p, "root").await.unwrap(), vec!["chan", "own"]);
+        let mut below = inheriting_descendants(&p, "root").await.unwrap();
+        below.sort();
+        assert_eq!(below, vec!["chan", "sub"]);
+
+        // Deleting a group takes its sub-groups with it.
+        delete_group(&p, "root").await.unwrap();
+        assert!(!group_exists(&p, "sub").await.unwrap());
+    }
 }
@@ -0,0 +1,184 @@
+//! SQLite-backed NIP-86 management store for the embedded in-process relay —
+//! a parity mirror of the Postgres `management_store` (schema prefixes
+//! dropped, `$n` → `?`, insertion order by `rowid` instead of timestamps).
+//!
+//! The tables are created by the schema in [`super::sqlite::connect`].
+
+use sqlx::SqlitePool;
+
+/// Ban `pubkey` from publishing. Lifts any allowlist entry for it.
+pub async fn ban_pubkey(pool: &SqlitePool, pubkey: &str, reason: Option<&str>) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO banned_pubkeys (pubkey, reason) VALUES (?, ?) \
+         ON CONFLICT (pubkey) DO UPDATE SET reason = excluded.reason",
+    )
+    .bind(pubkey)
+    .bind(reason)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM allowed_pubkeys WHERE pubkey = ?")
+        .bind(pubkey)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Allow `pubkey` to publish: lifts any ban and adds it to the allowlist.
+pub async fn allow_pubkey(pool: &SqlitePool, pubkey: &str, reason: Option<&str>) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO allowed_pubkeys (pubkey, reason) VALUES (?, ?) \
+         ON CONFLICT (pubkey) DO UPDATE SET reason = excluded.reason",
+    )
+    .bind(pubkey)
+    .bind(reason)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM banned_pubkeys WHERE pubkey = ?")
+        .bind(pubkey)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Lift a ban on `pubkey`, leaving the allowlist alone.
+pub async fn unban_pubkey(pool: &SqlitePool, pubkey: &str) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM banned_pubkeys WHERE pubkey = ?")
+        .bind(pubkey)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Banned pubkeys with their reasons, oldest ban first.
+pub async fn list_banned_pubkeys(pool: &SqlitePool) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    Ok(sqlx::query_as("SELECT pubkey, reason FROM banned_pubkeys ORDER BY rowid")
+        .fetch_all(pool)
+        .await?)
+}
+
+/// Allowlisted pubkeys with their reasons, oldest first.
+pub async fn list_allowed_pubkeys(pool: &SqlitePool) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    Ok(sqlx::query_as("SELECT pubkey, reason FROM allowed_pubkeys ORDER BY rowid")
+        .fetch_all(pool)
+        .await?)
+}
+
+/// Ban `event_id`: delete it and refuse it from now on. Returns whether a
+/// stored event was deleted.
+pub async fn ban_event(pool: &SqlitePool, event_id: &str, reason: Option<&str>) -> anyhow::Result<bool> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO banned_events (event_id, reason) VALUES (?, ?) \
+         ON CONFLICT (event_id) DO UPDATE SET reason = excluded.reason",
+    )
+    .bind(event_id)
+    .bind(reason)
+    .execute(&mut *tx)
+    .await?;
+    let deleted = sqlx::query("DELETE FROM events WHERE id = ?")
+        .bind(event_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+    Ok(deleted > 0)
+}
+
+/// Lift an event ban (the event itself stays deleted until re-published).
+/// Returns whether a ban existed.
+pub async fn allow_event(pool: &SqlitePool, event_id: &str) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM banned_events WHERE event_id = ?")
+        .bind(event_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Banned event ids with their reasons, oldest ban first.
+pub async fn list_banned_events(pool: &SqlitePool) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    Ok(sqlx::query_as("SELECT event_id, reason FROM banned_events ORDER BY rowid")
+        .fetch_all(pool)
+        .await?)
+}
+
+/// Is `event_id` banned?
+pub async fn is_event_banned(pool: &SqlitePool, event_id: &str) -> anyhow::Result<bool> {
+    let row: (bool,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM banned_events WHERE event_id = ?)")
+        .bind(event_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// An operator setting, if one was stored.
+pub async fn get_setting(pool: &SqlitePool, key: &str) -> anyhow::Result<Option<String>> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT value FROM settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|(v,)| v))
+}
+
+/// Store an operator setting (last write wins).
+pub async fn set_setting(pool: &SqlitePool, key: &str, value: &str) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO settings (key, value) VALUES (?, ?) \
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn ban_unban_and_allow_keep_the_lists_apart() {
+        let pool = super::super::sqlite::connect_memory().await.unwrap();
+        ban_pubkey(&pool, "a", Some("spam")).await.unwrap();
+        ban_pubkey(&pool, "b", None).await.unwrap();
+        assert_eq!(list_banned_pubkeys(&pool).await.unwrap().len(), 2);
+
+        // Un-banning only lifts the ban; it doesn't allowlist.
+        unban_pubkey(&pool, "a").await.unwrap();
+        assert_eq!(list_banned_pubkeys(&pool).await.unwrap(), vec![("b".to_string(), None)]);
+        assert!(list_allowed_pubkeys(&pool).await.unwrap().is_empty());
+
+        // Allowing a banned pubkey lifts its ban; banning it drops it again.
+        allow_pubkey(&pool, "b", None).await.unwrap();
+        assert!(list_banned_pubkeys(&pool).await.unwrap().is_empty());
+        ban_pubkey(&pool, "b", None).await.unwrap();
+        assert!(list_allowed_pubkeys(&pool).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn banned_event_is_deleted_and_refused_until_allowed() {
+        use crate::nostr::event::Event;
+        let pool = super::super::sqlite::connect_memory().await.unwrap();
+        let event = Event {
+            id: "e1".into(),
+            pubkey: "p".into(),
+            created_at: 1,
+            kind: 1,
+            tags: vec![],
+            content: String::new(),
+            sig: String::new(),
+        };
+        assert!(super::super::sqlite::store_event(&pool, &event).await.unwrap());
+
+        assert!(ban_event(&pool, "e1", None).await.unwrap());
+        assert!(is_event_banned(&pool, "e1").await.unwrap());
+        assert!(!super::super::sqlite::store_event(&pool, &event).await.unwrap());
+
+        assert!(allow_event(&pool, "e1").await.unwrap());
+        assert!(super::super::sqlite::store_event(&pool, &event).await.unwrap());
+    }
+}
@@ -0,0 +1,184 @@
+//! Relay signing-key rotation.
+//!
+//! Clients pin the relay's pubkey as the author of its group state
+//! (39000/39001/39002/39005), so changing `RELAY_SECRET_KEY` would otherwise
+//! orphan every group. To rotate, start the relay with the new key and the old
+//! one in `RELAY_PREVIOUS_SECRET_KEY` (the embedded relay takes both from the
+//! host app). At startup the relay compares its pubkey with the one it last
+//! ran under (recorded in the settings table) and, on a change:
+//!   1. signs a kind:39011 handover with the OLD key naming the new pubkey
+//!      (`["p", "<new>"]`), so clients can move their pin without trusting
+//!      the relay's URL,
+//!   2. re-signs every group's state with the new key and drops the old-key
+//!      copies,
+//!   3. records the old and new pubkeys and the rotation time; NIP-11 lists
+//!      both (`pubkeys`) until `RELAY_KEY_GRACE_DAYS` have passed.
+//!
+//! Only pubkeys are stored: a secret in the database would let anyone with a
+//! dump forge group state. The handover is signed only with the supplied old
+//! key (`RELAY_PREVIOUS_SECRET_KEY`, or the host app's keychain for the
+//! embedded relay). Without it the state is still re-signed, but no handover
+//! can be signed — members must re-trust the relay (or follow a group
+//! migration, `nip29::migration`).
+//!
+//! An ephemeral key (none configured) is never recorded or rotated from: it
+//! changes on every run, and isn't meant to be pinned.
+
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+
+use crate::db::Db;
+use crate::nostr::event::Event;
+use crate::nostr::nip29::metadata::{self, KIND_GROUP_MUTES};
+use crate::relay_identity::RelayIdentity;
+
+/// Old-key-signed announcement of the relay's new pubkey.
+pub const KIND_KEY_HANDOVER: i32 = 39011;
+
+/// Default for [`crate::config::Config::key_grace`].
+pub const DEFAULT_KEY_GRACE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Settings rows holding the key history.
+const SETTING_PUBKEY: &str = "relay_pubkey";
+const SETTING_PREVIOUS_PUBKEY: &str = "relay_previous_pubkey";
+const SETTING_ROTATED_AT: &str = "relay_key_rotated_at";
+
+/// Relay-signed group state kinds, re-signed on rotation.
+const STATE_KINDS: [i32; 4] = [39000, 39001, 39002, KIND_GROUP_MUTES];
+
+/// The key the relay signed with before the last rotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyHistory {
+    pub previous_pubkey: String,
+    /// Unix seconds.
+    pub rotated_at: i64,
+}
+
+impl KeyHistory {
+    /// Is the previous key still within its grace period at `now`?
+    pub fn in_grace(&self, now: i64, grace: Duration) -> bool {
+        now < self.rotated_at.saturating_add(grace.as_secs() as i64)
+    }
+}
+
+/// The pubkeys NIP-11 lists: the current one, then the previous one during
+/// its grace period.
+pub fn advertised_pubkeys(current: &str, history: Option<&KeyHistory>, now: i64, grace: Duration) -> Vec<String> {
+    let mut pubkeys = vec![current.to_string()];
+    if let Some(history) = history.filter(|h| h.in_grace(now, grace)) {
+        pubkeys.push(history.previous_pubkey.clone());
+    }
+    pubkeys
+}
+
+/// Sign the handover from `previous` to `current`.
+pub fn sign_handover(previous: &RelayIdentity, current: &RelayIdentity, relay_url: &str) -> Event {
+    let tags = vec![
+        vec!["d".to_string(), current.pubkey.clone()],
+        vec!["p".to_string(), current.pubkey.clone()],
+        vec!["relay".to_string(), relay_url.to_string()],
+    ];
+    previous.sign_event(KIND_KEY_HANDOVER, tags, "")
+}
+
+/// Detect a key change since the last run and carry it out (see the module
+/// docs). Returns the key history to advertise, if any.
+pub async fn rotate_if_needed(
+    db: &Db,
+    identity: &RelayIdentity,
+    previous: Option<&RelayIdentity>,
+    relay_url: &str,
+    broadcast_tx: &broadcast::Sender<Event>,
+) -> anyhow::Result<Option<KeyHistory>> {
+    if identity.is_ephemeral() {
+        return load_history(db).await;
+    }
+    let last = db.get_setting(SETTING_PUBKEY).await?;
+    // A fresh database has no record; a configured previous key still tells
+    // us what the relay used to be.
+    let old_pubkey = match last {
+        Some(last) => last,
+        None => match previous {
+            Some(previous) => previous.pubkey.clone(),
+            None => identity.pubkey.clone(),
+        },
+    };
+    if old_pubkey == identity.pubkey {
+        db.set_setting(SETTING_PUBKEY, &identity.pubkey).await?;
+        return load_history(db).await;
+    }
+
+    match previous.filter(|p| p.pubkey == old_pubkey) {
+        Some(previous) => {
+            let handover = sign_handover(previous, identity, relay_url);
+            db.replace_addressable(KIND_KEY_HANDOVER, &previous.pubkey, &identity.pubkey)
+                .await?;
+            db.store_event(&handover).await?;
+        }
+        None => tracing::warn!(
+            previous = %old_pubkey,
+            "Relay key changed without the old key's secret; no handover signed"
+        ),
+    }
+
+    for group_id in db.group_ids().await? {
+        for kind in STATE_KINDS {
+            db.replace_addressable(kind, &old_pubkey, &group_id).await?;
+        }
+        metadata::publish_group_metadata(db, identity, broadcast_tx, &group_id).await;
+    }
+
+    let history = KeyHistory {
+        previous_pubkey: old_pubkey,
+        rotated_at: chrono::Utc::now().timestamp(),
+    };
+    db.set_setting(SETTING_PREVIOUS_PUBKEY, &history.previous_pubkey).await?;
+    db.set_setting(SETTING_ROTATED_AT, &history.rotated_at.to_string()).await?;
+    db.set_setting(SETTING_PUBKEY, &identity.pubkey).await?;
+    tracing::info!(previous = %history.previous_pubkey, current = %identity.pubkey, "Relay key rotated");
+    Ok(Some(history))
+}
+
+/// The recorded key history, if the relay was ever rotated.
+async fn load_history(db: &Db) -> anyhow::Result<Option<KeyHistory>> {
+    let previous_pubkey = db.get_setting(SETTING_PREVIOUS_PUBKEY).await?;
+    let rotated_at = db.get_setting(SETTING_ROTATED_AT).await?.and_then(|t| t.parse().ok());
+    Ok(previous_pubkey
+        .zip(rotated_at)
+        .map(|(previous_pubkey, rotated_at)| KeyHistory {
+            previous_pubkey,
+            rotated_at,
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    #[test]
+    fn previous_key_is_advertised_during_grace_only() {
+        let history = KeyHistory {
+            previous_pubkey: "old".into(),
+            rotated_at: 1_000,
+        };
+        let grace = Duration::from_secs(2 * DAY as u64);
+        assert_eq!(advertised_pubkeys("new", None, 1_000, grace), vec!["new"]);
+        assert_eq!(advertised_pubkeys("new", Some(&history), 1_000 + DAY, grace), vec!["new", "old"]);
+        assert_eq!(advertised_pubkeys("new", Some(&history), 1_000 + 2 * DAY, grace), vec!["new"]);
+    }
+
+    #[test]
+    fn handover_is_signed_by_the_old_key_and_names_the_new_one() {
+        let old = RelayIdentity::new(None, "test");
+        let new = RelayIdentity::new(None, "test");
+        let handover = sign_handover(&old, &new, "wss://relay.example");
+        assert_eq!(handover.kind, KIND_KEY_HANDOVER);
+        assert_eq!(handover.pubkey, old.pubkey);
+        assert_eq!(handover.get_tag_value("p"), Some(new.pubkey.clone()));
+        assert_eq!(handover.get_tag_value("relay").as_deref(), Some("wss://relay.example"));
+        assert!(crate::nostr::verify::verify_event(&handover));
+    }
+}
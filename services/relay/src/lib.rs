@@ -12,8 +12,11 @@
 pub mod config;
 pub mod connection;
 pub mod db;
+pub mod key_rotation;
+pub mod membership_bus;
 pub mod music;
 pub mod nostr;
 pub mod protocol;
+pub mod rate_limit;
 pub mod relay_identity;
 pub mod server;
@@ -17,7 +17,7 @@ async fn main() -> anyhow::Result<()> {
             .init();
     }
 
-    let config = config::Config::from_env();
+    let config = config::Config::from_env()?;
     tracing::info!("Starting {} on port {}", config.relay_name, config.port);
 
     let pool = db::pool::create_pool(&config.database_url).await?;
@@ -0,0 +1,252 @@
+//! In-process membership-change bus.
+//!
+//! Each connection caches the set of groups its authenticated pubkey belongs
+//! to (`space_memberships` in `connection.rs`) so the broadcast path never hits
+//! the DB per event. That cache used to be refreshed lazily on a 30 s TTL, so a
+//! kicked user kept reading the channel for up to 30 s. Instead, every
+//! membership mutation now publishes a [`MembershipChange`] here and each
+//! `handle_connection` loop consumes it immediately: it re-resolves the
+//! affected `(group, pubkey)` against the DB and, if access was lost, drops the
+//! group from its cache and CLOSEs the subscriptions that target it.
+//!
+//! Producers:
+//!   - the protocol handler, after a successful 9000/9001/9007/9008/9021/9022
+//!     ([`changes_for`] maps the management event to the changes it implies;
+//!     a new inheriting sub-group concerns everyone in its parent),
+//!   - on Postgres, a `LISTEN` task ([`spawn_pg_listener`]) relaying
+//!     `NOTIFY relay_membership` fired by the backend's trigger on
+//!     `app.space_members` (REST joins/kicks never pass through the relay).
+//!
+//! A message only says "membership of X in G *may* have changed" — consumers
+//! always re-query the authoritative source, so duplicates and spurious
+//! notifications are harmless.
+
+use serde::Deserialize;
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+
+use crate::nostr::event::Event;
+use crate::nostr::nip29::subgroups;
+
+/// Postgres NOTIFY channel the backend's `app.space_members` trigger publishes
+/// on (see backend migration `0027_space_members_notify.sql`).
+pub const PG_CHANNEL: &str = "relay_membership";
+
+/// Capacity of the in-process bus. Membership changes are rare compared to
+/// events; a lagging receiver falls back to a full `members_of` refresh.
+pub const BUS_CAPACITY: usize = 1024;
+
+/// A (possible) change to who belongs to a group.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MembershipChange {
+    pub group_id: String,
+    /// The affected member, or `None` when the change concerns every member
+    /// (the group itself was deleted, or created under `parent`).
+    #[serde(default)]
+    pub pubkey: Option<String>,
+    /// For a sub-group just created with inherited membership: its parent,
+    /// whose members now belong to it too.
+    #[serde(default)]
+    pub parent: Option<String>,
+}
+
+impl MembershipChange {
+    /// Does this change concern a connection authenticated as `pubkeys` that
+    /// currently believes it belongs to `memberships`?
+    pub fn concerns(&self, pubkeys: &[String], memberships: &std::collections::HashSet<String>) -> bool {
+        match &self.pubkey {
+            Some(pk) => pubkeys.contains(pk),
+            None => {
+                memberships.contains(&self.group_id)
+                    || self.parent.as_ref().is_some_and(|parent| memberships.contains(parent))
+            }
+        }
+    }
+}
+
+/// The membership changes implied by a NIP-29 management event that its
+/// handler reported as successful. Non-membership kinds yield nothing.
+pub fn changes_for(event: &Event) -> Vec<MembershipChange> {
+    let group_id = match event.get_tag_value("h") {
+        Some(h) => h,
+        None => return Vec::new(),
+    };
+    let one = |pubkey: &str| MembershipChange {
+        group_id: group_id.clone(),
+        pubkey: Some(pubkey.to_string()),
+        parent: None,
+    };
+    match event.kind {
+        // put-user / remove-user: every p-tagged target.
+        9000 | 9001 => event
+            .tags
+            .iter()
+            .filter(|t| t.first().map(String::as_str) == Some("p"))
+            .filter_map(|t| t.get(1))
+            .map(|pk| one(pk))
+            .collect(),
+        // create (creator becomes admin + member), plus everyone in the parent
+        // of a sub-group that inherits its membership.
+        9007 => {
+            let mut changes = vec![one(&event.pubkey)];
+            if let Ok(Some((parent, true))) = subgroups::requested_parent(event) {
+                changes.push(MembershipChange {
+                    group_id: group_id.clone(),
+                    pubkey: None,
+                    parent: Some(parent),
+                });
+            }
+            changes
+        }
+        // join request / leave: the author.
+        9021 | 9022 => vec![one(&event.pubkey)],
+        // delete group: everyone in it.
+        9008 => vec![MembershipChange {
+            group_id,
+            pubkey: None,
+            parent: None,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+/// Parse a `relay_membership` NOTIFY payload:
+/// `{"group_id":"…","pubkey":"…"}` (`pubkey` optional).
+pub fn parse_notify_payload(payload: &str) -> Option<MembershipChange> {
+    serde_json::from_str(payload).ok()
+}
+
+/// Relay backend-originated membership changes (`NOTIFY relay_membership`)
+/// onto the in-process bus. Reconnects with a short back-off if the listener
+/// connection drops; the task lives as long as the relay process.
+pub fn spawn_pg_listener(pool: PgPool, tx: broadcast::Sender<MembershipChange>) {
+    tokio::spawn(async move {
+        loop {
+            match PgListener::connect_with(&pool).await {
+                Ok(mut listener) => {
+                    if let Err(e) = listener.listen(PG_CHANNEL).await {
+                        tracing::warn!(error = %e, "LISTEN {PG_CHANNEL} failed");
+                    } else {
+                        tracing::info!("Listening for membership changes on {PG_CHANNEL}");
+                        loop {
+                            match listener.recv().await {
+                                Ok(n) => match parse_notify_payload(n.payload()) {
+                                    Some(change) => {
+                                        let _ = tx.send(change);
+                                    }
+                                    None => tracing::warn!(
+                                        payload = n.payload(),
+                                        "Ignoring malformed membership notification"
+                                    ),
+                                },
+                                Err(e) => {
+                                    tracing::warn!(error = %e, "Membership listener error; reconnecting");
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Membership listener connect failed"),
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn event_with(kind: i32, tags: Vec<Vec<&str>>) -> Event {
+        Event {
+            id: "id".into(),
+            pubkey: "admin".into(),
+            created_at: 1,
+            kind,
+            tags: tags
+                .into_iter()
+                .map(|t| t.into_iter().map(String::from).collect())
+                .collect(),
+            content: String::new(),
+            sig: "sig".into(),
+        }
+    }
+
+    fn change(group_id: &str, pubkey: Option<&str>) -> MembershipChange {
+        MembershipChange {
+            group_id: group_id.into(),
+            pubkey: pubkey.map(String::from),
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn kick_targets_every_p_tag() {
+        let kick = event_with(9001, vec![vec!["h", "g"], vec!["p", "bob"], vec!["p", "carol"]]);
+        assert_eq!(
+            changes_for(&kick),
+            vec![change("g", Some("bob")), change("g", Some("carol"))]
+        );
+    }
+
+    #[test]
+    fn create_leave_and_join_target_the_author() {
+        let create = event_with(9007, vec![vec!["h", "g"]]);
+        assert_eq!(changes_for(&create), vec![change("g", Some("admin"))]);
+        let leave = event_with(9022, vec![vec!["h", "g"]]);
+        assert_eq!(changes_for(&leave), vec![change("g", Some("admin"))]);
+        let join = event_with(9021, vec![vec!["h", "g"]]);
+        assert_eq!(changes_for(&join), vec![change("g", Some("admin"))]);
+    }
+
+    #[test]
+    fn an_inheriting_sub_group_targets_the_parents_members() {
+        let create = event_with(9007, vec![vec!["h", "chan"], vec!["parent", "g"]]);
+        let for_parent = MembershipChange {
+            parent: Some("g".into()),
+            ..change("chan", None)
+        };
+        assert_eq!(changes_for(&create), vec![change("chan", Some("admin")), for_parent.clone()]);
+        let mine = HashSet::from(["g".to_string()]);
+        assert!(for_parent.concerns(&["bob".into()], &mine));
+        assert!(!for_parent.concerns(&["bob".into()], &HashSet::new()));
+        let own_members = event_with(9007, vec![vec!["h", "chan"], vec!["parent", "g"], vec!["inherit", "false"]]);
+        assert_eq!(changes_for(&own_members), vec![change("chan", Some("admin"))]);
+    }
+
+    #[test]
+    fn group_deletion_targets_everyone() {
+        let del = event_with(9008, vec![vec!["h", "g"]]);
+        assert_eq!(changes_for(&del), vec![change("g", None)]);
+    }
+
+    #[test]
+    fn non_membership_kinds_and_missing_h_yield_nothing() {
+        assert!(changes_for(&event_with(9, vec![vec!["h", "g"]])).is_empty());
+        assert!(changes_for(&event_with(9001, vec![vec!["p", "bob"]])).is_empty());
+    }
+
+    #[test]
+    fn concerns_matches_pubkey_or_cached_group() {
+        let mine = HashSet::from(["g".to_string()]);
+        let bob = ["bob".to_string()];
+        assert!(change("g", Some("bob")).concerns(&bob, &mine));
+        assert!(!change("g", Some("carol")).concerns(&bob, &mine));
+        assert!(change("g", None).concerns(&bob, &mine));
+        assert!(!change("other", None).concerns(&bob, &mine));
+        assert!(change("g", Some("carol")).concerns(&["bob".into(), "carol".into()], &mine));
+    }
+
+    #[test]
+    fn notify_payload_parses_with_and_without_pubkey() {
+        assert_eq!(
+            parse_notify_payload(r#"{"group_id":"g","pubkey":"bob"}"#),
+            Some(change("g", Some("bob")))
+        );
+        assert_eq!(parse_notify_payload(r#"{"group_id":"g"}"#), Some(change("g", None)));
+        assert_eq!(parse_notify_payload("not json"), None);
+    }
+}
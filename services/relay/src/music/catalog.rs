@@ -0,0 +1,293 @@
+//! Music catalog index and queries.
+//!
+//! `store_event` extracts each track's and album's metadata (title, artist,
+//! genre, album, duration, release date) into `music_catalog`, and each
+//! album's and playlist's `a` refs, in order, into `music_refs`. Both rows
+//! cascade away with their event, so replacing or deleting a release keeps
+//! the catalog in step.
+//!
+//! Clients query it with NIP-50 search extensions on a REQ:
+//!
+//! - `{"kinds":[31683], "search":"artist:\"Daft Punk\" genre:house"}` — tracks
+//!   (or albums, by kind) by artist and/or genre, newest release first; any
+//!   other words must appear in the title,
+//! - `{"search":"album:33123:<pubkey>:<d>"}` — the album's tracks,
+//! - `{"search":"playlist:30119:<pubkey>:<d>"}` — the playlist's entries.
+//!
+//! Results are gated like any other query, and the filter's other fields
+//! (`#t`, `#h`, time bounds...) still apply. An album's or playlist's entries
+//! are only resolved for readers who can see it. Resolved tracklists come back
+//! in the relay's usual newest-first order; the album's or playlist's own `a`
+//! tags give the play order.
+
+use std::collections::HashMap;
+
+use crate::db::Db;
+use crate::music::kinds::{Coordinate, KIND_ALBUM, KIND_PLAYLIST, KIND_TRACK};
+use crate::nostr::event::Event;
+use crate::nostr::filter::Filter;
+
+/// Most events a catalog query returns, and the page size it scans the
+/// catalog in.
+pub const MAX_RESULTS: i64 = 500;
+
+/// Catalog pages scanned for readable results before a query settles for
+/// fewer than its limit.
+const MAX_PAGES: i64 = 10;
+
+/// A track's or album's indexed metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub genre: Option<String>,
+    /// The album coordinate a track belongs to.
+    pub album: Option<String>,
+    /// Seconds.
+    pub duration: Option<f64>,
+    /// Unix seconds: the `published_at` tag, else `created_at`.
+    pub released_at: i64,
+}
+
+fn tag_value(event: &Event, name: &str) -> Option<String> {
+    event.get_tag_value(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+impl CatalogEntry {
+    /// The catalog row for a track or album.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        if event.kind != KIND_TRACK && event.kind != KIND_ALBUM {
+            return None;
+        }
+        let album = event.tags.iter().find_map(|t| match t.as_slice() {
+            [n, raw, ..] if n == "a" && event.kind == KIND_TRACK => {
+                Coordinate::parse(raw).filter(|c| c.kind == KIND_ALBUM).map(|c| c.to_string())
+            }
+            _ => None,
+        });
+        Some(Self {
+            title: tag_value(event, "title"),
+            artist: tag_value(event, "artist"),
+            genre: tag_value(event, "genre").map(|g| g.to_lowercase()),
+            album,
+            duration: tag_value(event, "duration")
+                .and_then(|d| d.parse::<f64>().ok())
+                .filter(|d| d.is_finite() && *d >= 0.0),
+            released_at: tag_value(event, "published_at")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(event.created_at),
+        })
+    }
+}
+
+/// An album's or playlist's `a` refs, in order, each with its 0-based index
+/// among the `a` tags (unparseable ones still take up a position).
+pub fn refs(event: &Event) -> Vec<(i64, Coordinate)> {
+    if event.kind != KIND_ALBUM && event.kind != KIND_PLAYLIST {
+        return Vec::new();
+    }
+    event
+        .tags
+        .iter()
+        .filter(|t| t.first().is_some_and(|n| n == "a"))
+        .enumerate()
+        .filter_map(|(i, t)| Some((i as i64, Coordinate::parse(t.get(1)?)?)))
+        .collect()
+}
+
+/// A structured catalog query, parsed from a NIP-50 search string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CatalogQuery {
+    pub artist: Option<String>,
+    pub genre: Option<String>,
+    /// Resolve this album's (or playlist's) entries.
+    pub source: Option<Coordinate>,
+    /// Words the title must contain.
+    pub words: Vec<String>,
+}
+
+/// Split a search string into words, keeping `"quoted phrases"` (also as a
+/// `key:"value"`) together.
+fn tokens(search: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in search.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    out.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        out.push(current);
+    }
+    out
+}
+
+impl CatalogQuery {
+    /// The catalog query in a filter's `search`, if it uses any catalog
+    /// extension (`artist:`, `genre:`, `album:`, `playlist:`). Plain searches
+    /// stay full-text.
+    pub fn from_filter(filter: &Filter) -> Option<Self> {
+        let mut query = Self::default();
+        let mut catalog = false;
+        for token in tokens(filter.search.as_deref()?) {
+            let (key, value) = token.split_once(':').unwrap_or(("", &token));
+            match key {
+                "artist" => query.artist = Some(value.to_string()),
+                "genre" => query.genre = Some(value.to_lowercase()),
+                "album" | "playlist" => {
+                    let kind = if key == "album" { KIND_ALBUM } else { KIND_PLAYLIST };
+                    query.source = Some(Coordinate::parse(value).filter(|c| c.kind == kind)?);
+                }
+                _ => {
+                    query.words.push(token.to_lowercase());
+                    continue;
+                }
+            }
+            catalog = true;
+        }
+        catalog.then_some(query)
+    }
+}
+
+/// `LIKE` pattern matching `word` anywhere (with `\` as the escape).
+pub fn contains_pattern(word: &str) -> String {
+    let escaped = word.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Events answering a catalog query that `authed_pubkeys` may read. The
+/// catalog's candidates go through the usual gating and the rest of the
+/// filter a page at a time, until `limit` are found or [`MAX_PAGES`] pages
+/// have been scanned. An album's or playlist's entries are only resolved for
+/// those who can read it.
+pub async fn query(
+    db: &Db,
+    query: &CatalogQuery,
+    filter: &Filter,
+    authed_pubkeys: &[String],
+) -> anyhow::Result<Vec<Event>> {
+    let limit = filter.limit.unwrap_or(100).clamp(0, MAX_RESULTS) as usize;
+    if let Some(ref source) = query.source {
+        let held = Filter {
+            kinds: vec![source.kind],
+            authors: vec![source.pubkey.clone()],
+            d_tags: vec![source.d.clone()],
+            limit: Some(1),
+            ..Default::default()
+        };
+        if db.query_events(&held, authed_pubkeys).await?.is_empty() {
+            return Ok(Vec::new());
+        }
+    }
+
+    let mut events = Vec::new();
+    for page in 0..MAX_PAGES {
+        if events.len() >= limit {
+            break;
+        }
+        let mut ids = db
+            .music_catalog_ids(query, &filter.kinds, &filter.authors, MAX_RESULTS, page * MAX_RESULTS)
+            .await?;
+        let exhausted = (ids.len() as i64) < MAX_RESULTS;
+        if !filter.ids.is_empty() {
+            ids.retain(|id| filter.ids.contains(id));
+        }
+        if !ids.is_empty() {
+            let candidates = Filter {
+                ids: ids.clone(),
+                search: None,
+                limit: Some(MAX_RESULTS),
+                ..filter.clone()
+            };
+            let mut visible: HashMap<String, Event> = db
+                .query_events(&candidates, authed_pubkeys)
+                .await?
+                .into_iter()
+                .map(|e| (e.id.clone(), e))
+                .collect();
+            events.extend(ids.iter().filter_map(|id| visible.remove(id)));
+        }
+        if exhausted {
+            break;
+        }
+    }
+    events.truncate(limit);
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nostr::event::test_event;
+
+    const PK: &str = "aa00000000000000000000000000000000000000000000000000000000000000";
+
+    fn search(s: &str) -> Option<CatalogQuery> {
+        CatalogQuery::from_filter(&Filter {
+            search: Some(s.into()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn parses_catalog_extensions() {
+        assert_eq!(
+            search(r#"artist:"Daft Punk" genre:House one more"#),
+            Some(CatalogQuery {
+                artist: Some("Daft Punk".into()),
+                genre: Some("house".into()),
+                source: None,
+                words: vec!["one".into(), "more".into()],
+            })
+        );
+        let album = search(&format!("album:33123:{PK}:discovery")).unwrap();
+        assert_eq!(album.source.map(|c| c.d), Some("discovery".into()));
+        // Plain searches and malformed coordinates aren't catalog queries.
+        assert_eq!(search("daft punk"), None);
+        assert_eq!(search(&format!("album:30119:{PK}:mix")), None);
+        assert_eq!(search("playlist:nope"), None);
+    }
+
+    #[test]
+    fn extracts_catalog_entries() {
+        let album = format!("33123:{PK}:lp");
+        let tags = vec![
+            vec!["d", "song"],
+            vec!["title", "Song"],
+            vec!["artist", "Someone"],
+            vec!["genre", "Ambient"],
+            vec!["duration", "183"],
+            vec!["a", &album],
+        ];
+        let event = Event {
+            pubkey: PK.into(),
+            created_at: 100,
+            ..test_event(KIND_TRACK, tags, "")
+        };
+        let entry = CatalogEntry::from_event(&event).unwrap();
+        assert_eq!(entry.genre.as_deref(), Some("ambient"));
+        assert_eq!(entry.album, Some(format!("33123:{PK}:lp")));
+        assert_eq!(entry.duration, Some(183.0));
+        assert_eq!(entry.released_at, 100);
+        assert!(refs(&event).is_empty());
+        assert_eq!(contains_pattern("50%_off"), "%50\\%\\_off%");
+    }
+
+    #[test]
+    fn refs_keep_their_position_among_a_tags() {
+        let track = format!("31683:{PK}:two");
+        let tags = vec![vec!["a", "not-a-coordinate"], vec!["title", "LP"], vec!["a", &track]];
+        let album = test_event(KIND_ALBUM, tags, "");
+        let refs = refs(&album);
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].0, 1);
+        assert_eq!(refs[0].1.to_string(), track);
+    }
+}
@@ -0,0 +1,176 @@
+//! Referential integrity of albums and playlists.
+//!
+//! Albums (33123) and playlists (30119) list their entries as `a` coordinates,
+//! which `music::catalog` indexes in `music_refs`. An entry dangles when no
+//! event is stored at its coordinate: it was never published here
+//! (`missing`), or its author deleted it with a NIP-09 `a` deletion
+//! (`removed`, known from the address tombstone).
+//!
+//! Authors read this with `REQ {"kinds":[9082], "#a":["<coordinate>", ...]}`:
+//! for each of their own albums/playlists the relay holds, it answers with a
+//! relay-signed kind:9082 report — `["a", <coordinate>]` then one
+//! `["missing" | "removed", <entry>, "<n>"]` per dangling entry, `n` being its
+//! 1-based position. Reports are synthesized per request: never stored,
+//! broadcast, or accepted over EVENT.
+//!
+//! Deleting a track that albums or playlists still list also answers the
+//! deletion with a NOTICE saying how many now contain a removed item.
+
+use crate::db::Db;
+use crate::music::kinds::{Coordinate, KIND_ALBUM, KIND_PLAYLIST, KIND_TRACK};
+use crate::nostr::event::Event;
+use crate::nostr::filter::Filter;
+use crate::relay_identity::RelayIdentity;
+
+pub const KIND_INTEGRITY_REPORT: i32 = 9082;
+
+/// Most albums/playlists reported on per filter.
+const MAX_SOURCES: usize = 100;
+
+/// An album or playlist entry with nothing stored at its coordinate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingRef {
+    /// 0-based index among the source's `a` tags.
+    pub position: i64,
+    pub coordinate: Coordinate,
+    /// Deleted by its author (NIP-09), rather than never stored.
+    pub removed: bool,
+}
+
+/// The report's tags for `source`.
+pub fn report_tags(source: &Coordinate, dangling: &[DanglingRef]) -> Vec<Vec<String>> {
+    let mut tags = vec![vec!["a".to_string(), source.to_string()]];
+    for r in dangling {
+        let status = if r.removed { "removed" } else { "missing" };
+        tags.push(vec![status.to_string(), r.coordinate.to_string(), (r.position + 1).to_string()]);
+    }
+    tags
+}
+
+/// Does this filter ask for integrity reports?
+pub fn wants_report(filter: &Filter) -> bool {
+    filter.kinds.contains(&KIND_INTEGRITY_REPORT) && filter.generic_tags.iter().any(|(name, _)| name == "a")
+}
+
+/// Signed reports for the filter's `#a` albums/playlists authored by one of
+/// `authed_pubkeys` and visible to them.
+pub async fn query(
+    db: &Db,
+    identity: &RelayIdentity,
+    filter: &Filter,
+    authed_pubkeys: &[String],
+) -> anyhow::Result<Vec<Event>> {
+    let mut sources: Vec<Coordinate> = filter
+        .generic_tags
+        .iter()
+        .filter(|(name, _)| name == "a")
+        .flat_map(|(_, values)| values.iter().filter_map(|v| Coordinate::parse(v)))
+        .filter(|c| matches!(c.kind, KIND_ALBUM | KIND_PLAYLIST) && authed_pubkeys.contains(&c.pubkey))
+        .collect();
+    sources.sort_by_key(|c| c.to_string());
+    sources.dedup();
+    sources.truncate(MAX_SOURCES);
+
+    let mut reports = Vec::new();
+    for source in sources {
+        if !readable(db, &source, authed_pubkeys).await? {
+            continue;
+        }
+        let dangling = db.music_dangling_refs(&source).await?;
+        reports.push(identity.sign_event(KIND_INTEGRITY_REPORT, report_tags(&source, &dangling), ""));
+    }
+    Ok(reports)
+}
+
+/// Is the album/playlist at `source` held here and visible to `readers`?
+async fn readable(db: &Db, source: &Coordinate, readers: &[String]) -> anyhow::Result<bool> {
+    let held = Filter {
+        kinds: vec![source.kind],
+        authors: vec![source.pubkey.clone()],
+        d_tags: vec![source.d.clone()],
+        limit: Some(1),
+        ..Default::default()
+    };
+    Ok(!db.query_events(&held, readers).await?.is_empty())
+}
+
+/// The NOTICE for a NIP-09 deletion that removed tracks still listed by
+/// albums or playlists the deleter can read, if any.
+pub async fn deletion_notice(db: &Db, deletion: &Event) -> Option<String> {
+    let mut dependents = Vec::new();
+    for tag in &deletion.tags {
+        let Some(track) = (match tag.as_slice() {
+            [n, raw, ..] if n == "a" => Coordinate::parse(raw),
+            _ => None,
+        }) else {
+            continue;
+        };
+        if track.kind != KIND_TRACK || track.pubkey != deletion.pubkey {
+            continue;
+        }
+        match db.music_dependents(&track).await {
+            Ok(sources) => dependents.extend(sources),
+            Err(e) => tracing::error!(error = %e, "Failed to look up albums listing a deleted track"),
+        }
+    }
+    dependents.sort_by_key(|c| c.to_string());
+    dependents.dedup();
+    let deleter = [deletion.pubkey.clone()];
+    let mut visible = 0;
+    for source in &dependents {
+        match readable(db, source, &deleter).await {
+            Ok(true) => visible += 1,
+            Ok(false) => {}
+            Err(e) => tracing::error!(error = %e, "Failed to check an album listing a deleted track"),
+        }
+    }
+    if visible == 0 {
+        return None;
+    }
+    let message = format!(
+        "music: {} album(s)/playlist(s) still list a deleted track and now contain removed items",
+        visible
+    );
+    Some(serde_json::json!(["NOTICE", message]).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PK: &str = "aa00000000000000000000000000000000000000000000000000000000000000";
+
+    #[test]
+    fn reports_dangling_entries_by_position() {
+        let coordinate = |kind: i32, d: &str| Coordinate {
+            kind,
+            pubkey: PK.into(),
+            d: d.into(),
+        };
+        let album = coordinate(KIND_ALBUM, "lp");
+        let dangling = vec![
+            DanglingRef {
+                position: 0,
+                coordinate: coordinate(KIND_TRACK, "gone"),
+                removed: true,
+            },
+            DanglingRef {
+                position: 2,
+                coordinate: coordinate(KIND_TRACK, "never"),
+                removed: false,
+            },
+        ];
+        let tags = report_tags(&album, &dangling);
+        assert_eq!(tags[0], vec!["a".to_string(), format!("33123:{PK}:lp")]);
+        assert_eq!(tags[1], vec!["removed".to_string(), format!("31683:{PK}:gone"), "1".into()]);
+        assert_eq!(tags[2], vec!["missing".to_string(), format!("31683:{PK}:never"), "3".into()]);
+    }
+
+    #[test]
+    fn reports_need_kind_and_a_filter() {
+        let filter = |json: serde_json::Value| serde_json::from_value::<Filter>(json).unwrap();
+        assert!(wants_report(&filter(serde_json::json!({"kinds":[9082],"#a":["x"]}))));
+        assert!(!wants_report(&filter(serde_json::json!({"kinds":[9082]}))));
+        assert!(!wants_report(&filter(serde_json::json!({"kinds":[33123],"#a":["x"]}))));
+    }
+}
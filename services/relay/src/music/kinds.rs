@@ -3,34 +3,286 @@
 /// - 31683: Music track metadata (addressable, title in tags)
 /// - 33123: Album (addressable, title in tags)
 /// - 30119: Playlist (addressable, title in tags)
+///
+/// Metadata lives in tags (per ARCHITECTURE.md); content is optional, except
+/// for private (`["visibility", "private"]`) tracks and albums, whose metadata
+/// is NIP-44 encrypted in content and only `d`, `p` and an album's `a` track
+/// refs stay in cleartext.
+use crate::nostr::event::Event;
+
+pub const KIND_TRACK: i32 = 31683;
+pub const KIND_ALBUM: i32 = 33123;
+pub const KIND_PLAYLIST: i32 = 30119;
+
+/// Most tags on any music event.
+const MAX_TAGS: usize = 1000;
+/// Most tracks on an album.
+const MAX_ALBUM_TRACKS: usize = 200;
+/// Most entries in a playlist.
+const MAX_PLAYLIST_ENTRIES: usize = 500;
+/// Most `t` hashtags.
+const MAX_HASHTAGS: usize = 32;
+/// Most `imeta` variants of a track's audio.
+const MAX_IMETA: usize = 8;
 
 /// Check if an event kind is a music-related kind
 pub fn is_music_kind(kind: i32) -> bool {
-    matches!(kind, 31683 | 33123 | 30119)
-}
-
-/// Validate music event structure by checking tags (not content).
-/// Per ARCHITECTURE.md, title is stored in tags; content is optional.
-pub fn validate_music_event(kind: i32, tags: &[Vec<String>]) -> bool {
-    match kind {
-        31683 => {
-            // Track must have a "title" tag and a "d" tag
-            let has_title = tags.iter().any(|t| t.len() >= 2 && t[0] == "title");
-            let has_d = tags.iter().any(|t| t.len() >= 2 && t[0] == "d");
-            has_title && has_d
-        }
-        33123 => {
-            // Album must have a "title" tag and a "d" tag
-            let has_title = tags.iter().any(|t| t.len() >= 2 && t[0] == "title");
-            let has_d = tags.iter().any(|t| t.len() >= 2 && t[0] == "d");
-            has_title && has_d
-        }
-        30119 => {
-            // Playlist must have a "title" tag and a "d" tag
-            let has_title = tags.iter().any(|t| t.len() >= 2 && t[0] == "title");
-            let has_d = tags.iter().any(|t| t.len() >= 2 && t[0] == "d");
-            has_title && has_d
-        }
-        _ => true,
+    matches!(kind, KIND_TRACK | KIND_ALBUM | KIND_PLAYLIST)
+}
+
+/// A parsed NIP-01 `a` coordinate, `<kind>:<pubkey>:<d>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coordinate {
+    pub kind: i32,
+    pub pubkey: String,
+    pub d: String,
+}
+
+impl Coordinate {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut parts = raw.splitn(3, ':');
+        let kind = parts.next()?;
+        let pubkey = parts.next()?;
+        let d = parts.next()?;
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_digit()) || !is_hex64(pubkey) {
+            return None;
+        }
+        Some(Self {
+            kind: kind.parse().ok()?,
+            pubkey: pubkey.to_string(),
+            d: d.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.kind, self.pubkey, self.d)
+    }
+}
+
+/// 64 lowercase hex characters: a pubkey or a sha256.
+fn is_hex64(s: &str) -> bool {
+    s.len() == 64 && s.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+fn values<'a>(tags: &'a [Vec<String>], name: &'a str) -> impl Iterator<Item = &'a str> {
+    tags.iter()
+        .filter(move |t| t.first().is_some_and(|n| n == name))
+        .map(|t| t.get(1).map_or("", String::as_str))
+}
+
+fn require_value(tags: &[Vec<String>], name: &str, what: &str) -> Result<(), String> {
+    match values(tags, name).next() {
+        Some(v) if !v.trim().is_empty() => Ok(()),
+        _ => Err(format!("{what} requires a non-empty {name} tag")),
+    }
+}
+
+fn check_count(tags: &[Vec<String>], name: &str, max: usize, what: &str) -> Result<(), String> {
+    if values(tags, name).count() > max {
+        return Err(format!("{what} has more than {max} {name} tags"));
+    }
+    Ok(())
+}
+
+/// A non-negative, finite number of seconds (or bytes).
+fn check_number(value: &str, field: &str) -> Result<(), String> {
+    match value.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() && n >= 0.0 => Ok(()),
+        _ => Err(format!("{field} must be a non-negative number")),
+    }
+}
+
+/// The `a` refs of an album or playlist, each a valid coordinate (of `kind`
+/// if given). Reasons cite refs by position, as their values may not be
+/// safe to echo.
+fn check_refs(tags: &[Vec<String>], kind: Option<i32>, max: usize, what: &str) -> Result<Vec<Coordinate>, String> {
+    check_count(tags, "a", max, what)?;
+    values(tags, "a")
+        .enumerate()
+        .map(|(i, raw)| match Coordinate::parse(raw) {
+            Some(c) if kind.is_none_or(|k| k == c.kind) && !c.d.is_empty() => Ok(c),
+            Some(_) if kind == Some(KIND_TRACK) => Err(format!("{what} a tag #{} is not a track (31683) coordinate", i + 1)),
+            _ => Err(format!("{what} a tag #{} is not a valid kind:pubkey:d coordinate", i + 1)),
+        })
+        .collect()
+}
+
+/// The audio file: an `imeta` with an http(s) `url`, an `audio/*` mime type
+/// (`m`) and a sha256 (`x`); `size`/`duration` fields must be numeric.
+fn check_imeta(tags: &[Vec<String>]) -> Result<(), String> {
+    check_count(tags, "imeta", MAX_IMETA, "track")?;
+    let imetas: Vec<_> = tags.iter().filter(|t| t.first().is_some_and(|n| n == "imeta")).collect();
+    if imetas.is_empty() {
+        return Err("track requires an imeta tag for its audio file".into());
+    }
+    for imeta in imetas {
+        let field = |name: &str| {
+            imeta[1..]
+                .iter()
+                .find_map(|entry| entry.strip_prefix(name).and_then(|v| v.strip_prefix(' ')))
+        };
+        match field("url") {
+            Some(url) if url.starts_with("https://") || url.starts_with("http://") => {}
+            _ => return Err("track imeta requires an http(s) url".into()),
+        }
+        if !field("m").is_some_and(|m| m.starts_with("audio/") && m.len() > "audio/".len()) {
+            return Err("track imeta requires an audio/* mime type (m)".into());
+        }
+        if !field("x").is_some_and(is_hex64) {
+            return Err("track imeta requires a lowercase hex sha256 (x)".into());
+        }
+        for name in ["size", "duration"] {
+            if let Some(v) = field(name) {
+                check_number(v, &format!("track imeta {name}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_private(tags: &[Vec<String>]) -> bool {
+    values(tags, "visibility").any(|v| v == "private")
+}
+
+/// Validate a music event's structure, returning why it is invalid.
+pub fn validate_music_event(event: &Event) -> Result<(), String> {
+    let tags = &event.tags;
+    let what = match event.kind {
+        KIND_TRACK => "track",
+        KIND_ALBUM => "album",
+        KIND_PLAYLIST => "playlist",
+        _ => return Ok(()),
+    };
+    if tags.len() > MAX_TAGS {
+        return Err(format!("{what} has more than {MAX_TAGS} tags"));
+    }
+    require_value(tags, "d", what)?;
+    check_count(tags, "t", MAX_HASHTAGS, what)?;
+    if let Some(duration) = values(tags, "duration").next() {
+        check_number(duration, &format!("{what} duration"))?;
+    }
+    // Encrypted metadata: only the cleartext parts can be checked.
+    let private = event.kind != KIND_PLAYLIST && is_private(tags) && !event.content.is_empty();
+    if !private {
+        require_value(tags, "title", what)?;
+    }
+
+    match event.kind {
+        KIND_TRACK => {
+            if !private {
+                check_imeta(tags)?;
+            }
+            if !values(tags, "a").all(|raw| Coordinate::parse(raw).is_some_and(|c| c.kind == KIND_ALBUM)) {
+                return Err("track a tag is not an album (33123) coordinate".into());
+            }
+        }
+        KIND_ALBUM => {
+            // Tracks are listed in album order, so each may appear once.
+            let tracks = check_refs(tags, Some(KIND_TRACK), MAX_ALBUM_TRACKS, what)?;
+            for (i, track) in tracks.iter().enumerate() {
+                if tracks[..i].contains(track) {
+                    return Err(format!("album a tag #{} repeats an earlier track", i + 1));
+                }
+            }
+        }
+        _ => {
+            check_refs(tags, None, MAX_PLAYLIST_ENTRIES, what)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nostr::event::test_event;
+
+    const PK: &str = "aa00000000000000000000000000000000000000000000000000000000000000";
+    const SHA: &str = "bb00000000000000000000000000000000000000000000000000000000000000";
+
+    fn event(kind: i32, tags: Vec<Vec<&str>>, content: &str) -> Event {
+        Event {
+            pubkey: PK.into(),
+            ..test_event(kind, tags, content)
+        }
+    }
+
+    fn track(extra: Vec<Vec<&str>>) -> Event {
+        let x = format!("x {SHA}");
+        let mut tags = vec![
+            vec!["d", "song"],
+            vec!["title", "Song"],
+            vec!["duration", "183"],
+            vec!["imeta", "url https://cdn.example/song.mp3", "m audio/mpeg", x.as_str(), "duration 183.4"],
+        ];
+        tags.extend(extra);
+        event(KIND_TRACK, tags, "")
+    }
+
+    fn reason(e: &Event) -> String {
+        validate_music_event(e).unwrap_err()
+    }
+
+    #[test]
+    fn tracks_need_audio_with_mime_and_hash() {
+        assert_eq!(validate_music_event(&track(vec![])), Ok(()));
+        assert_eq!(validate_music_event(&event(KIND_TRACK, vec![vec!["d", "s"], vec!["title", "S"]], "")),
+            Err("track requires an imeta tag for its audio file".into()));
+        let no_hash = event(
+            KIND_TRACK,
+            vec![vec!["d", "s"], vec!["title", "S"], vec!["imeta", "url https://x/s.mp3", "m audio/mpeg"]],
+            "",
+        );
+        assert!(reason(&no_hash).contains("sha256"));
+        let video = event(
+            KIND_TRACK,
+            vec![vec!["d", "s"], vec!["title", "S"], vec!["imeta", "url https://x/s.mp4", "m video/mp4"]],
+            "",
+        );
+        assert!(reason(&video).contains("audio/*"));
+        let mut long = track(vec![]);
+        long.tags[2][1] = "three minutes".into();
+        assert_eq!(reason(&long), "track duration must be a non-negative number");
+        assert!(reason(&track(vec![vec!["a", "30119:x:y"]])).contains("album (33123)"));
+    }
+
+    #[test]
+    fn title_and_d_are_required_unless_encrypted() {
+        assert_eq!(reason(&event(KIND_PLAYLIST, vec![vec!["title", "P"]], "")), "playlist requires a non-empty d tag");
+        assert_eq!(reason(&event(KIND_ALBUM, vec![vec!["d", "a"]], "")), "album requires a non-empty title tag");
+        let private = event(KIND_TRACK, vec![vec!["d", "s"], vec!["visibility", "private"]], "ciphertext");
+        assert_eq!(validate_music_event(&private), Ok(()));
+    }
+
+    #[test]
+    fn albums_list_distinct_track_coordinates() {
+        let t1 = format!("31683:{PK}:one");
+        let t2 = format!("31683:{PK}:two");
+        let album = |refs: &[&str]| {
+            let mut tags = vec![vec!["d", "lp"], vec!["title", "LP"]];
+            tags.extend(refs.iter().map(|r| vec!["a", *r]));
+            event(KIND_ALBUM, tags, "")
+        };
+        assert_eq!(validate_music_event(&album(&[&t1, &t2])), Ok(()));
+        assert!(reason(&album(&[&t1, &t2, &t1])).contains("#3 repeats"));
+        let playlist_ref = format!("30119:{PK}:mix");
+        assert!(reason(&album(&[&playlist_ref])).contains("not a track (31683) coordinate"));
+        assert!(reason(&album(&["31683:nothex:one"])).contains("not a valid"));
+    }
+
+    #[test]
+    fn playlists_hold_bounded_valid_coordinates() {
+        let entry = format!("31683:{PK}:one");
+        let playlist = |n: usize, raw: &str| {
+            let mut tags = vec![vec!["d", "mix"], vec!["title", "Mix"]];
+            tags.extend(std::iter::repeat_n(vec!["a", raw], n));
+            event(KIND_PLAYLIST, tags, "")
+        };
+        assert_eq!(validate_music_event(&playlist(3, &entry)), Ok(()));
+        assert!(reason(&playlist(1, "31683:abc")).contains("not a valid"));
+        assert_eq!(reason(&playlist(MAX_PLAYLIST_ENTRIES + 1, &entry)), "playlist has more than 500 a tags");
+        assert!(Coordinate::parse(&entry).is_some_and(|c| c.to_string() == entry));
     }
 }
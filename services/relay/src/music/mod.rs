@@ -1 +1,3 @@
+pub mod catalog;
+pub mod integrity;
 pub mod kinds;
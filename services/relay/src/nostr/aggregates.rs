@@ -0,0 +1,307 @@
+//! Per-event aggregates: reaction counts by content, reply counts and zap
+//! totals, so a chat UI doesn't have to fetch every kind:7 to show counts.
+//!
+//! `store_event` records what each stored event contributes (a reaction, a
+//! reply, or a zap, and to which event) in `event_contributions`; triggers on
+//! that table keep the `event_aggregates` counters in step, and the rows
+//! cascade away with the contributing event, so every deletion path (NIP-09,
+//! NIP-29 9005, vanish, group delete) decrements the counters too. The
+//! counters only hold contributions anyone may read; those from h-tagged or
+//! `visibility`-tagged events are gated, and counted per request for a reader
+//! who can see them.
+//!
+//! Clients read them with `REQ {"kinds":[9081], "#e":["<id>", ...]}`: the
+//! relay answers with one relay-signed kind:9081 summary per requested event
+//! the requester can see —
+//! `["e", id]`, `["reaction", "<content>", "<count>"]`..., `["replies", "<n>"]`
+//! and `["zaps", "<n>", "<msats>"]`. Summaries are synthesized per request:
+//! never stored, broadcast, or accepted over EVENT.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::db::Db;
+use crate::nostr::event::Event;
+use crate::nostr::filter::Filter;
+use crate::nostr::nip57;
+use crate::relay_identity::RelayIdentity;
+
+pub const KIND_EVENT_STATS: i32 = 9081;
+
+/// NIP-25 reaction.
+const KIND_REACTION: i32 = 7;
+
+/// Kinds counted as replies to the event they point at: notes (NIP-10), group
+/// chat (NIP-29 / NIP-C7) and comments (NIP-22).
+const KIND_NOTE: i32 = 1;
+const KIND_CHAT: i32 = 9;
+const KIND_COMMENT: i32 = 1111;
+
+/// Longest reaction content counted (an emoji or `:shortcode:`).
+const MAX_REACTION_LEN: usize = 64;
+
+/// Most events summarized per filter.
+const MAX_TARGETS: usize = 500;
+
+/// What an event adds to another event's aggregates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Metric {
+    /// A reaction with this content (`+` for an empty one, per NIP-25).
+    Reaction(String),
+    Reply,
+    Zap { msats: i64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contribution {
+    pub target: String,
+    pub metric: Metric,
+}
+
+impl Contribution {
+    /// `(metric, key, amount)` as stored.
+    pub fn columns(&self) -> (&'static str, &str, i64) {
+        match &self.metric {
+            Metric::Reaction(content) => ("reaction", content, 0),
+            Metric::Reply => ("reply", "", 0),
+            Metric::Zap { msats } => ("zap", "", *msats),
+        }
+    }
+}
+
+/// `(marker, value)` of each tag named `name`, in tag order.
+fn tagged<'a>(event: &'a Event, name: &'a str) -> impl Iterator<Item = (Option<&'a str>, &'a str)> {
+    event.tags.iter().filter_map(move |t| match t.as_slice() {
+        [n, value, rest @ ..] if n == name => Some((rest.get(1).map(|m| m.as_str()), value.as_str())),
+        _ => None,
+    })
+}
+
+/// The event a reply points at: NIP-10 `reply` marker, then `root`; a NIP-C7
+/// `q` for chat; a NIP-22 comment's `e`; or, for an unmarked note, its last
+/// `e` (the deprecated positional form).
+fn reply_target(event: &Event) -> Option<&str> {
+    if event.kind == KIND_COMMENT {
+        return tagged(event, "e").map(|(_, id)| id).next();
+    }
+    let marked = |marker: &str| tagged(event, "e").find(|(m, _)| *m == Some(marker)).map(|(_, id)| id);
+    if let Some(id) = marked("reply").or_else(|| marked("root")) {
+        return Some(id);
+    }
+    if event.kind == KIND_CHAT {
+        return tagged(event, "q").map(|(_, id)| id).next();
+    }
+    tagged(event, "e").filter(|(m, _)| m.is_none_or(str::is_empty)).map(|(_, id)| id).last()
+}
+
+/// Is `event` readable only by some (a group's members, or a private event's
+/// author and collaborators)? Its contribution is then counted per reader.
+pub fn is_gated(event: &Event) -> bool {
+    event.get_tag_value("h").is_some() || event.get_tag_value("visibility").is_some()
+}
+
+/// What `event` contributes to another event's aggregates, if anything.
+pub fn contribution(event: &Event) -> Option<Contribution> {
+    let (target, metric) = match event.kind {
+        KIND_REACTION => {
+            let content = match event.content.trim() {
+                "" => "+",
+                c => c,
+            };
+            if content.chars().count() > MAX_REACTION_LEN {
+                return None;
+            }
+            // NIP-25: the reacted-to event is the last `e` tag.
+            let target = tagged(event, "e").map(|(_, id)| id).last()?;
+            (target, Metric::Reaction(content.to_string()))
+        }
+        KIND_NOTE | KIND_CHAT | KIND_COMMENT => (reply_target(event)?, Metric::Reply),
+        nip57::KIND_ZAP_RECEIPT => {
+            let target = tagged(event, "e").map(|(_, id)| id).next()?;
+            let msats = nip57::receipt_msats(event).unwrap_or(0);
+            (target, Metric::Zap { msats })
+        }
+        _ => return None,
+    };
+    if target.is_empty() || target == event.id {
+        return None;
+    }
+    Some(Contribution {
+        target: target.to_string(),
+        metric,
+    })
+}
+
+/// One event's counters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventStats {
+    /// Reaction content → count.
+    pub reactions: BTreeMap<String, i64>,
+    pub replies: i64,
+    pub zaps: i64,
+    pub zap_msats: i64,
+}
+
+impl EventStats {
+    /// Fold `(metric, key, count, total)` rows into counters.
+    pub fn from_rows(rows: impl IntoIterator<Item = (String, String, i64, i64)>) -> Self {
+        let mut stats = Self::default();
+        for (metric, key, count, total) in rows {
+            match metric.as_str() {
+                "reaction" => *stats.reactions.entry(key).or_default() += count,
+                "reply" => stats.replies += count,
+                "zap" => {
+                    stats.zaps += count;
+                    stats.zap_msats += total;
+                }
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    /// The summary event's tags for event `target`.
+    pub fn tags(&self, target: &str) -> Vec<Vec<String>> {
+        let mut tags = vec![vec!["e".to_string(), target.to_string()]];
+        for (content, count) in &self.reactions {
+            tags.push(vec!["reaction".to_string(), content.clone(), count.to_string()]);
+        }
+        tags.push(vec!["replies".to_string(), self.replies.to_string()]);
+        tags.push(vec!["zaps".to_string(), self.zaps.to_string(), self.zap_msats.to_string()]);
+        tags
+    }
+}
+
+/// Does this filter ask for event summaries?
+pub fn wants_stats(filter: &Filter) -> bool {
+    filter.kinds.contains(&KIND_EVENT_STATS) && !filter.e_tags.is_empty()
+}
+
+/// Signed summaries for the filter's `#e` events that `authed_pubkeys` may
+/// read. Events the relay doesn't hold (or hides from them) get none.
+pub async fn query(
+    db: &Db,
+    identity: &RelayIdentity,
+    filter: &Filter,
+    authed_pubkeys: &[String],
+) -> anyhow::Result<Vec<Event>> {
+    let mut ids = filter.e_tags.clone();
+    ids.sort_unstable();
+    ids.dedup();
+    ids.truncate(MAX_TARGETS);
+    let visible = Filter {
+        ids,
+        limit: Some(MAX_TARGETS as i64),
+        ..Default::default()
+    };
+    let targets: Vec<String> = db
+        .query_events(&visible, authed_pubkeys)
+        .await?
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+
+    let mut rows: BTreeMap<String, Vec<(String, String, i64, i64)>> =
+        targets.iter().map(|id| (id.clone(), Vec::new())).collect();
+    for (target, metric, key, count, total) in db.event_aggregates(&targets).await? {
+        rows.entry(target).or_default().push((metric, key, count, total));
+    }
+    // Gated contributions count once each, for a reader who can see them.
+    let gated = db.gated_contributions(&targets).await?;
+    let mut readable = HashSet::new();
+    for chunk in gated.chunks(MAX_TARGETS) {
+        let held = Filter {
+            ids: chunk.iter().map(|(event_id, ..)| event_id.clone()).collect(),
+            limit: Some(MAX_TARGETS as i64),
+            ..Default::default()
+        };
+        readable.extend(db.query_events(&held, authed_pubkeys).await?.into_iter().map(|e| e.id));
+    }
+    for (event_id, target, metric, key, amount) in gated {
+        if readable.contains(&event_id) {
+            rows.entry(target).or_default().push((metric, key, 1, amount));
+        }
+    }
+    Ok(rows
+        .into_iter()
+        .map(|(target, rows)| {
+            let tags = EventStats::from_rows(rows).tags(&target);
+            identity.sign_event(KIND_EVENT_STATS, tags, "")
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: i32, tags: Vec<Vec<&str>>, content: &str) -> Event {
+        Event {
+            id: "self".into(),
+            pubkey: "p".into(),
+            created_at: 1,
+            kind,
+            tags: tags.into_iter().map(|t| t.into_iter().map(String::from).collect()).collect(),
+            content: content.into(),
+            sig: String::new(),
+        }
+    }
+
+    fn to(target: &str, metric: Metric) -> Option<Contribution> {
+        Some(Contribution {
+            target: target.into(),
+            metric,
+        })
+    }
+
+    #[test]
+    fn reactions_count_against_the_last_e_tag() {
+        let like = event(7, vec![vec!["e", "root"], vec!["e", "msg"], vec!["p", "x"]], "");
+        assert_eq!(contribution(&like), to("msg", Metric::Reaction("+".into())));
+        let emoji = event(7, vec![vec!["e", "msg"]], " 🔥 ");
+        assert_eq!(contribution(&emoji), to("msg", Metric::Reaction("🔥".into())));
+        assert_eq!(contribution(&event(7, vec![vec!["p", "x"]], "+")), None);
+        assert_eq!(contribution(&event(7, vec![vec!["e", "msg"]], &"x".repeat(65))), None);
+    }
+
+    #[test]
+    fn replies_follow_nip10_markers_then_fallbacks() {
+        let marked = event(1, vec![vec!["e", "root", "", "root"], vec!["e", "parent", "", "reply"]], "");
+        assert_eq!(contribution(&marked), to("parent", Metric::Reply));
+        let root_only = event(1, vec![vec!["e", "root", "", "root"], vec!["e", "m", "", "mention"]], "");
+        assert_eq!(contribution(&root_only), to("root", Metric::Reply));
+        let positional = event(1, vec![vec!["e", "a"], vec!["e", "b"]], "");
+        assert_eq!(contribution(&positional), to("b", Metric::Reply));
+        let chat = event(9, vec![vec!["h", "g"], vec!["q", "msg", "wss://r", "pk"]], "");
+        assert_eq!(contribution(&chat), to("msg", Metric::Reply));
+        let comment = event(1111, vec![vec!["E", "root"], vec!["e", "parent"]], "");
+        assert_eq!(contribution(&comment), to("parent", Metric::Reply));
+        assert_eq!(contribution(&event(9, vec![vec!["h", "g"]], "hi")), None);
+        assert_eq!(contribution(&event(1, vec![vec!["e", "m", "", "mention"]], "")), None);
+    }
+
+    #[test]
+    fn zap_receipts_add_their_amount() {
+        let receipt = event(9735, vec![vec!["p", "x"], vec!["e", "msg"], vec!["bolt11", "lnbc10u1xyz"]], "");
+        assert_eq!(contribution(&receipt), to("msg", Metric::Zap { msats: 1_000_000 }));
+        assert_eq!(contribution(&event(9735, vec![vec!["p", "x"]], "")), None);
+    }
+
+    #[test]
+    fn rows_fold_into_summary_tags() {
+        let stats = EventStats::from_rows(vec![
+            ("reaction".to_string(), "+".to_string(), 3, 0),
+            ("reaction".to_string(), "🔥".to_string(), 1, 0),
+            ("reply".to_string(), String::new(), 2, 0),
+            ("zap".to_string(), String::new(), 2, 21_000),
+        ]);
+        let tags = stats.tags("msg");
+        let expected: Vec<Vec<&str>> = vec![
+            vec!["e", "msg"],
+            vec!["reaction", "+", "3"],
+            vec!["reaction", "🔥", "1"],
+            vec!["replies", "2"],
+            vec!["zaps", "2", "21000"],
+        ];
+        assert_eq!(tags, expected);
+    }
+}
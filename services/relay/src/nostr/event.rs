@@ -20,6 +20,11 @@ impl Event {
             .and_then(|t| t.get(1).cloned())
     }
 
+    /// NIP-70: does the event carry the `["-"]` protected marker?
+    pub fn is_protected(&self) -> bool {
+        self.tags.iter().any(|t| t.len() == 1 && t[0] == "-")
+    }
+
     /// Compute the canonical serialization for hashing (NIP-01)
     pub fn serialize_for_id(&self) -> String {
         let tags_value = serde_json::to_value(&self.tags).unwrap_or_default();
@@ -35,6 +40,21 @@ impl Event {
     }
 }
 
+/// An unsigned event for unit tests, with placeholder id and pubkey (override
+/// them with struct update syntax where they matter).
+#[cfg(test)]
+pub(crate) fn test_event(kind: i32, tags: Vec<Vec<&str>>, content: &str) -> Event {
+    Event {
+        id: "x".into(),
+        pubkey: "p".into(),
+        created_at: 1,
+        kind,
+        tags: tags.into_iter().map(|t| t.into_iter().map(String::from).collect()).collect(),
+        content: content.into(),
+        sig: String::new(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,6 +81,14 @@ mod tests {
         assert_eq!(event.get_tag_value("p"), Some("target_pk".to_string()));
     }
 
+    #[test]
+    fn test_is_protected() {
+        let mut event = make_event();
+        assert!(!event.is_protected());
+        event.tags.push(vec!["-".to_string()]);
+        assert!(event.is_protected());
+    }
+
     #[test]
     fn test_get_tag_value_missing() {
         let event = make_event();
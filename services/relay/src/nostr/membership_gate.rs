@@ -39,6 +39,9 @@ pub fn requires_h_membership_check(kind: i32) -> bool {
         | 9005 // NIP-29 mod delete event (admin-gated)
         | 9007 // NIP-29 create group
         | 9008 // NIP-29 delete group
+        | 9010 // mute user (admin-gated)
+        | 9011 // unmute user (admin-gated)
+        | 9012 // migrate group (admin-gated)
         | 9021 // NIP-29 join request (from non-member by definition)
         | 9022 // NIP-29 leave request
     )
@@ -68,6 +71,17 @@ pub fn evaluate_publish_gate(event: &Event, is_member: bool) -> PublishVerdict {
     PublishVerdict::Allow
 }
 
+/// Pure gate for timed mutes: a member muted in the h-tagged group keeps
+/// reading it but may not publish content there. The same kinds as the
+/// membership check are exempt, so a muted member can still delete their own
+/// events or leave.
+pub fn evaluate_mute_gate(event: &Event, is_muted: bool) -> PublishVerdict {
+    if is_muted && event.get_tag_value("h").is_some() && requires_h_membership_check(event.kind) {
+        return PublishVerdict::Reject("restricted: you are muted in this group");
+    }
+    PublishVerdict::Allow
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,7 +117,7 @@ mod tests {
 
     #[test]
     fn nip29_management_kinds_exempt() {
-        for kind in [5, 9000, 9001, 9002, 9005, 9007, 9008, 9021, 9022] {
+        for kind in [5, 9000, 9001, 9002, 9005, 9007, 9008, 9010, 9011, 9012, 9021, 9022] {
             assert!(
                 !requires_h_membership_check(kind),
                 "kind {kind} must skip membership check (handled separately)"
@@ -198,4 +212,25 @@ mod tests {
             );
         }
     }
+
+    // ── evaluate_mute_gate ──────────────────────────────────────────────
+
+    /// A muted member's chat is refused; once unmuted it goes through.
+    #[test]
+    fn muted_member_cannot_post_until_unmuted() {
+        let chat = h_tagged(9);
+        assert_eq!(
+            evaluate_mute_gate(&chat, true),
+            PublishVerdict::Reject("restricted: you are muted in this group"),
+        );
+        assert_eq!(evaluate_mute_gate(&chat, false), PublishVerdict::Allow);
+    }
+
+    /// Muted members may still delete their own events and leave.
+    #[test]
+    fn mute_leaves_deletion_and_leave_alone() {
+        for kind in [5, 9022] {
+            assert_eq!(evaluate_mute_gate(&h_tagged(kind), true), PublishVerdict::Allow);
+        }
+    }
 }
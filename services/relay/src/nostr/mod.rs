@@ -1,5 +1,11 @@
+pub mod aggregates;
 pub mod event;
 pub mod filter;
 pub mod membership_gate;
+pub mod nip13;
 pub mod nip29;
+pub mod nip56;
+pub mod nip57;
+pub mod nip59;
+pub mod nip62;
 pub mod verify;
@@ -0,0 +1,157 @@
+//! NIP-13 proof-of-work admission.
+//!
+//! An event's difficulty is the number of leading zero bits of its id, capped
+//! by the target it commits to in its `["nonce", "<n>", "<target>"]` tag — so
+//! an event mined against a low target that happened to come out lucky still
+//! counts only as what its author paid for. No nonce tag, no work.
+//!
+//! The required difficulty for an event is the highest of the relay-wide
+//! minimum (`RELAY_MIN_POW`), a per-kind minimum (`RELAY_MIN_POW_KINDS`) and,
+//! for h-tagged events, the group's own minimum (set with a `min_pow` tag on
+//! 9002, advertised in 39000). Group admins are exempt from their group's
+//! minimum. The embedded relay only hosts its owner's groups, so there the
+//! per-group minimum is the knob.
+
+use std::collections::HashMap;
+
+use crate::nostr::event::Event;
+
+/// Highest difficulty worth asking for (a 256-bit id).
+pub const MAX_DIFFICULTY: u32 = 256;
+
+/// Leading zero bits of a hex event id. Stops at the first non-hex char.
+pub fn leading_zero_bits(id: &str) -> u32 {
+    let mut bits = 0;
+    for c in id.chars() {
+        let Some(nibble) = c.to_digit(16) else {
+            break;
+        };
+        if nibble == 0 {
+            bits += 4;
+        } else {
+            bits += nibble.leading_zeros() - 28;
+            break;
+        }
+    }
+    bits
+}
+
+/// The target difficulty committed to in the event's `nonce` tag.
+pub fn committed_target(event: &Event) -> Option<u32> {
+    event
+        .tags
+        .iter()
+        .find(|t| t.first().map(|s| s.as_str()) == Some("nonce"))
+        .and_then(|t| t.get(2))
+        .and_then(|target| target.trim().parse().ok())
+}
+
+/// The work an event proves: its id's leading zero bits, capped by its
+/// committed target (0 without one).
+pub fn difficulty(event: &Event) -> u32 {
+    committed_target(event).map_or(0, |target| leading_zero_bits(&event.id).min(target))
+}
+
+/// Relay-wide and per-kind minimum difficulties.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PowPolicy {
+    /// Minimum for every event (0 = none). Advertised in NIP-11.
+    pub min_difficulty: u32,
+    /// Overrides for specific kinds (take the higher of this and the global).
+    pub per_kind: HashMap<i32, u32>,
+}
+
+impl PowPolicy {
+    /// Parse `RELAY_MIN_POW` (bits) and `RELAY_MIN_POW_KINDS`
+    /// (`<kind>:<bits>,...`, e.g. `1:20,9:16`). Malformed entries are skipped.
+    pub fn from_env_values(global: Option<&str>, per_kind: Option<&str>) -> Self {
+        let min_difficulty = global
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0)
+            .min(MAX_DIFFICULTY);
+        let per_kind = per_kind
+            .unwrap_or("")
+            .split(',')
+            .filter_map(|entry| {
+                let (kind, bits) = entry.split_once(':')?;
+                let bits: u32 = bits.trim().parse().ok()?;
+                Some((kind.trim().parse().ok()?, bits.min(MAX_DIFFICULTY)))
+            })
+            .collect();
+        Self {
+            min_difficulty,
+            per_kind,
+        }
+    }
+
+    /// The relay's minimum for `kind`.
+    pub fn required_for(&self, kind: i32) -> u32 {
+        self.per_kind
+            .get(&kind)
+            .copied()
+            .unwrap_or(0)
+            .max(self.min_difficulty)
+    }
+}
+
+/// The `pow:` rejection reason when `event` proves less than `required`.
+pub fn check(event: &Event, required: u32) -> Result<(), String> {
+    let got = difficulty(event);
+    if got >= required {
+        return Ok(());
+    }
+    Err(format!("pow: difficulty {got} is less than {required}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: &str, tags: Vec<Vec<&str>>) -> Event {
+        Event {
+            id: id.into(),
+            pubkey: "p".into(),
+            created_at: 1,
+            kind: 1,
+            tags: tags.into_iter().map(|t| t.into_iter().map(String::from).collect()).collect(),
+            content: String::new(),
+            sig: String::new(),
+        }
+    }
+
+    #[test]
+    fn counts_leading_zero_bits() {
+        // The NIP-13 example: 000000000e9d97a1... has 36 leading zero bits.
+        assert_eq!(leading_zero_bits("000000000e9d97a1ab09fc381030b346cdd7a142ad57e6df0b46dc9bef6c7e2d"), 36);
+        assert_eq!(leading_zero_bits("f0"), 0);
+        assert_eq!(leading_zero_bits("7f"), 1);
+        assert_eq!(leading_zero_bits("01"), 7);
+        assert_eq!(leading_zero_bits("0000"), 16);
+    }
+
+    #[test]
+    fn difficulty_is_capped_by_the_committed_target() {
+        let id = "00000fff";
+        assert_eq!(difficulty(&event(id, vec![vec!["nonce", "7", "20"]])), 20);
+        // Mined against 8, got lucky: counts as 8.
+        assert_eq!(difficulty(&event(id, vec![vec!["nonce", "7", "8"]])), 8);
+        assert_eq!(difficulty(&event(id, vec![vec!["nonce", "7"]])), 0);
+        assert_eq!(difficulty(&event(id, vec![])), 0);
+    }
+
+    #[test]
+    fn policy_takes_the_higher_of_global_and_per_kind() {
+        let policy = PowPolicy::from_env_values(Some("8"), Some("1:20, 9:4, bad, 7:x"));
+        assert_eq!(policy.required_for(1), 20);
+        assert_eq!(policy.required_for(9), 8);
+        assert_eq!(policy.required_for(30023), 8);
+        assert_eq!(PowPolicy::from_env_values(None, None), PowPolicy::default());
+    }
+
+    #[test]
+    fn check_reports_the_shortfall() {
+        let e = event("00ff", vec![vec!["nonce", "1", "8"]]);
+        assert!(check(&e, 8).is_ok());
+        assert_eq!(check(&e, 12), Err("pow: difficulty 8 is less than 12".to_string()));
+    }
+}
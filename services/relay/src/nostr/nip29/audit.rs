@@ -0,0 +1,171 @@
+//! Per-group moderation audit log.
+//!
+//! Every successful NIP-29 management op (9000/9001/9002/9005/9007/9008, and
+//! the 9010/9011 mutes and 9012 migrations) is written to a durable log table as a relay-signed
+//! kind:9080 entry, kept apart from `events` so deleting the affected events —
+//! or the group — never deletes the record of who did it. Group admins read the log with
+//! `REQ {"kinds":[9080], "#h":["<group>"]}`; entries are never broadcast and
+//! never accepted over EVENT.
+//!
+//! Entry shape: `h` = group, `k` = management kind, `actor` = who did it,
+//! `p`/`e` = the op's target pubkeys/events, `source` = the management event
+//! id; content = the op's `reason` tag (or its content).
+
+use crate::db::Db;
+use crate::nostr::event::Event;
+use crate::nostr::filter::Filter;
+use crate::relay_identity::RelayIdentity;
+
+pub const KIND_MOD_LOG: i32 = 9080;
+
+/// Most entries returned per filter.
+const MAX_LOG_ENTRIES: i64 = 500;
+
+/// What a management op did, extracted from its event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModerationAction {
+    pub group_id: String,
+    pub actor: String,
+    pub action: i32,
+    /// Target pubkeys (`p`) and event ids (`e`), in tag order.
+    pub targets: Vec<String>,
+    pub reason: Option<String>,
+}
+
+/// The audit record for a management event, if it is one and names a group.
+pub fn action_for(event: &Event) -> Option<ModerationAction> {
+    if !matches!(event.kind, 9000 | 9001 | 9002 | 9005 | 9007 | 9008 | 9010 | 9011 | 9012) {
+        return None;
+    }
+    let group_id = event.get_tag_value("h")?;
+    let targets = event
+        .tags
+        .iter()
+        .filter(|t| matches!(t.first().map(|s| s.as_str()), Some("p") | Some("e")))
+        .filter_map(|t| t.get(1).cloned())
+        .collect();
+    let reason = event
+        .get_tag_value("reason")
+        .or_else(|| Some(event.content.clone()))
+        .filter(|r| !r.is_empty());
+    Some(ModerationAction {
+        group_id,
+        actor: event.pubkey.clone(),
+        action: event.kind,
+        targets,
+        reason,
+    })
+}
+
+/// Sign the kind:9080 entry for `action`, taken by management event `source`.
+pub fn sign_entry(identity: &RelayIdentity, action: &ModerationAction, source: &Event) -> Event {
+    let mut tags = vec![
+        vec!["h".to_string(), action.group_id.clone()],
+        vec!["k".to_string(), action.action.to_string()],
+        vec!["actor".to_string(), action.actor.clone()],
+    ];
+    for tag in &source.tags {
+        if let (Some(name @ ("p" | "e")), Some(value)) = (tag.first().map(|s| s.as_str()), tag.get(1)) {
+            tags.push(vec![name.to_string(), value.clone()]);
+        }
+    }
+    tags.push(vec!["source".to_string(), source.id.clone()]);
+    identity.sign_event(KIND_MOD_LOG, tags, action.reason.as_deref().unwrap_or(""))
+}
+
+/// Record a successful management op. Failures are logged, not fatal: the op
+/// itself already happened.
+pub async fn record(db: &Db, identity: &RelayIdentity, source: &Event) {
+    let Some(action) = action_for(source) else {
+        return;
+    };
+    let entry = sign_entry(identity, &action, source);
+    if let Err(e) = db.append_moderation_log(&action, &entry).await {
+        tracing::error!(group_id = %action.group_id, kind = action.action, error = %e, "Failed to write moderation audit log");
+    }
+}
+
+/// Does this filter ask for the audit log?
+pub fn wants_log(filter: &Filter) -> bool {
+    filter.kinds.contains(&KIND_MOD_LOG)
+}
+
+/// Audit entries matching `filter` from the groups it names (`#h`) that one of
+/// `authed_pubkeys` administers, newest first. Anyone else gets nothing.
+pub async fn query(db: &Db, filter: &Filter, authed_pubkeys: &[String]) -> anyhow::Result<Vec<Event>> {
+    let limit = filter.limit.unwrap_or(MAX_LOG_ENTRIES).clamp(0, MAX_LOG_ENTRIES);
+    let mut out = Vec::new();
+    for group_id in &filter.h_tags {
+        let mut is_admin = false;
+        for pubkey in authed_pubkeys {
+            if db.is_admin(group_id, pubkey).await? {
+                is_admin = true;
+                break;
+            }
+        }
+        if !is_admin {
+            continue;
+        }
+        let entries = db
+            .moderation_log(group_id, filter.since, filter.until, limit)
+            .await?;
+        out.extend(entries.into_iter().filter(|e| filter.matches(e)));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: i32, tags: Vec<Vec<&str>>, content: &str) -> Event {
+        Event {
+            id: "src".into(),
+            pubkey: "admin".into(),
+            created_at: 1,
+            kind,
+            tags: tags.into_iter().map(|t| t.into_iter().map(String::from).collect()).collect(),
+            content: content.into(),
+            sig: String::new(),
+        }
+    }
+
+    #[test]
+    fn action_for_reads_targets_and_reason() {
+        let kick = event(9001, vec![vec!["h", "g"], vec!["p", "bob"], vec!["reason", "spam"]], "ignored");
+        assert_eq!(
+            action_for(&kick),
+            Some(ModerationAction {
+                group_id: "g".into(),
+                actor: "admin".into(),
+                action: 9001,
+                targets: vec!["bob".into()],
+                reason: Some("spam".into()),
+            })
+        );
+        let delete = event(9005, vec![vec!["h", "g"], vec!["e", "x"]], "off-topic");
+        assert_eq!(action_for(&delete).unwrap().reason.as_deref(), Some("off-topic"));
+        assert_eq!(action_for(&event(9005, vec![vec!["h", "g"]], "")).unwrap().reason, None);
+    }
+
+    #[test]
+    fn action_for_ignores_other_kinds_and_groupless_ops() {
+        assert_eq!(action_for(&event(9, vec![vec!["h", "g"]], "hi")), None);
+        assert_eq!(action_for(&event(9001, vec![vec!["p", "bob"]], "")), None);
+    }
+
+    #[test]
+    fn signed_entry_carries_group_action_and_targets() {
+        let identity = RelayIdentity::new(None, "test");
+        let kick = event(9001, vec![vec!["h", "g"], vec!["p", "bob"]], "spam");
+        let entry = sign_entry(&identity, &action_for(&kick).unwrap(), &kick);
+        assert_eq!(entry.kind, KIND_MOD_LOG);
+        assert_eq!(entry.pubkey, identity.pubkey);
+        assert_eq!(entry.get_tag_value("h").as_deref(), Some("g"));
+        assert_eq!(entry.get_tag_value("k").as_deref(), Some("9001"));
+        assert_eq!(entry.get_tag_value("p").as_deref(), Some("bob"));
+        assert_eq!(entry.get_tag_value("source").as_deref(), Some("src"));
+        assert_eq!(entry.content, "spam");
+        assert!(crate::nostr::verify::verify_event(&entry));
+    }
+}
@@ -1,3 +1,5 @@
+use super::policy::GroupPolicy;
+use super::subgroups;
 use crate::db::Db;
 use crate::nostr::event::Event;
 
@@ -20,6 +22,20 @@ pub async fn handle_create_group(db: &Db, event: &Event) -> anyhow::Result<Vec<S
         )]);
     }
 
+    let policy = match GroupPolicy::default().with_tags(event) {
+        Ok(policy) => policy,
+        Err(reason) => return Ok(vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)]),
+    };
+    let parent = match subgroups::requested_parent(event) {
+        Ok(parent) => parent,
+        Err(reason) => return Ok(vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)]),
+    };
+    if let Some((parent_id, _)) = &parent {
+        if let Some(reason) = subgroups::check_parent(db, &group_id, parent_id, &event.pubkey).await? {
+            return Ok(vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)]);
+        }
+    }
+
     let name = event.content.clone();
 
     db.create_group(&group_id, &name, &event.pubkey).await?;
@@ -31,6 +47,13 @@ pub async fn handle_create_group(db: &Db, event: &Event) -> anyhow::Result<Vec<S
     if is_private || is_closed {
         db.set_group_flags(&group_id, is_private, is_closed).await?;
     }
+    // Announcement / allowed_kinds / restricted (see `policy`).
+    if let Some(policy) = policy {
+        db.set_group_policy(&group_id, &policy).await?;
+    }
+    if let Some((parent_id, inherits)) = &parent {
+        db.set_group_parent(&group_id, parent_id, *inherits).await?;
+    }
 
     tracing::info!("Group created: {} by {}", group_id, event.pubkey);
     Ok(vec![format!(
@@ -39,8 +62,22 @@ pub async fn handle_create_group(db: &Db, event: &Event) -> anyhow::Result<Vec<S
     )])
 }
 
+/// A numeric 9002 setting tag (`min_pow`, `slow_mode`): `Ok(None)` if absent,
+/// `Err` with the OK reason if it isn't a number in `0..=max`.
+fn bounded_setting(event: &Event, name: &str, max: u32) -> Result<Option<u32>, String> {
+    match event.get_tag_value(name) {
+        None => Ok(None),
+        Some(raw) => match raw.trim().parse::<u32>() {
+            Ok(value) if value <= max => Ok(Some(value)),
+            _ => Err(format!("invalid: {name} must be 0-{max}")),
+        },
+    }
+}
+
 /// Handle kind:9002 -- Edit group metadata (admin only). Reads `name`/`picture`/
-/// `about` tags and updates the group; the caller then republishes 39000.
+/// `about` tags, a NIP-13 `min_pow` (bits), a `slow_mode` interval
+/// (seconds; 0 turns either off) and the `policy` tags, and updates the group;
+/// the caller then republishes 39000.
 pub async fn handle_edit_metadata(db: &Db, event: &Event) -> anyhow::Result<Vec<String>> {
     let group_id = match event.get_tag_value("h") {
         Some(id) => id,
@@ -59,9 +96,31 @@ pub async fn handle_edit_metadata(db: &Db, event: &Event) -> anyhow::Result<Vec<
     let name = event.get_tag_value("name");
     let picture = event.get_tag_value("picture");
     let about = event.get_tag_value("about");
+    let (min_pow, slow_mode) = match (
+        bounded_setting(event, "min_pow", crate::nostr::nip13::MAX_DIFFICULTY),
+        bounded_setting(event, "slow_mode", super::slow_mode::MAX_SLOW_MODE_SECS),
+    ) {
+        (Ok(min_pow), Ok(slow_mode)) => (min_pow, slow_mode),
+        (Err(reason), _) | (_, Err(reason)) => {
+            return Ok(vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)])
+        }
+    };
+    let policy = match db.group_policy(&group_id).await?.with_tags(event) {
+        Ok(policy) => policy,
+        Err(reason) => return Ok(vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)]),
+    };
 
     db.edit_group_metadata(&group_id, name.as_deref(), picture.as_deref(), about.as_deref())
         .await?;
+    if let Some(bits) = min_pow {
+        db.set_group_min_pow(&group_id, bits).await?;
+    }
+    if let Some(secs) = slow_mode {
+        db.set_group_slow_mode(&group_id, secs).await?;
+    }
+    if let Some(policy) = policy {
+        db.set_group_policy(&group_id, &policy).await?;
+    }
 
     tracing::info!("Group metadata edited: {} by {}", group_id, event.pubkey);
     Ok(vec![format!(r#"["OK","{}",true,""]"#, event.id)])
@@ -29,6 +29,26 @@ async fn build_group_metadata(
     if let Some(a) = &about {
         tags.push(vec!["about".to_string(), a.clone()]);
     }
+    let min_pow = db.group_min_pow(group_id).await?;
+    if min_pow > 0 {
+        tags.push(vec!["min_pow".to_string(), min_pow.to_string()]);
+    }
+    let slow_mode = db.group_slow_mode(group_id).await?;
+    if slow_mode > 0 {
+        tags.push(vec!["slow_mode".to_string(), slow_mode.to_string()]);
+    }
+    tags.extend(db.group_policy(group_id).await?.metadata_tags());
+    if let Some((parent, inherits)) = db.group_parent(group_id).await? {
+        tags.push(vec!["parent".to_string(), parent]);
+        if !inherits {
+            tags.push(vec!["inherit".to_string(), "false".to_string()]);
+        }
+    }
+    for child in db.group_children(group_id).await? {
+        if listed_child(db, &child).await? {
+            tags.push(vec!["child".to_string(), child]);
+        }
+    }
 
     // Content mirrors the human-readable metadata for clients that read it there.
     let content = serde_json::json!({
@@ -41,6 +61,13 @@ async fn build_group_metadata(
     Ok(Some((tags, content)))
 }
 
+/// Whether a parent's 39000 may name `child`: private and restricted groups
+/// aren't listed, so readers who can't see them don't learn their ids.
+async fn listed_child(db: &Db, child: &str) -> anyhow::Result<bool> {
+    let private = db.get_group_metadata(child).await?.is_none_or(|(_, _, _, is_private, _)| is_private);
+    Ok(!private && !db.group_policy(child).await?.restricted)
+}
+
 /// Build the tags for a kind:39001 group admins event.
 async fn build_group_admins(db: &Db, group_id: &str) -> anyhow::Result<(Vec<Vec<String>>, String)> {
     let admins = db.get_group_admins(group_id).await?;
@@ -51,6 +78,21 @@ async fn build_group_admins(db: &Db, group_id: &str) -> anyhow::Result<(Vec<Vec<
     Ok((tags, String::new()))
 }
 
+/// Relay-signed list of the group's active timed mutes (`["p", pubkey,
+/// "<muted_until>"]`). Expired entries drop out at the next republish; clients
+/// should also compare `muted_until` to the clock.
+pub const KIND_GROUP_MUTES: i32 = 39005;
+
+/// Build the tags for a kind:39005 group mutes event.
+async fn build_group_mutes(db: &Db, group_id: &str) -> anyhow::Result<(Vec<Vec<String>>, String)> {
+    let mutes = db.active_mutes(group_id, chrono::Utc::now().timestamp()).await?;
+    let mut tags = vec![vec!["d".to_string(), group_id.to_string()]];
+    for (pubkey, muted_until) in mutes {
+        tags.push(vec!["p".to_string(), pubkey, muted_until.to_string()]);
+    }
+    Ok((tags, String::new()))
+}
+
 /// Build the tags for a kind:39002 group members event.
 async fn build_group_members(db: &Db, group_id: &str) -> anyhow::Result<(Vec<Vec<String>>, String)> {
     let members = db.get_members(group_id).await?;
@@ -62,15 +104,20 @@ async fn build_group_members(db: &Db, group_id: &str) -> anyhow::Result<(Vec<Vec
 }
 
 /// Sign one metadata event with the relay identity, store it (replacing the
-/// previous addressable version), and broadcast it to subscribers.
+/// previous addressable version), and broadcast it to subscribers. `scope`
+/// h-tags it into a restricted group, so only that group's members see it.
 async fn sign_store_broadcast(
     db: &Db,
     identity: &RelayIdentity,
     broadcast_tx: &broadcast::Sender<Event>,
     kind: i32,
-    tags: Vec<Vec<String>>,
+    mut tags: Vec<Vec<String>>,
     content: &str,
+    scope: Option<&str>,
 ) -> anyhow::Result<()> {
+    if let Some(group_id) = scope {
+        tags.push(vec!["h".to_string(), group_id.to_string()]);
+    }
     let event = identity.sign_event(kind, tags, content);
 
     // The relay is the SOLE author of its own metadata, so always replace the
@@ -87,8 +134,9 @@ async fn sign_store_broadcast(
     Ok(())
 }
 
-/// Regenerate, sign (with the relay identity), store, and broadcast the three
-/// NIP-29 group state events (39000 metadata, 39001 admins, 39002 members).
+/// Regenerate, sign (with the relay identity), store, and broadcast the
+/// NIP-29 group state events (39000 metadata, 39001 admins, 39002 members)
+/// and our 39005 mute list.
 ///
 /// Called after every state-changing NIP-29 op so that other clients
 /// (0xchat / Chachi / Flotilla / Obelisk / our own) can render the group —
@@ -102,10 +150,15 @@ pub async fn publish_group_metadata(
     broadcast_tx: &broadcast::Sender<Event>,
     group_id: &str,
 ) {
+    // A restricted group's state is for its members only; if the policy can't
+    // be read, err on the side of hiding it.
+    let restricted = db.group_policy(group_id).await.map_or(true, |p| p.restricted);
+    let scope = restricted.then_some(group_id);
+
     match build_group_metadata(db, group_id).await {
         Ok(Some((tags, content))) => {
             if let Err(e) =
-                sign_store_broadcast(db, identity, broadcast_tx, 39000, tags, &content).await
+                sign_store_broadcast(db, identity, broadcast_tx, 39000, tags, &content, scope).await
             {
                 tracing::warn!(group_id, error = %e, "Failed to publish 39000 metadata");
             }
@@ -120,7 +173,7 @@ pub async fn publish_group_metadata(
     match build_group_admins(db, group_id).await {
         Ok((tags, content)) => {
             if let Err(e) =
-                sign_store_broadcast(db, identity, broadcast_tx, 39001, tags, &content).await
+                sign_store_broadcast(db, identity, broadcast_tx, 39001, tags, &content, scope).await
             {
                 tracing::warn!(group_id, error = %e, "Failed to publish 39001 admins");
             }
@@ -131,11 +184,22 @@ pub async fn publish_group_metadata(
     match build_group_members(db, group_id).await {
         Ok((tags, content)) => {
             if let Err(e) =
-                sign_store_broadcast(db, identity, broadcast_tx, 39002, tags, &content).await
+                sign_store_broadcast(db, identity, broadcast_tx, 39002, tags, &content, scope).await
             {
                 tracing::warn!(group_id, error = %e, "Failed to publish 39002 members");
             }
         }
         Err(e) => tracing::warn!(group_id, error = %e, "Failed to build 39002 members"),
     }
+
+    match build_group_mutes(db, group_id).await {
+        Ok((tags, content)) => {
+            if let Err(e) =
+                sign_store_broadcast(db, identity, broadcast_tx, KIND_GROUP_MUTES, tags, &content, scope).await
+            {
+                tracing::warn!(group_id, error = %e, "Failed to publish 39005 mutes");
+            }
+        }
+        Err(e) => tracing::warn!(group_id, error = %e, "Failed to build 39005 mutes"),
+    }
 }
@@ -0,0 +1,287 @@
+//! Group migration: moving (or forking) a group to another relay when its
+//! host changes — a rotated tunnel URL, or a new identity after
+//! `relay_reset(wipe_identity)`.
+//!
+//! 1. A group admin sends the old relay a kind:9012 with `h` = group,
+//!    `["from", "<old relay key>"]`, `["relay", "<new ws(s) URL>"]` and
+//!    `["relay_pubkey", "<new relay key>"]`.
+//! 2. The old relay signs a kind:39010 announcement with its identity: `d` =
+//!    group, the same `relay`/`relay_pubkey` tags, `p` = the approving admin,
+//!    `e` = the 9012 id, and the admin's signed 9012 as content. Both
+//!    signatures together are the cross-signature — the old relay vouches
+//!    that the signer was an admin, the admin that the move was wanted from
+//!    that relay (`from`) to the new one. It is
+//!    h-tagged for a restricted group, like the rest of its state.
+//! 3. Anyone may publish the announcement on the new relay. It is accepted
+//!    only there (its `relay_pubkey` must be the receiving relay's key) and
+//!    only if both signatures check out; the group is then created on the new
+//!    relay with the approving admin as its admin, unless it already exists
+//!    there under that admin.
+//!
+//! Clients that see a 39010 from a group's pinned relay key can follow it to
+//! the new relay and pin the new key from the announcement.
+
+use crate::db::Db;
+use crate::nostr::event::Event;
+use crate::nostr::verify::verify_event;
+use crate::relay_identity::RelayIdentity;
+
+/// Admin request to migrate a group (sent to the old relay).
+pub const KIND_MIGRATE_GROUP: i32 = 9012;
+/// Relay-signed migration announcement (published on both relays).
+pub const KIND_GROUP_MIGRATION: i32 = 39010;
+
+/// Longest new relay URL accepted.
+const MAX_URL_LEN: usize = 512;
+
+/// Where a group is moving.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationTarget {
+    pub relay_url: String,
+    pub relay_pubkey: String,
+}
+
+impl MigrationTarget {
+    /// Read the `relay` and `relay_pubkey` tags. `Err` with the OK reason if
+    /// either is missing or malformed.
+    pub fn from_tags(event: &Event) -> Result<Self, String> {
+        let relay_url = event.get_tag_value("relay").map(|u| u.trim().to_string()).unwrap_or_default();
+        let valid_url = (relay_url.starts_with("wss://") || relay_url.starts_with("ws://"))
+            && relay_url.len() <= MAX_URL_LEN
+            && !relay_url.contains(char::is_whitespace);
+        if !valid_url {
+            return Err("invalid: relay must be a ws:// or wss:// URL".to_string());
+        }
+        let relay_pubkey = event.get_tag_value("relay_pubkey").unwrap_or_default();
+        let valid_pubkey =
+            relay_pubkey.len() == 64 && relay_pubkey.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+        if !valid_pubkey {
+            return Err("invalid: relay_pubkey must be a 64-char lowercase hex key".to_string());
+        }
+        Ok(Self {
+            relay_url,
+            relay_pubkey,
+        })
+    }
+}
+
+/// Sign the 39010 announcement for an admin's 9012 `request`.
+pub fn sign_announcement(
+    identity: &RelayIdentity,
+    group_id: &str,
+    target: &MigrationTarget,
+    request: &Event,
+    restricted: bool,
+) -> Event {
+    let mut tags = vec![
+        vec!["d".to_string(), group_id.to_string()],
+        vec!["relay".to_string(), target.relay_url.clone()],
+        vec!["relay_pubkey".to_string(), target.relay_pubkey.clone()],
+        vec!["p".to_string(), request.pubkey.clone()],
+        vec!["e".to_string(), request.id.clone()],
+    ];
+    if restricted {
+        tags.push(vec!["h".to_string(), group_id.to_string()]);
+    }
+    let content = serde_json::to_string(request).unwrap_or_default();
+    identity.sign_event(KIND_GROUP_MIGRATION, tags, &content)
+}
+
+/// Handle kind:9012 -- Migrate group (admin only). On success also returns
+/// the signed 39010 announcement for the caller to store and broadcast.
+pub async fn handle_migrate_group(
+    db: &Db,
+    identity: &RelayIdentity,
+    event: &Event,
+) -> anyhow::Result<(Vec<String>, Option<Event>)> {
+    let group_id = match event.get_tag_value("h") {
+        Some(id) => id,
+        None => return Ok((vec![format!(r#"["OK","{}",false,"missing h tag"]"#, event.id)], None)),
+    };
+
+    if !db.is_admin(&group_id, &event.pubkey).await? {
+        return Ok((vec![format!(r#"["OK","{}",false,"not authorized"]"#, event.id)], None));
+    }
+
+    let target = match MigrationTarget::from_tags(event) {
+        Ok(target) => target,
+        Err(reason) => return Ok((vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)], None)),
+    };
+    // Bind the admin's signature to this relay, so the request can't be
+    // wrapped into an announcement by anyone else.
+    if event.get_tag_value("from").as_deref() != Some(identity.pubkey.as_str()) {
+        return Ok((
+            vec![format!(r#"["OK","{}",false,"invalid: from must be this relay's pubkey"]"#, event.id)],
+            None,
+        ));
+    }
+
+    let restricted = db.group_policy(&group_id).await?.restricted;
+    let announcement = sign_announcement(identity, &group_id, &target, event, restricted);
+    // One live announcement per group: a newer migration replaces it.
+    db.replace_addressable(KIND_GROUP_MIGRATION, &identity.pubkey, &group_id).await?;
+
+    tracing::info!("Group migration announced: {} -> {} by {}", group_id, target.relay_url, event.pubkey);
+    Ok((vec![format!(r#"["OK","{}",true,""]"#, event.id)], Some(announcement)))
+}
+
+/// A verified announcement: the group, where it moves, and who approved it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub group_id: String,
+    pub target: MigrationTarget,
+    pub admin: String,
+}
+
+/// Check an inbound 39010 (whose own signature the caller has verified)
+/// against the receiving relay's key. Returns the OK rejection reason.
+pub fn verify_announcement(event: &Event, relay_pubkey: &str) -> Result<Migration, String> {
+    let group_id = event
+        .get_tag_value("d")
+        .filter(|d| !d.is_empty())
+        .ok_or_else(|| "invalid: migration announcement has no d tag".to_string())?;
+    let target = MigrationTarget::from_tags(event)?;
+    if target.relay_pubkey != relay_pubkey {
+        return Err("restricted: this migration is for another relay".to_string());
+    }
+
+    let request: Event = serde_json::from_str(&event.content)
+        .map_err(|_| "invalid: migration announcement lacks the admin's request".to_string())?;
+    let cross_signed = request.kind == KIND_MIGRATE_GROUP
+        && request.get_tag_value("h").as_deref() == Some(group_id.as_str())
+        && request.get_tag_value("from").as_deref() == Some(event.pubkey.as_str())
+        && MigrationTarget::from_tags(&request).as_ref() == Ok(&target)
+        && event.get_tag_value("p").as_deref() == Some(request.pubkey.as_str())
+        && event.get_tag_value("e").as_deref() == Some(request.id.as_str())
+        && verify_event(&request);
+    if !cross_signed {
+        return Err("invalid: migration announcement is not cross-signed by a group admin".to_string());
+    }
+    Ok(Migration {
+        group_id,
+        target,
+        admin: request.pubkey,
+    })
+}
+
+/// Handle an inbound kind:39010 on the relay it points to: create the group
+/// here with the approving admin as admin. `creator_only` is the only pubkey
+/// allowed to create groups on this relay, if it is restricted.
+pub async fn handle_announcement(
+    db: &Db,
+    event: &Event,
+    relay_pubkey: &str,
+    creator_only: Option<&str>,
+) -> anyhow::Result<(Vec<String>, Option<Migration>)> {
+    let migration = match verify_announcement(event, relay_pubkey) {
+        Ok(migration) => migration,
+        Err(reason) => return Ok((vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)], None)),
+    };
+    let group_id = &migration.group_id;
+
+    if db.group_exists(group_id).await? {
+        // Re-publishing the announcement (or a newer one) for a group that
+        // already moved here is fine; claiming someone else's group is not.
+        if !db.is_admin(group_id, &migration.admin).await? {
+            return Ok((
+                vec![format!(r#"["OK","{}",false,"restricted: group id is already in use on this relay"]"#, event.id)],
+                None,
+            ));
+        }
+        return Ok((vec![format!(r#"["OK","{}",true,""]"#, event.id)], Some(migration)));
+    }
+    if db.platform_space_exists(group_id).await? {
+        return Ok((vec![format!(r#"["OK","{}",false,"error: group id is reserved"]"#, event.id)], None));
+    }
+    if creator_only.is_some_and(|owner| owner != migration.admin) {
+        return Ok((
+            vec![format!(r#"["OK","{}",false,"restricted: only the relay owner can create groups"]"#, event.id)],
+            None,
+        ));
+    }
+
+    db.create_group(group_id, group_id, &migration.admin).await?;
+
+    tracing::info!("Group migrated in: {} by {}", group_id, migration.admin);
+    Ok((vec![format!(r#"["OK","{}",true,""]"#, event.id)], Some(migration)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 9012 signed by a fresh admin key.
+    fn signed_request(tags: Vec<Vec<&str>>) -> Event {
+        let admin = RelayIdentity::new(None, "test");
+        let tags = tags.into_iter().map(|t| t.into_iter().map(String::from).collect()).collect();
+        admin.sign_event(KIND_MIGRATE_GROUP, tags, "")
+    }
+
+    fn target(pubkey: &str) -> MigrationTarget {
+        MigrationTarget {
+            relay_url: "wss://new.example".into(),
+            relay_pubkey: pubkey.into(),
+        }
+    }
+
+    #[test]
+    fn target_tags_are_validated() {
+        let key = "ab".repeat(32);
+        let ok = signed_request(vec![vec!["h", "g"], vec!["relay", "wss://new.example"], vec!["relay_pubkey", &key]]);
+        assert_eq!(MigrationTarget::from_tags(&ok), Ok(target(&key)));
+        let bad_url = signed_request(vec![vec!["relay", "https://new.example"], vec!["relay_pubkey", &key]]);
+        assert!(MigrationTarget::from_tags(&bad_url).is_err());
+        let upper = key.to_uppercase();
+        let bad_key = signed_request(vec![vec!["relay", "wss://new.example"], vec!["relay_pubkey", &upper]]);
+        assert!(MigrationTarget::from_tags(&bad_key).is_err());
+        assert!(MigrationTarget::from_tags(&signed_request(vec![])).is_err());
+    }
+
+    #[test]
+    fn announcement_verifies_only_on_the_new_relay() {
+        let old = RelayIdentity::new(None, "test");
+        let new = RelayIdentity::new(None, "test");
+        let request = signed_request(vec![
+            vec!["h", "g"],
+            vec!["from", &old.pubkey],
+            vec!["relay", "wss://new.example"],
+            vec!["relay_pubkey", &new.pubkey],
+        ]);
+        let announcement = sign_announcement(&old, "g", &target(&new.pubkey), &request, false);
+        assert_eq!(announcement.pubkey, old.pubkey);
+        assert!(verify_event(&announcement));
+        assert_eq!(
+            verify_announcement(&announcement, &new.pubkey),
+            Ok(Migration {
+                group_id: "g".into(),
+                target: target(&new.pubkey),
+                admin: request.pubkey.clone(),
+            })
+        );
+        assert!(verify_announcement(&announcement, &old.pubkey).is_err());
+    }
+
+    #[test]
+    fn announcement_must_match_the_admins_request() {
+        let old = RelayIdentity::new(None, "test");
+        let new = RelayIdentity::new(None, "test");
+        let request = signed_request(vec![
+            vec!["h", "g"],
+            vec!["from", &old.pubkey],
+            vec!["relay", "wss://new.example"],
+            vec!["relay_pubkey", &new.pubkey],
+        ]);
+        // Relay-signed, but for a different group than the admin approved.
+        let other_group = sign_announcement(&old, "other", &target(&new.pubkey), &request, false);
+        assert!(verify_announcement(&other_group, &new.pubkey).is_err());
+        // Wrapped by someone other than the relay the admin named in `from`.
+        let stranger = RelayIdentity::new(None, "test");
+        let rewrapped = sign_announcement(&stranger, "g", &target(&new.pubkey), &request, false);
+        assert!(verify_announcement(&rewrapped, &new.pubkey).is_err());
+        // The embedded request was tampered with after the admin signed it.
+        let mut forged = request.clone();
+        forged.tags[0][1] = "other".into();
+        let tampered = sign_announcement(&old, "other", &target(&new.pubkey), &forged, false);
+        assert!(verify_announcement(&tampered, &new.pubkey).is_err());
+    }
+}
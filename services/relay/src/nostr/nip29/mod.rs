@@ -1,4 +1,10 @@
+pub mod audit;
 pub mod groups;
 pub mod membership;
+pub mod migration;
 pub mod metadata;
 pub mod moderation;
+pub mod policy;
+pub mod slow_mode;
+pub mod subgroups;
+pub mod timeline;
@@ -1,6 +1,25 @@
 use crate::db::Db;
 use crate::nostr::event::Event;
 
+/// Mute a member for a while: `h`, `p` targets, `["duration", "<seconds>"]`,
+/// optional `reason`. Membership and read access are kept.
+pub const KIND_MUTE_USER: i32 = 9010;
+/// Lift a mute early: `h`, `p` targets.
+pub const KIND_UNMUTE_USER: i32 = 9011;
+
+/// Longest mute an admin may hand out (one year); kick for anything longer.
+pub const MAX_MUTE_SECS: i64 = 365 * 24 * 60 * 60;
+
+/// The `p` tag values of a management event.
+fn target_pubkeys(event: &Event) -> Vec<String> {
+    event
+        .tags
+        .iter()
+        .filter(|t| t.first().map(|s| s.as_str()) == Some("p"))
+        .filter_map(|t| t.get(1).cloned())
+        .collect()
+}
+
 /// Handle kind:9000 -- Put user (add to group)
 pub async fn handle_put_user(db: &Db, event: &Event) -> anyhow::Result<Vec<String>> {
     let group_id = match event.get_tag_value("h") {
@@ -15,13 +34,7 @@ pub async fn handle_put_user(db: &Db, event: &Event) -> anyhow::Result<Vec<Strin
         )]);
     }
 
-    // Get target pubkeys from p tags
-    let targets: Vec<String> = event
-        .tags
-        .iter()
-        .filter(|t| t.first().map(|s| s.as_str()) == Some("p"))
-        .filter_map(|t| t.get(1).cloned())
-        .collect();
+    let targets = target_pubkeys(event);
 
     for pubkey in &targets {
         db.add_member(&group_id, pubkey).await?;
@@ -45,12 +58,7 @@ pub async fn handle_remove_user(db: &Db, event: &Event) -> anyhow::Result<Vec<St
         )]);
     }
 
-    let targets: Vec<String> = event
-        .tags
-        .iter()
-        .filter(|t| t.first().map(|s| s.as_str()) == Some("p"))
-        .filter_map(|t| t.get(1).cloned())
-        .collect();
+    let targets = target_pubkeys(event);
 
     for pubkey in &targets {
         db.remove_member(&group_id, pubkey).await?;
@@ -60,6 +68,22 @@ pub async fn handle_remove_user(db: &Db, event: &Event) -> anyhow::Result<Vec<St
     Ok(vec![format!(r#"["OK","{}",true,""]"#, event.id)])
 }
 
+/// Record a deletion tombstone; failures are logged, not fatal (the row is
+/// already gone, only re-insertion protection is lost).
+async fn tombstone_event(db: &Db, event_id: &str, pubkey: &str) {
+    if let Err(e) = db.tombstone_event(event_id, pubkey).await {
+        tracing::error!(event_id, error = %e, "Failed to record deletion tombstone");
+    }
+}
+
+/// Close the NIP-56 reports against a deleted event; failures are logged (the
+/// entry just stays in the queue).
+async fn resolve_reports(db: &Db, event_id: &str, resolved_by: &str) {
+    if let Err(e) = db.resolve_reports(event_id, resolved_by, "deleted").await {
+        tracing::error!(event_id, error = %e, "Failed to resolve reports");
+    }
+}
+
 /// Handle kind:5 -- NIP-09 deletion (author deletes own events)
 pub async fn handle_deletion(db: &Db, event: &Event) -> anyhow::Result<Vec<String>> {
     let mut deleted = 0u32;
@@ -68,13 +92,20 @@ pub async fn handle_deletion(db: &Db, event: &Event) -> anyhow::Result<Vec<Strin
 
         if tag_name == Some("e") {
             if let Some(target_id) = tag.get(1) {
-                // Verify the target event is authored by the deletion sender
-                if let Ok(Some(target)) = db.get_event_by_id(target_id).await {
-                    if target.pubkey == event.pubkey {
+                // Verify the target event is authored by the deletion sender.
+                // Tombstone it (under the sender's pubkey) so a copy re-synced
+                // from another relay is refused — also when it hasn't reached
+                // us yet; a tombstone under the wrong author blocks nothing.
+                match db.get_event_by_id(target_id).await {
+                    Ok(Some(target)) if target.pubkey == event.pubkey => {
                         if db.delete_event(target_id).await.unwrap_or(false) {
                             deleted += 1;
                         }
+                        tombstone_event(db, target_id, &event.pubkey).await;
+                        resolve_reports(db, target_id, &event.pubkey).await;
                     }
+                    Ok(None) => tombstone_event(db, target_id, &event.pubkey).await,
+                    _ => {}
                 }
             }
         }
@@ -99,6 +130,12 @@ pub async fn handle_deletion(db: &Db, event: &Event) -> anyhow::Result<Vec<Strin
                                 Ok(n) => deleted += n as u32,
                                 Err(e) => tracing::error!(addr, error = %e, "Failed to delete addressable event"),
                             }
+                            if let Err(e) = db
+                                .tombstone_address(kind, addr_pubkey, d_tag, event.created_at)
+                                .await
+                            {
+                                tracing::error!(addr, error = %e, "Failed to tombstone addressable event");
+                            }
                         }
                     }
                 }
@@ -124,13 +161,21 @@ pub async fn handle_delete_event(db: &Db, event: &Event) -> anyhow::Result<Vec<S
         )]);
     }
 
+    // Each event deleted is tombstoned under its author so it can't be
+    // re-published.
     let mut deleted = 0u32;
     for tag in &event.tags {
         if tag.first().map(|s| s.as_str()) == Some("e") {
             if let Some(target_id) = tag.get(1) {
+                let target = match db.get_event_by_id(target_id).await {
+                    Ok(Some(t)) => t,
+                    _ => continue,
+                };
                 if db.delete_event(target_id).await.unwrap_or(false) {
                     deleted += 1;
                 }
+                tombstone_event(db, target_id, &target.pubkey).await;
+                resolve_reports(db, target_id, &event.pubkey).await;
             }
         }
     }
@@ -143,3 +188,72 @@ pub async fn handle_delete_event(db: &Db, event: &Event) -> anyhow::Result<Vec<S
     );
     Ok(vec![format!(r#"["OK","{}",true,""]"#, event.id)])
 }
+
+/// Handle kind:9010 -- Mute user (admin only). Admins can't be muted.
+pub async fn handle_mute_user(db: &Db, event: &Event) -> anyhow::Result<Vec<String>> {
+    let group_id = match event.get_tag_value("h") {
+        Some(id) => id,
+        None => return Ok(vec![format!(r#"["OK","{}",false,"missing h tag"]"#, event.id)]),
+    };
+
+    if !db.is_admin(&group_id, &event.pubkey).await? {
+        return Ok(vec![format!(
+            r#"["OK","{}",false,"not authorized"]"#,
+            event.id
+        )]);
+    }
+
+    let duration = match event.get_tag_value("duration").and_then(|d| d.trim().parse::<i64>().ok()) {
+        Some(secs) if (1..=MAX_MUTE_SECS).contains(&secs) => secs,
+        _ => {
+            return Ok(vec![format!(
+                r#"["OK","{}",false,"invalid: duration must be 1-{} seconds"]"#,
+                event.id, MAX_MUTE_SECS
+            )])
+        }
+    };
+    let targets = target_pubkeys(event);
+    for pubkey in &targets {
+        if db.is_admin(&group_id, pubkey).await? {
+            return Ok(vec![format!(
+                r#"["OK","{}",false,"invalid: can't mute a group admin"]"#,
+                event.id
+            )]);
+        }
+    }
+
+    let muted_until = chrono::Utc::now().timestamp() + duration;
+    let reason = event.get_tag_value("reason");
+    for pubkey in &targets {
+        db.mute_member(&group_id, pubkey, muted_until, &event.pubkey, reason.as_deref())
+            .await?;
+    }
+
+    tracing::info!(group_id, muted = targets.len(), muted_until, "Members muted");
+    Ok(vec![format!(r#"["OK","{}",true,""]"#, event.id)])
+}
+
+/// Handle kind:9011 -- Unmute user (admin only)
+pub async fn handle_unmute_user(db: &Db, event: &Event) -> anyhow::Result<Vec<String>> {
+    let group_id = match event.get_tag_value("h") {
+        Some(id) => id,
+        None => return Ok(vec![format!(r#"["OK","{}",false,"missing h tag"]"#, event.id)]),
+    };
+
+    if !db.is_admin(&group_id, &event.pubkey).await? {
+        return Ok(vec![format!(
+            r#"["OK","{}",false,"not authorized"]"#,
+            event.id
+        )]);
+    }
+
+    let mut unmuted = 0u32;
+    for pubkey in &target_pubkeys(event) {
+        if db.unmute_member(&group_id, pubkey).await? {
+            unmuted += 1;
+        }
+    }
+
+    tracing::info!(group_id, unmuted, "Members unmuted");
+    Ok(vec![format!(r#"["OK","{}",true,""]"#, event.id)])
+}
@@ -0,0 +1,204 @@
+//! Per-group write and read policies, stored on `relay.groups` next to the
+//! private/closed flags:
+//!   - `announcement`: a read-only channel — only admins post content,
+//!   - `allowed_kinds`: the content kinds the group accepts (empty = any),
+//!   - `restricted`: only members may read the group's metadata. The relay
+//!     h-tags its 39000-39005 state events for such a group, so the usual
+//!     h-tag visibility rules hide them from everyone else on REQ and on the
+//!     live broadcast.
+//!
+//! Set with tags on 9007 (create) or 9002 (edit): `["announcement"]` /
+//! `["announcement", "false"]`, `["restricted"]` / `["restricted", "false"]`,
+//! and `["allowed_kinds", "9", "11", ...]` (no values clears the list).
+//! Advertised in 39000 the same way, plus NIP-29's `hidden` marker for
+//! restricted groups. Only content kinds are subject to the write policy —
+//! the NIP-29 management kinds, join/leave and NIP-09 deletions always pass.
+
+use crate::nostr::event::Event;
+use crate::nostr::membership_gate::requires_h_membership_check;
+
+/// Most kinds one group may list.
+pub const MAX_ALLOWED_KINDS: usize = 64;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupPolicy {
+    pub announcement: bool,
+    pub allowed_kinds: Vec<i32>,
+    pub restricted: bool,
+}
+
+impl GroupPolicy {
+    /// Apply the policy tags of a 9007/9002 event. `Ok(None)` if it carries
+    /// none; `Err` with the OK reason if one is malformed.
+    pub fn with_tags(&self, event: &Event) -> Result<Option<GroupPolicy>, String> {
+        let mut policy = self.clone();
+        let mut changed = false;
+        for tag in &event.tags {
+            let Some(name) = tag.first().map(|s| s.as_str()) else {
+                continue;
+            };
+            match name {
+                "announcement" | "restricted" => {
+                    let on = switch(tag.get(1).map(|s| s.as_str()))
+                        .ok_or_else(|| format!("invalid: {name} must be true or false"))?;
+                    if name == "announcement" {
+                        policy.announcement = on;
+                    } else {
+                        policy.restricted = on;
+                    }
+                }
+                "allowed_kinds" => {
+                    let kinds = tag[1..]
+                        .iter()
+                        .map(|k| k.trim().parse::<i32>().ok().filter(|k| *k >= 0))
+                        .collect::<Option<Vec<_>>>()
+                        .filter(|kinds| kinds.len() <= MAX_ALLOWED_KINDS)
+                        .ok_or_else(|| {
+                            format!("invalid: allowed_kinds must list at most {MAX_ALLOWED_KINDS} kinds")
+                        })?;
+                    policy.allowed_kinds = kinds;
+                    policy.allowed_kinds.sort_unstable();
+                    policy.allowed_kinds.dedup();
+                }
+                _ => continue,
+            }
+            changed = true;
+        }
+        Ok(changed.then_some(policy))
+    }
+
+    /// May `kind` be posted here by someone who is (or isn't) a group admin?
+    /// Returns the OK rejection reason.
+    pub fn check_write(&self, kind: i32, is_admin: bool) -> Result<(), String> {
+        if !requires_h_membership_check(kind) {
+            return Ok(());
+        }
+        if !self.allowed_kinds.is_empty() && !self.allowed_kinds.contains(&kind) {
+            return Err(format!("restricted: kind {kind} is not allowed in this group"));
+        }
+        if self.announcement && !is_admin {
+            return Err("restricted: only admins post in this group".to_string());
+        }
+        Ok(())
+    }
+
+    /// The 39000 tags advertising this policy.
+    pub fn metadata_tags(&self) -> Vec<Vec<String>> {
+        let mut tags = Vec::new();
+        if self.announcement {
+            tags.push(vec!["announcement".to_string()]);
+        }
+        if self.restricted {
+            tags.push(vec!["restricted".to_string()]);
+            tags.push(vec!["hidden".to_string()]);
+        }
+        if !self.allowed_kinds.is_empty() {
+            let mut tag = vec!["allowed_kinds".to_string()];
+            tag.extend(self.allowed_kinds.iter().map(|k| k.to_string()));
+            tags.push(tag);
+        }
+        tags
+    }
+}
+
+/// A marker tag's value: bare or `true` turns it on, `false` off.
+fn switch(value: Option<&str>) -> Option<bool> {
+    match value.map(str::trim) {
+        None | Some("true") => Some(true),
+        Some("false") => Some(false),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(tags: Vec<Vec<&str>>) -> Event {
+        Event {
+            id: "x".into(),
+            pubkey: "admin".into(),
+            created_at: 1,
+            kind: 9002,
+            tags: tags.into_iter().map(|t| t.into_iter().map(String::from).collect()).collect(),
+            content: String::new(),
+            sig: String::new(),
+        }
+    }
+
+    #[test]
+    fn tags_update_only_what_they_name() {
+        let base = GroupPolicy {
+            announcement: true,
+            ..Default::default()
+        };
+        assert_eq!(base.with_tags(&event(vec![vec!["h", "g"], vec!["name", "x"]])), Ok(None));
+        let edited = base
+            .with_tags(&event(vec![vec!["restricted"], vec!["allowed_kinds", "9", " 7", "9"]]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            edited,
+            GroupPolicy {
+                announcement: true,
+                allowed_kinds: vec![7, 9],
+                restricted: true,
+            }
+        );
+        let cleared = edited
+            .with_tags(&event(vec![vec!["announcement", "false"], vec!["allowed_kinds"]]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            cleared,
+            GroupPolicy {
+                restricted: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_tags_are_rejected() {
+        let base = GroupPolicy::default();
+        assert!(base.with_tags(&event(vec![vec!["announcement", "maybe"]])).is_err());
+        assert!(base.with_tags(&event(vec![vec!["allowed_kinds", "9", "chat"]])).is_err());
+        assert!(base.with_tags(&event(vec![vec!["allowed_kinds", "-1"]])).is_err());
+    }
+
+    #[test]
+    fn write_policy_applies_to_content_kinds() {
+        let policy = GroupPolicy {
+            announcement: true,
+            allowed_kinds: vec![9, 11],
+            restricted: false,
+        };
+        assert!(policy.check_write(9, true).is_ok());
+        assert_eq!(policy.check_write(9, false), Err("restricted: only admins post in this group".into()));
+        assert!(policy.check_write(1, true).unwrap_err().contains("kind 1"));
+        // Leaving, deleting and moderating still work.
+        assert!(policy.check_write(9022, false).is_ok());
+        assert!(policy.check_write(5, false).is_ok());
+        assert!(policy.check_write(9001, false).is_ok());
+        assert!(GroupPolicy::default().check_write(1, false).is_ok());
+    }
+
+    #[test]
+    fn metadata_tags_advertise_the_policy() {
+        assert!(GroupPolicy::default().metadata_tags().is_empty());
+        let policy = GroupPolicy {
+            announcement: true,
+            allowed_kinds: vec![9, 11],
+            restricted: true,
+        };
+        assert_eq!(
+            policy.metadata_tags(),
+            vec![
+                vec!["announcement".to_string()],
+                vec!["restricted".to_string()],
+                vec!["hidden".to_string()],
+                vec!["allowed_kinds".to_string(), "9".to_string(), "11".to_string()],
+            ]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Slow mode: a per-group minimum interval between one member's kind:9 chat
+//! messages (Discord-style).
+//!
+//! The interval is a group flag (`relay.groups.slow_mode_secs`), set with a
+//! `slow_mode` tag on 9002 and advertised in 39000. Admins are exempt. The
+//! last accepted post per (group, member) is tracked in memory by relay
+//! receive time — `created_at` is client-chosen, so it can't be trusted to
+//! space messages out. A post claims its slot atomically when it's checked,
+//! so several sockets sending at once can't all slip through; the slot is
+//! handed back if the post isn't stored, so a refused or retried message
+//! doesn't start the clock. A restart forgets the history, which at worst
+//! lets one early message through.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Kind subject to slow mode (NIP-29 chat message).
+pub const KIND_CHAT: i32 = 9;
+
+/// Longest interval an admin may set (6 hours).
+pub const MAX_SLOW_MODE_SECS: u32 = 6 * 60 * 60;
+
+/// Tracked posts above which entries older than the longest interval are
+/// swept out.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// When each (group, member) last got a chat message through.
+#[derive(Default)]
+pub struct SlowMode {
+    last_post: Mutex<HashMap<(String, String), Instant>>,
+}
+
+impl SlowMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claim `pubkey`'s next post in `group_id`: if at least `interval` has
+    /// passed since their last one, it's recorded as now and `Ok`; otherwise
+    /// how long they must wait. [`Self::release`] the slot if the post isn't
+    /// stored.
+    pub fn reserve(&self, group_id: &str, pubkey: &str, interval: Duration) -> Result<Reservation, Duration> {
+        self.reserve_at(group_id, pubkey, interval, Instant::now())
+    }
+
+    /// Hand back a slot whose post wasn't stored, restoring the member's
+    /// previous post time unless a later post has replaced it since.
+    pub fn release(&self, reservation: Reservation) {
+        let mut last_post = self.last_post.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (reservation.group_id, reservation.pubkey);
+        if last_post.get(&key) != Some(&reservation.at) {
+            return;
+        }
+        match reservation.previous {
+            Some(previous) => last_post.insert(key, previous),
+            None => last_post.remove(&key),
+        };
+    }
+
+    fn reserve_at(&self, group_id: &str, pubkey: &str, interval: Duration, now: Instant) -> Result<Reservation, Duration> {
+        let mut last_post = self.last_post.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (group_id.to_string(), pubkey.to_string());
+        let previous = last_post.get(&key).copied();
+        if let Some(at) = previous {
+            let since = now.saturating_duration_since(at);
+            if since < interval {
+                return Err(interval - since);
+            }
+        }
+        if last_post.len() >= PRUNE_THRESHOLD {
+            let horizon = Duration::from_secs(MAX_SLOW_MODE_SECS as u64);
+            last_post.retain(|_, at| now.saturating_duration_since(*at) < horizon);
+        }
+        last_post.insert(key, now);
+        Ok(Reservation {
+            group_id: group_id.to_string(),
+            pubkey: pubkey.to_string(),
+            at: now,
+            previous,
+        })
+    }
+}
+
+/// A claimed slow-mode slot (see [`SlowMode::reserve`]).
+#[derive(Debug)]
+pub struct Reservation {
+    group_id: String,
+    pubkey: String,
+    at: Instant,
+    previous: Option<Instant>,
+}
+
+/// The OK reason for a message sent too soon.
+pub fn wait_reason(wait: Duration) -> String {
+    // Round up so "wait 0s" is never shown.
+    let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    format!("rate-limited: slow mode is on, wait {secs}s")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces_out_one_members_posts_per_group() {
+        let slow = SlowMode::new();
+        let t0 = Instant::now();
+        let ten = Duration::from_secs(10);
+        assert!(slow.reserve_at("g", "alice", ten, t0).is_ok());
+        // The slot is taken as soon as it's claimed: a concurrent send waits.
+        assert_eq!(
+            slow.reserve_at("g", "alice", ten, t0 + Duration::from_secs(4)).unwrap_err(),
+            Duration::from_secs(6)
+        );
+        // Other members and other groups are independent.
+        assert!(slow.reserve_at("g", "bob", ten, t0).is_ok());
+        assert!(slow.reserve_at("h", "alice", ten, t0).is_ok());
+        assert!(slow.reserve_at("g", "alice", ten, t0 + ten).is_ok());
+    }
+
+    #[test]
+    fn a_released_slot_restores_the_previous_post() {
+        let slow = SlowMode::new();
+        let t0 = Instant::now();
+        let ten = Duration::from_secs(10);
+        let first = slow.reserve_at("g", "alice", ten, t0).unwrap();
+        slow.release(first);
+        // Never stored, so it didn't start the clock.
+        slow.reserve_at("g", "alice", ten, t0 + Duration::from_secs(1)).unwrap();
+        let refused = slow.reserve_at("g", "alice", ten, t0 + Duration::from_secs(11)).unwrap();
+        slow.release(refused);
+        // Back to the stored post at t0 + 1s.
+        assert_eq!(
+            slow.reserve_at("g", "alice", ten, t0 + Duration::from_secs(5)).unwrap_err(),
+            Duration::from_secs(6)
+        );
+    }
+
+    #[test]
+    fn wait_reason_rounds_up() {
+        assert_eq!(wait_reason(Duration::from_millis(5200)), "rate-limited: slow mode is on, wait 6s");
+        assert_eq!(wait_reason(Duration::from_secs(3)), "rate-limited: slow mode is on, wait 3s");
+    }
+}
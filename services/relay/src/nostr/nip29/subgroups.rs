@@ -0,0 +1,92 @@
+//! Sub-groups (channels): a 9007 with `["parent", "<group>"]` creates a child
+//! of an existing relay-native group. Only an admin of the parent may do so,
+//! and the child must be new — a group's parent is fixed at creation, so the
+//! hierarchy can't form a cycle.
+//!
+//! A child inherits its parent's members and admins (transitively, up to
+//! [`MAX_DEPTH`] levels) on top of its own, unless created with
+//! `["inherit", "false"]`, in which case only its own members and admins
+//! count. Resolution walks the hierarchy in the membership queries
+//! (`membership_source` / `sqlite_groups`), so inherited access is never
+//! copied into `group_members`; 39002 keeps listing direct members only.
+//!
+//! The parent's 39000 lists its public, unrestricted children as
+//! `["child", "<group>"]` (the others' ids stay with their members); a
+//! child's carries `["parent", "<group>"]` (and `["inherit", "false"]` if set).
+//! Deleting a group deletes its children.
+
+use crate::db::Db;
+use crate::nostr::event::Event;
+
+/// Deepest a group may nest below its root group.
+pub const MAX_DEPTH: usize = 4;
+
+/// The parent a 9007 asks for and whether the child inherits from it.
+/// `Err` with the OK reason for a malformed `inherit` tag.
+pub fn requested_parent(event: &Event) -> Result<Option<(String, bool)>, String> {
+    let Some(parent) = event.get_tag_value("parent") else {
+        return Ok(None);
+    };
+    let inherits = match event.get_tag_value("inherit").as_deref().map(str::trim) {
+        None | Some("true") => true,
+        Some("false") => false,
+        Some(_) => return Err("invalid: inherit must be true or false".to_string()),
+    };
+    Ok(Some((parent, inherits)))
+}
+
+/// Can `creator` create `group_id` under `parent`? Returns the OK rejection
+/// reason, if any.
+pub async fn check_parent(db: &Db, group_id: &str, parent: &str, creator: &str) -> anyhow::Result<Option<String>> {
+    if parent == group_id || db.group_exists(group_id).await? {
+        return Ok(Some("invalid: a sub-group must be a new group".to_string()));
+    }
+    if !db.group_exists(parent).await? {
+        return Ok(Some("invalid: parent group not found".to_string()));
+    }
+    if !db.is_admin(parent, creator).await? {
+        return Ok(Some("restricted: only a parent group admin can add sub-groups".to_string()));
+    }
+    // The parent's depth below its root: walk up to MAX_DEPTH links.
+    let mut depth = 0;
+    let mut cursor = parent.to_string();
+    while let Some((up, _)) = db.group_parent(&cursor).await? {
+        depth += 1;
+        if depth >= MAX_DEPTH {
+            return Ok(Some(format!("invalid: sub-groups nest at most {MAX_DEPTH} levels deep")));
+        }
+        cursor = up;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(tags: Vec<Vec<&str>>) -> Event {
+        Event {
+            id: "x".into(),
+            pubkey: "admin".into(),
+            created_at: 1,
+            kind: 9007,
+            tags: tags.into_iter().map(|t| t.into_iter().map(String::from).collect()).collect(),
+            content: String::new(),
+            sig: String::new(),
+        }
+    }
+
+    #[test]
+    fn reads_parent_and_inherit_tags() {
+        assert_eq!(requested_parent(&event(vec![vec!["h", "c"]])), Ok(None));
+        assert_eq!(
+            requested_parent(&event(vec![vec!["h", "c"], vec!["parent", "g"]])),
+            Ok(Some(("g".to_string(), true)))
+        );
+        assert_eq!(
+            requested_parent(&event(vec![vec!["parent", "g"], vec!["inherit", "false"]])),
+            Ok(Some(("g".to_string(), false)))
+        );
+        assert!(requested_parent(&event(vec![vec!["parent", "g"], vec!["inherit", "no"]])).is_err());
+    }
+}
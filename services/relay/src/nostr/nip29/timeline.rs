@@ -0,0 +1,187 @@
+//! NIP-29 timeline references: `["previous", "<id prefix>", ...]` tags on
+//! h-tagged events.
+//!
+//! Clients tag a group event with the first 8 hex chars of recent events they
+//! saw in that group, so the event can't be replayed into another relay's
+//! timeline out of context. The relay checks each reference against the last
+//! `window` events it holds for the group (`RELAY_PREVIOUS_WINDOW`), with a
+//! strictness set by `RELAY_PREVIOUS_CHECK`:
+//!   - `off`: no check,
+//!   - `lenient` (default): references must all be known, but may be omitted,
+//!   - `strict`: as lenient, and an event in a group with history must carry
+//!     at least one reference. Join/leave requests (9021/9022) are exempt —
+//!     the requester usually can't read the group yet.
+
+use crate::nostr::event::Event;
+
+/// Shortest reference accepted (NIP-29 uses 8 hex chars).
+pub const MIN_PREFIX_LEN: usize = 8;
+
+/// Default number of recent group events a reference may point at.
+pub const DEFAULT_WINDOW: i64 = 100;
+
+/// Kinds never required to carry a reference (join and leave requests).
+const EXEMPT_KINDS: [i32; 2] = [9021, 9022];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness {
+    Off,
+    #[default]
+    Lenient,
+    Strict,
+}
+
+/// How `previous` tags are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimelinePolicy {
+    pub strictness: Strictness,
+    /// How many of the group's most recent events a reference may match.
+    pub window: i64,
+}
+
+impl Default for TimelinePolicy {
+    fn default() -> Self {
+        Self {
+            strictness: Strictness::default(),
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+impl TimelinePolicy {
+    /// Parse `RELAY_PREVIOUS_CHECK` (`off` / `lenient` / `strict`) and
+    /// `RELAY_PREVIOUS_WINDOW` (events). Unknown values keep the default.
+    pub fn from_env_values(check: Option<&str>, window: Option<&str>) -> Self {
+        let strictness = match check.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("off" | "false" | "0") => Strictness::Off,
+            Some("strict") => Strictness::Strict,
+            _ => Strictness::Lenient,
+        };
+        let window = window
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&n: &i64| n > 0)
+            .unwrap_or(DEFAULT_WINDOW);
+        Self { strictness, window }
+    }
+
+    /// Does `event` need the group's recent ids looked up at all?
+    pub fn applies_to(&self, event: &Event) -> bool {
+        match self.strictness {
+            Strictness::Off => false,
+            Strictness::Lenient => !references(event).is_empty(),
+            Strictness::Strict => true,
+        }
+    }
+}
+
+/// The `previous` tag values of an event, in tag order.
+pub fn references(event: &Event) -> Vec<&str> {
+    event
+        .tags
+        .iter()
+        .filter(|t| t.first().map(|s| s.as_str()) == Some("previous"))
+        .flat_map(|t| t.iter().skip(1))
+        .map(|s| s.as_str())
+        .collect()
+}
+
+/// Check `event`'s references against `recent_ids`, the group's latest events
+/// (newest first, at most the policy window). Returns the rejection reason.
+pub fn check(policy: &TimelinePolicy, event: &Event, recent_ids: &[String]) -> Result<(), String> {
+    if policy.strictness == Strictness::Off {
+        return Ok(());
+    }
+    let refs = references(event);
+    for prefix in &refs {
+        let well_formed = (MIN_PREFIX_LEN..=64).contains(&prefix.len())
+            && prefix.chars().all(|c| c.is_ascii_hexdigit());
+        if !well_formed {
+            return Err("invalid: malformed previous tag".to_string());
+        }
+        let prefix = prefix.to_ascii_lowercase();
+        if !recent_ids.iter().any(|id| id.starts_with(&prefix)) {
+            return Err(format!(
+                "invalid: previous tag {prefix} is not among this group's recent events"
+            ));
+        }
+    }
+    if policy.strictness == Strictness::Strict
+        && refs.is_empty()
+        && !recent_ids.is_empty()
+        && !EXEMPT_KINDS.contains(&event.kind)
+    {
+        return Err("invalid: previous tag required in this group".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: i32, previous: &[&str]) -> Event {
+        let mut tags = vec![vec!["h".to_string(), "g".to_string()]];
+        if !previous.is_empty() {
+            let mut tag = vec!["previous".to_string()];
+            tag.extend(previous.iter().map(|p| p.to_string()));
+            tags.push(tag);
+        }
+        Event {
+            id: "x".into(),
+            pubkey: "p".into(),
+            created_at: 1,
+            kind,
+            tags,
+            content: String::new(),
+            sig: String::new(),
+        }
+    }
+
+    fn recent() -> Vec<String> {
+        vec!["abcdef0123".repeat(6) + "abcd", "0011223344".repeat(6) + "5566"]
+    }
+
+    #[test]
+    fn parses_strictness_and_window() {
+        assert_eq!(TimelinePolicy::from_env_values(None, None), TimelinePolicy::default());
+        let strict = TimelinePolicy::from_env_values(Some(" Strict "), Some("25"));
+        assert_eq!(strict.strictness, Strictness::Strict);
+        assert_eq!(strict.window, 25);
+        assert_eq!(TimelinePolicy::from_env_values(Some("off"), Some("-3")).strictness, Strictness::Off);
+        assert_eq!(TimelinePolicy::from_env_values(Some("bogus"), Some("-3")).window, DEFAULT_WINDOW);
+    }
+
+    #[test]
+    fn lenient_rejects_unknown_references_only() {
+        let policy = TimelinePolicy::default();
+        assert!(check(&policy, &event(9, &["abcdef01", "00112233"]), &recent()).is_ok());
+        assert!(check(&policy, &event(9, &["ABCDEF01"]), &recent()).is_ok());
+        assert!(check(&policy, &event(9, &[]), &recent()).is_ok());
+        let err = check(&policy, &event(9, &["abcdef01", "deadbeef"]), &recent()).unwrap_err();
+        assert!(err.contains("deadbeef"), "{err}");
+        assert!(check(&policy, &event(9, &["abc"]), &recent()).is_err());
+        assert!(check(&policy, &event(9, &["zzzzzzzz"]), &recent()).is_err());
+    }
+
+    #[test]
+    fn strict_requires_a_reference_once_the_group_has_history() {
+        let policy = TimelinePolicy {
+            strictness: Strictness::Strict,
+            ..Default::default()
+        };
+        assert!(check(&policy, &event(9, &[]), &recent()).is_err());
+        assert!(check(&policy, &event(9, &[]), &[]).is_ok());
+        assert!(check(&policy, &event(9021, &[]), &recent()).is_ok());
+        assert!(check(&policy, &event(9, &["00112233"]), &recent()).is_ok());
+    }
+
+    #[test]
+    fn off_checks_nothing() {
+        let policy = TimelinePolicy {
+            strictness: Strictness::Off,
+            ..Default::default()
+        };
+        assert!(!policy.applies_to(&event(9, &["deadbeef"])));
+        assert!(check(&policy, &event(9, &["deadbeef"]), &recent()).is_ok());
+    }
+}
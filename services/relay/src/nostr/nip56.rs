@@ -0,0 +1,105 @@
+//! NIP-56 reports (kind:1984) routed to group moderators.
+//!
+//! A report naming an event posted to a NIP-29 group is filed in that group's
+//! moderation queue and hidden from everyone but its author and the group's
+//! moderators — its admins (inherited ones included) and, for a backend space,
+//! holders of an admin role or one granting `MANAGE_MESSAGES` — so the
+//! reported user never learns who reported them. It is not broadcast live:
+//! moderators read it with a REQ or through the NIP-86
+//! `listeventsneedingmoderation` call. Reports about anything else are stored
+//! like any other event.
+//!
+//! Queue entries are resolved when the target is removed (9005 moderator
+//! deletion, the author's own kind:5, NIP-86 `banevent`) or dismissed with
+//! NIP-86 `allowevent`.
+
+use crate::db::Db;
+use crate::nostr::event::Event;
+
+pub const KIND_REPORT: i32 = 1984;
+
+/// One (report, reported event) entry in a group's moderation queue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiledReport {
+    pub report_id: String,
+    pub target_event_id: String,
+    pub group_id: String,
+    pub reporter: String,
+    /// The NIP-56 report type (`spam`, `illegal`, ...), if given.
+    pub report_type: Option<String>,
+    pub created_at: i64,
+}
+
+/// The event ids a report names, with their report type (`["e", id, type]`).
+pub fn reported_events(tags: &[Vec<String>]) -> Vec<(String, Option<String>)> {
+    tags.iter()
+        .filter(|t| t.first().map(|s| s.as_str()) == Some("e"))
+        .filter_map(|t| Some((t.get(1)?.clone(), t.get(2).filter(|s| !s.is_empty()).cloned())))
+        .collect()
+}
+
+/// Handle kind:1984. `None` when the report names no stored group event — the
+/// caller then treats it as a regular event.
+pub async fn handle_report(db: &Db, event: &Event) -> anyhow::Result<Option<Vec<String>>> {
+    let mut filed = Vec::new();
+    for (target_event_id, report_type) in reported_events(&event.tags) {
+        let group_id = db
+            .get_event_by_id(&target_event_id)
+            .await?
+            .and_then(|target| target.get_tag_value("h"));
+        if let Some(group_id) = group_id {
+            filed.push(FiledReport {
+                report_id: event.id.clone(),
+                target_event_id,
+                group_id,
+                reporter: event.pubkey.clone(),
+                report_type,
+                created_at: event.created_at,
+            });
+        }
+    }
+    if filed.is_empty() {
+        return Ok(None);
+    }
+
+    // File before storing: the queue row is what hides the report, so it must
+    // exist before the event becomes readable.
+    db.file_reports(&filed).await?;
+    let inserted = db.store_event(event).await?;
+    if !inserted && db.get_event_by_id(&event.id).await?.is_none() {
+        // Refused by a tombstone or ban rather than a duplicate.
+        db.withdraw_report(&event.id).await?;
+        return Ok(Some(vec![format!(
+            r#"["OK","{}",false,"blocked: this report was refused"]"#,
+            event.id
+        )]));
+    }
+
+    tracing::info!(
+        reporter = crate::protocol::handler::log_prefix(&event.pubkey),
+        targets = filed.len(),
+        group_id = %filed[0].group_id,
+        "NIP-56 report filed"
+    );
+    let note = if inserted { "" } else { "duplicate:" };
+    Ok(Some(vec![format!(r#"["OK","{}",true,"{}"]"#, event.id, note)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reported_events_reads_e_tags_and_types() {
+        let tags = vec![
+            vec!["e".to_string(), "a".to_string(), "spam".to_string()],
+            vec!["p".to_string(), "pk".to_string(), "spam".to_string()],
+            vec!["e".to_string(), "b".to_string()],
+            vec!["e".to_string()],
+        ];
+        assert_eq!(
+            reported_events(&tags),
+            vec![("a".to_string(), Some("spam".to_string())), ("b".to_string(), None)]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! NIP-57 zap receipts (kind:9735): the amount a receipt records, read from
+//! its `bolt11` invoice, and the checks a receipt must pass to be stored.
+//!
+//! A receipt is published by the recipient's LNURL provider once the invoice
+//! is paid. It embeds the sender's signed zap request (kind:9734) in its
+//! `description` tag; the relay checks that the request is genuine, that it
+//! zaps the same recipient/event as the receipt, and that the invoice is for
+//! the amount the sender asked for. Operators can also pin the provider keys
+//! allowed to sign receipts (`Config::zap_provider_pubkeys`).
+
+use std::collections::HashSet;
+
+use crate::nostr::event::Event;
+use crate::nostr::verify::verify_event;
+
+pub const KIND_ZAP_RECEIPT: i32 = 9735;
+pub const KIND_ZAP_REQUEST: i32 = 9734;
+
+/// Millisatoshis per bitcoin.
+const MSATS_PER_BTC: i64 = 100_000_000_000;
+
+/// The amount of a BOLT-11 invoice in millisatoshis, from its human-readable
+/// part (`ln<currency><amount><multiplier>`). `None` for an amountless or
+/// malformed invoice.
+pub fn bolt11_msats(invoice: &str) -> Option<i64> {
+    let invoice = invoice.trim().to_ascii_lowercase();
+    // The data part's charset has no '1', so the last one separates it.
+    let hrp = &invoice[..invoice.rfind('1')?];
+    let rest = hrp.strip_prefix("ln")?.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let digits_end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let (digits, multiplier) = rest.split_at(digits_end);
+    if digits.is_empty() || digits.starts_with('0') {
+        return None;
+    }
+    let amount: i64 = digits.parse().ok()?;
+    match multiplier {
+        "" => amount.checked_mul(MSATS_PER_BTC),
+        "m" => amount.checked_mul(MSATS_PER_BTC / 1_000),
+        "u" => amount.checked_mul(MSATS_PER_BTC / 1_000_000),
+        "n" => amount.checked_mul(MSATS_PER_BTC / 1_000_000_000),
+        // A pico-bitcoin is a tenth of a millisatoshi.
+        "p" if amount % 10 == 0 => Some(amount / 10),
+        _ => None,
+    }
+}
+
+/// The amount a zap receipt records, in millisatoshis.
+pub fn receipt_msats(event: &Event) -> Option<i64> {
+    bolt11_msats(&event.get_tag_value("bolt11")?)
+}
+
+/// Why `receipt` can't be accepted, if it can't. An empty `providers` set
+/// accepts any signer.
+pub fn validate_receipt(receipt: &Event, providers: &HashSet<String>) -> Result<(), String> {
+    if !providers.is_empty() && !providers.contains(&receipt.pubkey) {
+        return Err("zap receipt not signed by an allowed provider".into());
+    }
+    let description = receipt
+        .get_tag_value("description")
+        .ok_or("zap receipt needs a description tag")?;
+    let request: Event =
+        serde_json::from_str(&description).map_err(|_| "zap receipt description is not an event")?;
+    if request.kind != KIND_ZAP_REQUEST || !verify_event(&request) {
+        return Err("zap receipt description is not a signed zap request".into());
+    }
+
+    let recipients: Vec<_> = request.tags.iter().filter(|t| t.first().is_some_and(|n| n == "p")).collect();
+    if recipients.len() != 1 {
+        return Err("zap request must have exactly one p tag".into());
+    }
+    for name in ["p", "e", "a"] {
+        if receipt.get_tag_value(name) != request.get_tag_value(name) {
+            return Err(format!("zap receipt {name} tag does not match its request"));
+        }
+    }
+    if receipt.get_tag_value("P").is_some_and(|sender| sender != request.pubkey) {
+        return Err("zap receipt P tag is not the zap sender".into());
+    }
+
+    let paid = receipt_msats(receipt).ok_or("zap receipt needs a bolt11 invoice with an amount")?;
+    if let Some(amount) = request.get_tag_value("amount") {
+        let asked: i64 = amount.trim().parse().map_err(|_| "zap request amount is not a number")?;
+        if asked != paid {
+            return Err("zap receipt bolt11 amount does not match the request amount".into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay_identity::RelayIdentity;
+
+    fn tag(name: &str, value: &str) -> Vec<String> {
+        vec![name.to_string(), value.to_string()]
+    }
+
+    /// A receipt from `provider` for `request`, with the given invoice.
+    fn receipt(provider: &RelayIdentity, request: &Event, bolt11: &str) -> Event {
+        let mut tags = vec![tag("bolt11", bolt11), tag("description", &serde_json::to_string(request).unwrap())];
+        tags.extend(request.tags.iter().filter(|t| t[0] == "p" || t[0] == "e").cloned());
+        tags.push(tag("P", &request.pubkey));
+        provider.sign_event(KIND_ZAP_RECEIPT, tags, "")
+    }
+
+    #[test]
+    fn reads_bolt11_amounts() {
+        assert_eq!(bolt11_msats("lnbc2500u1pvjluezpp5qqqsyqcyq5"), Some(250_000_000));
+        assert_eq!(bolt11_msats("lnbc20m1pvjluezpp5qqqsyqcyq5"), Some(2_000_000_000));
+        assert_eq!(bolt11_msats("LNBC10N1PVJLUEZ"), Some(1_000));
+        assert_eq!(bolt11_msats("lnbcrt50p1xyz"), Some(5));
+        assert_eq!(bolt11_msats("lntb1500n1xyz"), Some(150_000));
+        assert_eq!(bolt11_msats("lnbc1pvjluezpp5qqqsyqcyq5"), None);
+        assert_eq!(bolt11_msats("lnbc15p1xyz"), None);
+        assert_eq!(bolt11_msats("lnbc25x1xyz"), None);
+        assert_eq!(bolt11_msats("not an invoice"), None);
+    }
+
+    #[test]
+    fn accepts_a_receipt_matching_its_request() {
+        let (sender, provider) = (RelayIdentity::new(None, "test"), RelayIdentity::new(None, "test"));
+        let request = sender.sign_event(
+            KIND_ZAP_REQUEST,
+            vec![tag("p", "bob"), tag("e", "msg"), tag("amount", "21000")],
+            "",
+        );
+        let ok = receipt(&provider, &request, "lnbc210n1xyz");
+        assert_eq!(validate_receipt(&ok, &HashSet::new()), Ok(()));
+        assert_eq!(validate_receipt(&ok, &HashSet::from([provider.pubkey.clone()])), Ok(()));
+        assert!(validate_receipt(&ok, &HashSet::from(["someone".to_string()])).is_err());
+    }
+
+    #[test]
+    fn rejects_forged_receipts() {
+        let (sender, provider) = (RelayIdentity::new(None, "test"), RelayIdentity::new(None, "test"));
+        let request = sender.sign_event(
+            KIND_ZAP_REQUEST,
+            vec![tag("p", "bob"), tag("e", "msg"), tag("amount", "21000")],
+            "",
+        );
+        let none = HashSet::new();
+        // Invoice for another amount.
+        assert!(validate_receipt(&receipt(&provider, &request, "lnbc1u1xyz"), &none).is_err());
+        // Tampered request.
+        let mut tampered = request.clone();
+        tampered.tags[0][1] = "mallory".into();
+        assert!(validate_receipt(&receipt(&provider, &tampered, "lnbc210n1xyz"), &none).is_err());
+        // Receipt pointing at another event.
+        let mut moved = receipt(&provider, &request, "lnbc210n1xyz");
+        moved.tags.retain(|t| t[0] != "e");
+        moved.tags.push(tag("e", "other"));
+        let moved = provider.sign_event(KIND_ZAP_RECEIPT, moved.tags, "");
+        assert!(validate_receipt(&moved, &none).is_err());
+        // No request at all.
+        let bare = provider.sign_event(KIND_ZAP_RECEIPT, vec![tag("p", "bob"), tag("bolt11", "lnbc210n1xyz")], "");
+        assert!(validate_receipt(&bare, &none).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+//! NIP-59 gift wraps (NIP-17 DMs and group rooms): recipient-only read access.
+//!
+//! A gift wrap is signed by a throwaway key and addressed to one `p` tag, so
+//! neither "author sees own event" nor the `visibility`/`h` rules apply. These
+//! kinds are served only to a connection authenticated as a `p` target — on
+//! REQ (both backends, including NIP-50 search) and on live broadcast.
+
+/// Kinds readable only by their `p`-tagged recipient.
+pub const RECIPIENT_ONLY_KINDS: [i32; 2] = [1059, 1060];
+
+/// [`RECIPIENT_ONLY_KINDS`] as an SQL list literal for `kind NOT IN …`
+/// predicates. Keep in sync with the array.
+pub const RECIPIENT_ONLY_KINDS_SQL: &str = "(1059, 1060)";
+
+pub fn is_recipient_only(kind: i32) -> bool {
+    RECIPIENT_ONLY_KINDS.contains(&kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sql_list_matches_kinds() {
+        let listed: Vec<i32> = RECIPIENT_ONLY_KINDS_SQL
+            .trim_matches(|c| c == '(' || c == ')')
+            .split(',')
+            .map(|k| k.trim().parse().unwrap())
+            .collect();
+        assert_eq!(listed, RECIPIENT_ONLY_KINDS);
+        assert!(is_recipient_only(1059));
+        assert!(!is_recipient_only(14));
+    }
+}
@@ -0,0 +1,46 @@
+//! NIP-62 request to vanish (kind 62).
+//!
+//! A signed kind:62 asks the relays named in its `relay` tags (or every relay,
+//! via `ALL_RELAYS`) to delete everything its author ever published there up
+//! to the request's `created_at`, plus the NIP-59 gift wraps addressed to them.
+//! The relay also drops the author's group memberships/roles and remembers the
+//! cutoff, so older events re-broadcast from elsewhere are refused. The request
+//! itself is kept.
+
+use crate::protocol::nip42::normalize_relay_url;
+
+pub const KIND_VANISH: i32 = 62;
+
+/// `relay` tag value that targets every relay.
+pub const ALL_RELAYS: &str = "ALL_RELAYS";
+
+/// Is this vanish request addressed to the relay at `relay_url`? Compared
+/// after the same normalization as NIP-42 AUTH (scheme, case, trailing slash,
+/// default port, loopback aliases). Deliberately strict even on the embedded
+/// relay — this is irreversible, so a request aimed elsewhere must not match.
+pub fn addressed_to(tags: &[Vec<String>], relay_url: &str) -> bool {
+    let want = normalize_relay_url(relay_url);
+    tags.iter()
+        .filter(|t| t.first().map(String::as_str) == Some("relay"))
+        .filter_map(|t| t.get(1))
+        .any(|u| u == ALL_RELAYS || normalize_relay_url(u) == want)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relay(u: &str) -> Vec<String> {
+        vec!["relay".to_string(), u.to_string()]
+    }
+
+    #[test]
+    fn matches_own_url_or_all_relays_only() {
+        let me = "wss://relay.thewired.app";
+        assert!(addressed_to(&[relay("wss://Relay.TheWired.app/")], me));
+        assert!(addressed_to(&[relay("wss://other.example"), relay(ALL_RELAYS)], me));
+        assert!(!addressed_to(&[relay("wss://other.example")], me));
+        assert!(!addressed_to(&[vec!["relay".to_string()]], me));
+        assert!(!addressed_to(&[], me));
+    }
+}
@@ -0,0 +1,155 @@
+//! Hold REQs that race ahead of NIP-42 AUTH (NIP29_CHAT Phase 3).
+//!
+//! Clients REQ in `onopen`, before they've even read the AUTH challenge, so an
+//! h-tagged REQ is answered as anonymous: zero group history and a bare EOSE.
+//! Instead, for a short window after the challenge is sent, REQs that touch
+//! private data are parked here and replayed through the normal REQ path once
+//! the connection authenticates — or, if it never does, when the window ends
+//! (they then get exactly the anonymous answer they would have got up front).
+
+use std::time::{Duration, Instant};
+
+/// Cap on REQs held per connection; the overflow is answered immediately.
+pub const MAX_HELD_REQS: usize = 32;
+
+/// The REQs one connection is holding until AUTH (raw frames, arrival order).
+pub struct HeldReqs {
+    deadline: Instant,
+    reqs: Vec<(String, String)>,
+}
+
+impl HeldReqs {
+    /// Start the hold window now (call when the AUTH challenge is sent). A
+    /// zero `window` disables holding.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            deadline: Instant::now() + window,
+            reqs: Vec::new(),
+        }
+    }
+
+    /// Are REQs still being held (window not yet elapsed)?
+    pub fn is_open(&self) -> bool {
+        Instant::now() < self.deadline
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reqs.is_empty()
+    }
+
+    /// Park a REQ frame. A REQ reusing a held subscription id replaces it (as
+    /// it would replace a live subscription). `false` if the window is over or
+    /// the buffer is full — the caller serves the REQ right away.
+    pub fn hold(&mut self, sub_id: String, raw: String) -> bool {
+        if !self.is_open() {
+            return false;
+        }
+        if let Some(slot) = self.reqs.iter_mut().find(|(id, _)| *id == sub_id) {
+            slot.1 = raw;
+            return true;
+        }
+        if self.reqs.len() >= MAX_HELD_REQS {
+            return false;
+        }
+        self.reqs.push((sub_id, raw));
+        true
+    }
+
+    /// Forget a held REQ (the client CLOSEd it before it was replayed).
+    pub fn release(&mut self, sub_id: &str) -> bool {
+        let before = self.reqs.len();
+        self.reqs.retain(|(id, _)| id != sub_id);
+        self.reqs.len() != before
+    }
+
+    /// Take every held frame for replay and end the window, so later REQs are
+    /// served directly.
+    pub fn drain(&mut self) -> Vec<String> {
+        self.deadline = Instant::now();
+        self.reqs.drain(..).map(|(_, raw)| raw).collect()
+    }
+}
+
+/// If `text` is a REQ an unauthenticated connection should hold, its
+/// subscription id. In auth-required mode every REQ qualifies (it would only
+/// be refused); otherwise a REQ qualifies when any filter is `#h`-scoped, since
+/// group content is what anonymous reads silently drop.
+pub fn holdable_req(text: &str, auth_required: bool) -> Option<String> {
+    let msg: serde_json::Value = serde_json::from_str(text).ok()?;
+    let arr = msg.as_array()?;
+    if arr.first()?.as_str()? != "REQ" {
+        return None;
+    }
+    let sub_id = arr.get(1)?.as_str()?;
+    let touches_groups = arr[2..].iter().any(|f| {
+        f.get("#h")
+            .and_then(|h| h.as_array())
+            .is_some_and(|h| !h.is_empty())
+    });
+    (auth_required || touches_groups).then(|| sub_id.to_string())
+}
+
+/// Subscription id of a CLOSE frame, if `text` is one.
+pub fn close_sub_id(text: &str) -> Option<String> {
+    let msg: serde_json::Value = serde_json::from_str(text).ok()?;
+    if msg.get(0)?.as_str()? != "CLOSE" {
+        return None;
+    }
+    Some(msg.get(1)?.as_str()?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_group_scoped_reqs_are_held_when_auth_is_optional() {
+        let group = r##"["REQ","chat",{"#h":["g"],"kinds":[9]}]"##;
+        let public = r#"["REQ","feed",{"kinds":[1]}]"#;
+        assert_eq!(holdable_req(group, false).as_deref(), Some("chat"));
+        assert_eq!(holdable_req(public, false), None);
+        assert_eq!(holdable_req(public, true).as_deref(), Some("feed"));
+        assert_eq!(holdable_req(r#"["CLOSE","chat"]"#, true), None);
+        assert_eq!(holdable_req("not json", true), None);
+    }
+
+    #[test]
+    fn hold_replaces_same_sub_and_release_forgets_it() {
+        let mut held = HeldReqs::new(Duration::from_secs(60));
+        assert!(held.hold("a".into(), "first".into()));
+        assert!(held.hold("b".into(), "other".into()));
+        assert!(held.hold("a".into(), "second".into()));
+        assert!(held.release("b"));
+        assert!(!held.release("b"));
+        assert_eq!(held.drain(), vec!["second".to_string()]);
+        // Draining ends the window.
+        assert!(!held.is_open());
+        assert!(!held.hold("c".into(), "late".into()));
+    }
+
+    #[test]
+    fn zero_window_never_holds() {
+        let mut held = HeldReqs::new(Duration::ZERO);
+        assert!(!held.hold("a".into(), "req".into()));
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn overflow_is_served_directly() {
+        let mut held = HeldReqs::new(Duration::from_secs(60));
+        for i in 0..MAX_HELD_REQS {
+            assert!(held.hold(i.to_string(), String::new()));
+        }
+        assert!(!held.hold("one-too-many".into(), String::new()));
+    }
+
+    #[test]
+    fn close_sub_id_parses_close_frames_only() {
+        assert_eq!(close_sub_id(r#"["CLOSE","chat"]"#).as_deref(), Some("chat"));
+        assert_eq!(close_sub_id(r#"["REQ","chat",{}]"#), None);
+    }
+}
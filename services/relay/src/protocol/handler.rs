@@ -3,9 +3,12 @@ use std::sync::Arc;
 use tokio::sync::broadcast;
 use tokio::sync::Mutex;
 
+use crate::config::AuthPolicy;
+use crate::membership_bus::{self, MembershipChange};
 use crate::nostr::event::Event;
 use crate::nostr::filter::Filter;
-use crate::nostr::membership_gate::{evaluate_publish_gate, PublishVerdict};
+use crate::nostr::membership_gate::{evaluate_mute_gate, evaluate_publish_gate, PublishVerdict};
+use crate::nostr::nip29::{audit, migration, slow_mode, timeline};
 use crate::nostr::verify::verify_event;
 use crate::protocol::subscription::SubscriptionManager;
 use crate::server::AppState;
@@ -13,7 +16,7 @@ use crate::server::AppState;
 /// Char-boundary-safe prefix for logging untrusted strings (#113). Slicing an
 /// unverified event's id/pubkey with `&s[..12]` panics on a short string or a
 /// multi-byte char straddling the boundary; this never panics.
-fn log_prefix(s: &str) -> &str {
+pub(crate) fn log_prefix(s: &str) -> &str {
     let mut end = s.len().min(12);
     while end > 0 && !s.is_char_boundary(end) {
         end -= 1;
@@ -26,7 +29,7 @@ pub async fn handle_message(
     text: &str,
     state: &Arc<AppState>,
     subscriptions: &Arc<Mutex<SubscriptionManager>>,
-    authed_pubkey: &mut Option<String>,
+    authed_pubkeys: &mut Vec<String>,
     space_memberships: &mut HashSet<String>,
     auth_challenge: &str,
     broadcast_tx: &broadcast::Sender<Event>,
@@ -40,11 +43,34 @@ pub async fn handle_message(
 
     tracing::debug!(msg_type, "Received");
 
+    // NIP-42 auth-required mode: until the connection has authenticated, only
+    // AUTH and CLOSE are served; REQ/EVENT get the standard `auth-required:`
+    // CLOSED/OK so the client knows to answer the challenge and retry.
+    if state.config.auth_policy.is_required() {
+        match msg_type {
+            "EVENT" if authed_pubkeys.is_empty() => {
+                let id = msg.get(1).and_then(|e| e.get("id")).and_then(|v| v.as_str()).unwrap_or("");
+                return vec![format!(
+                    r#"["OK","{}",false,"auth-required: this relay requires authentication"]"#,
+                    id
+                )];
+            }
+            "REQ" if authed_pubkeys.is_empty() => {
+                let sub_id = msg.get(1).and_then(|v| v.as_str()).unwrap_or("");
+                return vec![format!(
+                    r#"["CLOSED","{}","auth-required: this relay requires authentication"]"#,
+                    sub_id
+                )];
+            }
+            _ => {}
+        }
+    }
+
     match msg_type {
-        "EVENT" => handle_event(msg, state, broadcast_tx).await,
-        "REQ" => handle_req(msg, state, subscriptions, authed_pubkey).await,
+        "EVENT" => handle_event(msg, state, authed_pubkeys, broadcast_tx).await,
+        "REQ" => handle_req(msg, state, subscriptions, authed_pubkeys).await,
         "CLOSE" => handle_close(msg, subscriptions).await,
-        "AUTH" => handle_auth(msg, state, authed_pubkey, space_memberships, auth_challenge).await,
+        "AUTH" => handle_auth(msg, state, authed_pubkeys, space_memberships, auth_challenge).await,
         _ => {
             tracing::debug!(msg_type, "Unknown message type");
             vec![format!(r#"["NOTICE","unknown message type: {msg_type}"]"#)]
@@ -81,6 +107,52 @@ async fn republish_metadata_if_ok(
     }
 }
 
+/// After a successful membership-changing NIP-29 op (9000/9001/9007/9008/9021/9022),
+/// publish the implied changes on the membership bus so every open connection
+/// updates its read cache — and CLOSEs revoked subscriptions — immediately.
+fn notify_membership_if_ok(state: &Arc<AppState>, result: &[String], changes: Vec<MembershipChange>) {
+    if !op_succeeded(result) {
+        return;
+    }
+    for change in changes {
+        let _ = state.membership_tx.send(change);
+    }
+}
+
+/// Extend membership changes to the sub-groups that inherit from each changed
+/// group (`nip29::subgroups`), whose members change along with it. Looked up
+/// before the op, so a deleted group's children are still found.
+async fn with_inherited(state: &Arc<AppState>, changes: Vec<MembershipChange>) -> Vec<MembershipChange> {
+    let mut out = Vec::with_capacity(changes.len());
+    for change in changes {
+        let descendants = state
+            .pool
+            .inheriting_descendants(&change.group_id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(group_id = %change.group_id, error = %e, "Sub-group lookup failed");
+                Vec::new()
+            });
+        for group_id in descendants {
+            out.push(MembershipChange {
+                group_id,
+                pubkey: change.pubkey.clone(),
+                parent: None,
+            });
+        }
+        out.push(change);
+    }
+    out
+}
+
+/// After a successful NIP-29 management op, append it to the group's
+/// moderation audit log.
+async fn audit_if_ok(state: &Arc<AppState>, result: &[String], event: &Event) {
+    if op_succeeded(result) {
+        audit::record(&state.pool, &state.relay_identity, event).await;
+    }
+}
+
 /// Store + broadcast a NIP-29 management event ONLY if its handler reported
 /// success (#68). Previously these were stored/broadcast unconditionally, so a
 /// non-admin's rejected 9000/9001/9005/... still propagated to every subscriber.
@@ -98,9 +170,112 @@ async fn store_and_broadcast_if_ok(
     }
 }
 
+/// NIP-86 bans, from the cached lists (see `nip86::Bans`).
+fn management_block(state: &AppState, event: &Event) -> Option<&'static str> {
+    state
+        .bans
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .block_reason(event, state.config.allowlist_only)
+}
+
+/// NIP-13: the highest of the relay-wide, per-kind and (for h-tagged events)
+/// group minimum difficulty, with the group's admins exempt from the group's
+/// own minimum. Returns the rejection reason, if any. Fails closed.
+async fn pow_shortfall(state: &AppState, event: &Event) -> Option<String> {
+    let mut required = state.config.pow_policy.required_for(event.kind);
+    if let Some(group_id) = event.get_tag_value("h") {
+        let lookup = async {
+            let group_min = state.pool.group_min_pow(&group_id).await?;
+            let exempt = group_min > required && state.pool.is_admin(&group_id, &event.pubkey).await?;
+            anyhow::Ok(if exempt { 0 } else { group_min })
+        };
+        match lookup.await {
+            Ok(group_min) => required = required.max(group_min),
+            Err(e) => {
+                tracing::error!(error = %e, "Group PoW lookup failed");
+                return Some("error: could not check proof-of-work policy".to_string());
+            }
+        }
+    }
+    crate::nostr::nip13::check(event, required).err()
+}
+
+/// NIP-29 `previous` tags on an h-tagged event must point at the group's
+/// recent events (see `nip29::timeline`). Returns the rejection reason, if
+/// any. Fails closed.
+///
+/// Only a member's event is checked: answering anyone else would let them
+/// probe a group's ids by prefix. Their event is left to the membership gate
+/// or the group op's own permission checks.
+async fn timeline_mismatch(state: &AppState, event: &Event) -> Option<String> {
+    let policy = &state.config.timeline_policy;
+    let group_id = event.get_tag_value("h")?;
+    if !policy.applies_to(event) {
+        return None;
+    }
+    match state.pool.is_member(&group_id, &event.pubkey).await {
+        Ok(true) => {}
+        Ok(false) => return None,
+        Err(e) => {
+            tracing::error!(error = %e, group_id, "Membership lookup failed; rejecting publish");
+            return Some("error: could not check previous tags".to_string());
+        }
+    }
+    match state.pool.recent_group_event_ids(&group_id, policy.window).await {
+        Ok(recent) => timeline::check(policy, event, &recent).err(),
+        Err(e) => {
+            tracing::error!(error = %e, group_id, "Group timeline lookup failed");
+            Some("error: could not check previous tags".to_string())
+        }
+    }
+}
+
+/// The group's write policy (announcement channel, allowed kinds) for a
+/// content event. Fails closed.
+async fn check_write_policy(state: &AppState, group_id: &str, event: &Event) -> Result<(), String> {
+    let lookup = async {
+        let policy = state.pool.group_policy(group_id).await?;
+        let is_admin = policy.announcement && state.pool.is_admin(group_id, &event.pubkey).await?;
+        anyhow::Ok(policy.check_write(event.kind, is_admin))
+    };
+    lookup.await.unwrap_or_else(|e| {
+        tracing::error!(error = %e, group_id, "Group policy lookup failed");
+        Err("error: could not check group policy".to_string())
+    })
+}
+
+/// Group slow mode for a member's kind:9: admins are exempt; anyone else must
+/// wait out the group's interval since their last stored message. `Ok(Some)`
+/// holds the post's claimed slot, to release if it isn't stored. Fails closed.
+async fn check_slow_mode(
+    state: &AppState,
+    group_id: &str,
+    pubkey: &str,
+) -> Result<Option<slow_mode::Reservation>, String> {
+    let lookup = async {
+        let secs = state.pool.group_slow_mode(group_id).await?;
+        let exempt = secs == 0 || state.pool.is_admin(group_id, pubkey).await?;
+        anyhow::Ok(if exempt { 0 } else { secs })
+    };
+    match lookup.await {
+        Ok(0) => Ok(None),
+        Ok(secs) => state
+            .slow_mode
+            .reserve(group_id, pubkey, std::time::Duration::from_secs(secs as u64))
+            .map(Some)
+            .map_err(slow_mode::wait_reason),
+        Err(e) => {
+            tracing::error!(error = %e, group_id, "Slow mode lookup failed");
+            Err("error: could not check slow mode".to_string())
+        }
+    }
+}
+
 async fn handle_event(
     msg: serde_json::Value,
     state: &Arc<AppState>,
+    authed_pubkeys: &[String],
     broadcast_tx: &broadcast::Sender<Event>,
 ) -> Vec<String> {
     let event: Event = match serde_json::from_value(msg.get(1).cloned().unwrap_or_default()) {
@@ -126,58 +301,126 @@ async fn handle_event(
         )];
     }
 
+    // NIP-70 protected event (`["-"]`): only its author may publish it here, so
+    // a third party can't re-broadcast space chat copied from another relay.
+    if event.is_protected() && !authed_pubkeys.contains(&event.pubkey) {
+        let reason = if authed_pubkeys.is_empty() {
+            "auth-required: this event may only be published by its author"
+        } else {
+            "restricted: this event may only be published by its author"
+        };
+        return vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)];
+    }
+
+    if let Some(reason) = management_block(state, &event) {
+        return vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)];
+    }
+
+    if let Some(reason) = pow_shortfall(state, &event).await {
+        return vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)];
+    }
+
+    if let Some(reason) = timeline_mismatch(state, &event).await {
+        return vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)];
+    }
+
     // NIP-29 group metadata (39000-39009) is RELAY-generated: the relay signs and
     // writes its own group state directly (never accepting it over EVENT), so any
     // inbound one is a forgery trying to spoof the admin/member lists (#112).
-    if (39000..=39009).contains(&event.kind) {
+    // Same for moderation audit log entries, key handovers, event summaries and
+    // music integrity reports.
+    if (39000..=39009).contains(&event.kind)
+        || event.kind == audit::KIND_MOD_LOG
+        || event.kind == crate::key_rotation::KIND_KEY_HANDOVER
+        || event.kind == crate::nostr::aggregates::KIND_EVENT_STATS
+        || event.kind == crate::music::integrity::KIND_INTEGRITY_REPORT
+    {
         return vec![format!(
             r#"["OK","{}",false,"restricted: kind {} is relay-generated"]"#,
             event.id, event.kind
         )];
     }
 
-    // #115 — music events (31683/33123/30119) must carry the structural tags the
-    // client relies on (title + d). Previously stored unvalidated.
-    if crate::music::kinds::is_music_kind(event.kind)
-        && !crate::music::kinds::validate_music_event(event.kind, &event.tags)
-    {
-        return vec![format!(
-            r#"["OK","{}",false,"invalid: music events require title and d tags"]"#,
-            event.id
-        )];
+    // #115 — music events (31683/33123/30119) must match the schema the client
+    // relies on (see `music::kinds`). Previously stored unvalidated.
+    if crate::music::kinds::is_music_kind(event.kind) {
+        if let Err(reason) = crate::music::kinds::validate_music_event(&event) {
+            return vec![format!(r#"["OK","{}",false,"invalid: {}"]"#, event.id, reason)];
+        }
+    }
+
+    // NIP-57: a zap receipt must carry the signed zap request it answers and
+    // the invoice amount that request asked for.
+    if event.kind == crate::nostr::nip57::KIND_ZAP_RECEIPT {
+        if let Err(reason) = crate::nostr::nip57::validate_receipt(&event, &state.config.zap_provider_pubkeys) {
+            return vec![format!(r#"["OK","{}",false,"invalid: {}"]"#, event.id, reason)];
+        }
     }
 
     // Handle NIP-29 moderation events
     match event.kind {
         9000 => {
             let group_id = event.get_tag_value("h");
+            let changes = with_inherited(state, membership_bus::changes_for(&event)).await;
             let result = crate::nostr::nip29::moderation::handle_put_user(&state.pool, &event)
                 .await
                 .unwrap_or_else(|e| vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]);
+            notify_membership_if_ok(state, &result, changes);
             // Also store and broadcast NIP-29 events
+            audit_if_ok(state, &result, &event).await;
             store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
             republish_metadata_if_ok(state, broadcast_tx, &result, group_id).await;
             return result;
         }
         9001 => {
             let group_id = event.get_tag_value("h");
+            let changes = with_inherited(state, membership_bus::changes_for(&event)).await;
             let result =
                 crate::nostr::nip29::moderation::handle_remove_user(&state.pool, &event)
                     .await
                     .unwrap_or_else(|e| {
                         vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]
                     });
+            notify_membership_if_ok(state, &result, changes);
+            audit_if_ok(state, &result, &event).await;
+            store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
+            republish_metadata_if_ok(state, broadcast_tx, &result, group_id).await;
+            return result;
+        }
+        crate::nostr::nip29::moderation::KIND_MUTE_USER => {
+            let group_id = event.get_tag_value("h");
+            let result = crate::nostr::nip29::moderation::handle_mute_user(&state.pool, &event)
+                .await
+                .unwrap_or_else(|e| vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]);
+            audit_if_ok(state, &result, &event).await;
+            store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
+            republish_metadata_if_ok(state, broadcast_tx, &result, group_id).await;
+            return result;
+        }
+        crate::nostr::nip29::moderation::KIND_UNMUTE_USER => {
+            let group_id = event.get_tag_value("h");
+            let result = crate::nostr::nip29::moderation::handle_unmute_user(&state.pool, &event)
+                .await
+                .unwrap_or_else(|e| vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]);
+            audit_if_ok(state, &result, &event).await;
             store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
             republish_metadata_if_ok(state, broadcast_tx, &result, group_id).await;
             return result;
         }
         9002 => {
             let group_id = event.get_tag_value("h");
+            let parent_id = match &group_id {
+                Some(h) => state.pool.group_parent(h).await.ok().flatten().map(|(parent, _)| parent),
+                None => None,
+            };
             let result = crate::nostr::nip29::groups::handle_edit_metadata(&state.pool, &event)
                 .await
                 .unwrap_or_else(|e| vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]);
+            audit_if_ok(state, &result, &event).await;
             store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
             republish_metadata_if_ok(state, broadcast_tx, &result, group_id).await;
+            // Going private/restricted (or back) changes the parent's child list.
+            republish_metadata_if_ok(state, broadcast_tx, &result, parent_id).await;
             return result;
         }
         9007 => {
@@ -193,24 +436,98 @@ async fn handle_event(
                 )];
             }
             let group_id = event.get_tag_value("h");
+            let parent_id = event.get_tag_value("parent");
+            let changes = with_inherited(state, membership_bus::changes_for(&event)).await;
             let result = crate::nostr::nip29::groups::handle_create_group(&state.pool, &event)
                 .await
                 .unwrap_or_else(|e| vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]);
+            notify_membership_if_ok(state, &result, changes);
+            audit_if_ok(state, &result, &event).await;
             store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
             republish_metadata_if_ok(state, broadcast_tx, &result, group_id).await;
+            // The parent's 39000 lists its children.
+            republish_metadata_if_ok(state, broadcast_tx, &result, parent_id).await;
             return result;
         }
         9008 => {
+            let changes = with_inherited(state, membership_bus::changes_for(&event)).await;
+            let parent_id = match event.get_tag_value("h") {
+                Some(h) => state.pool.group_parent(&h).await.ok().flatten().map(|(parent, _)| parent),
+                None => None,
+            };
             let result = crate::nostr::nip29::groups::handle_delete_group(&state.pool, &event)
                 .await
                 .unwrap_or_else(|e| vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]);
+            notify_membership_if_ok(state, &result, changes);
+            audit_if_ok(state, &result, &event).await;
             store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
+            // Drop the deleted sub-group from its parent's 39000.
+            republish_metadata_if_ok(state, broadcast_tx, &result, parent_id).await;
             return result;
         }
+        migration::KIND_MIGRATE_GROUP => {
+            let (result, announcement) =
+                migration::handle_migrate_group(&state.pool, &state.relay_identity, &event)
+                    .await
+                    .unwrap_or_else(|e| {
+                        (vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)], None)
+                    });
+            audit_if_ok(state, &result, &event).await;
+            store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
+            if let Some(announcement) = announcement {
+                store_and_broadcast_if_ok(state, broadcast_tx, &result, announcement).await;
+            }
+            return result;
+        }
+        // A migration announcement from another relay, pointing here: adopt
+        // the group under its approving admin (see `nip29::migration`).
+        migration::KIND_GROUP_MIGRATION => {
+            let creator_only = if state.hosted_only { state.owner_pubkey.as_deref() } else { None };
+            let (result, migration) = migration::handle_announcement(
+                &state.pool,
+                &event,
+                &state.relay_identity.pubkey,
+                creator_only,
+            )
+            .await
+            .unwrap_or_else(|e| (vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)], None));
+            if let Some(migration) = migration {
+                notify_membership_if_ok(
+                    state,
+                    &result,
+                    vec![MembershipChange {
+                        group_id: migration.group_id.clone(),
+                        pubkey: Some(migration.admin),
+                        parent: None,
+                    }],
+                );
+                store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
+                republish_metadata_if_ok(state, broadcast_tx, &result, Some(migration.group_id)).await;
+            }
+            return result;
+        }
+        crate::nostr::nip62::KIND_VANISH => return handle_vanish(state, broadcast_tx, event).await,
+        // Reports against group events go to the group's moderation queue and
+        // are not broadcast (only the reporter and admins may read them);
+        // anything else is stored as a regular event below.
+        crate::nostr::nip56::KIND_REPORT => {
+            match crate::nostr::nip56::handle_report(&state.pool, &event).await {
+                Ok(Some(result)) => return result,
+                Ok(None) => {}
+                Err(e) => return vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)],
+            }
+        }
         5 => {
-            let result = crate::nostr::nip29::moderation::handle_deletion(&state.pool, &event)
+            let mut result = crate::nostr::nip29::moderation::handle_deletion(&state.pool, &event)
                 .await
                 .unwrap_or_else(|e| vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]);
+            // Tell the author when deleted tracks leave albums/playlists with
+            // removed items.
+            if op_succeeded(&result) {
+                if let Some(notice) = crate::music::integrity::deletion_notice(&state.pool, &event).await {
+                    result.push(notice);
+                }
+            }
             // Store the deletion event itself for history
             store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
             return result;
@@ -222,26 +539,31 @@ async fn handle_event(
                     .unwrap_or_else(|e| {
                         vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]
                     });
+            audit_if_ok(state, &result, &event).await;
             store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
             return result;
         }
         9021 => {
             let group_id = event.get_tag_value("h");
+            let changes = with_inherited(state, membership_bus::changes_for(&event)).await;
             let result =
                 crate::nostr::nip29::membership::handle_join_request(&state.pool, &event)
                     .await
                     .unwrap_or_else(|e| {
                         vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]
                     });
+            notify_membership_if_ok(state, &result, changes);
             store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
             republish_metadata_if_ok(state, broadcast_tx, &result, group_id).await;
             return result;
         }
         9022 => {
             let group_id = event.get_tag_value("h");
+            let changes = with_inherited(state, membership_bus::changes_for(&event)).await;
             let result = crate::nostr::nip29::membership::handle_leave(&state.pool, &event)
                 .await
                 .unwrap_or_else(|e| vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)]);
+            notify_membership_if_ok(state, &result, changes);
             store_and_broadcast_if_ok(state, broadcast_tx, &result, event).await;
             republish_metadata_if_ok(state, broadcast_tx, &result, group_id).await;
             return result;
@@ -273,6 +595,7 @@ async fn handle_event(
     // verify the author is a current member of `app.space_members` — otherwise
     // a kicked user keeps posting via the same WebSocket (the per-connection
     // membership cache is read-side only and stale post-kick).
+    let mut slow_mode_slot = None;
     if let Some(h) = event.get_tag_value("h") {
         if crate::nostr::membership_gate::requires_h_membership_check(event.kind) {
             // Union check: members of EITHER the backend space (app.space_members)
@@ -302,11 +625,44 @@ async fn handle_event(
                     event.id, reason
                 )];
             }
+
+            // Timed mutes: a muted member keeps reading but can't post.
+            let is_muted = state
+                .pool
+                .muted_until(&h, &event.pubkey, chrono::Utc::now().timestamp())
+                .await
+                .map(|until| until.is_some())
+                .unwrap_or_else(|e| {
+                    tracing::error!(error = %e, space_id = %h, "Mute lookup failed; rejecting publish");
+                    true
+                });
+            if let PublishVerdict::Reject(reason) = evaluate_mute_gate(&event, is_muted) {
+                return vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)];
+            }
+
+            if let Err(reason) = check_write_policy(state, &h, &event).await {
+                return vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)];
+            }
+        }
+        if event.kind == slow_mode::KIND_CHAT {
+            match check_slow_mode(state, &h, &event.pubkey).await {
+                Ok(slot) => slow_mode_slot = slot,
+                // A resend of a message already stored isn't a new post: let
+                // it through to be answered as a duplicate.
+                Err(_) if matches!(state.pool.get_event_by_id(&event.id).await, Ok(Some(_))) => {}
+                Err(reason) => {
+                    return vec![format!(r#"["OK","{}",false,"{}"]"#, event.id, reason)];
+                }
+            }
         }
     }
 
     // Store regular events
-    match state.pool.store_event(&event).await {
+    let stored = state.pool.store_event(&event).await;
+    if let (Some(slot), false) = (slow_mode_slot, matches!(stored, Ok(true))) {
+        state.slow_mode.release(slot);
+    }
+    match stored {
         Ok(true) => {
             tracing::debug!(
                 event_id = log_prefix(&event.id),
@@ -318,6 +674,23 @@ async fn handle_event(
             vec![format!(r#"["OK","{}",true,""]"#, event.id)]
         }
         Ok(false) => {
+            // Not inserted: a duplicate — or refused by a tombstone (NIP-62
+            // vanish, NIP-09/9005 deletion), which the client should hear
+            // about rather than see as success.
+            if let Ok(Some(until)) = state.pool.vanished_until(&event.pubkey).await {
+                if event.created_at <= until {
+                    return vec![format!(
+                        r#"["OK","{}",false,"blocked: this pubkey has vanished from this relay"]"#,
+                        event.id
+                    )];
+                }
+            }
+            if let Ok(true) = state.pool.is_tombstoned(&event).await {
+                return vec![format!(
+                    r#"["OK","{}",false,"blocked: this event was deleted"]"#,
+                    event.id
+                )];
+            }
             tracing::trace!(event_id = log_prefix(&event.id), "Duplicate event");
             vec![format!(r#"["OK","{}",true,"duplicate:"]"#, event.id)]
         }
@@ -333,11 +706,61 @@ async fn handle_event(
     }
 }
 
+/// NIP-62 request to vanish. If addressed to this relay (or `ALL_RELAYS`),
+/// purge the author's data and tombstone them (`Db::vanish`), tell open
+/// connections about the dropped memberships, republish the affected groups'
+/// member lists, then keep the request itself.
+async fn handle_vanish(
+    state: &Arc<AppState>,
+    broadcast_tx: &broadcast::Sender<Event>,
+    event: Event,
+) -> Vec<String> {
+    if !crate::nostr::nip62::addressed_to(&event.tags, &state.relay_url) {
+        return vec![format!(
+            r#"["OK","{}",false,"invalid: vanish request is not addressed to this relay"]"#,
+            event.id
+        )];
+    }
+
+    let (deleted, groups) = match state.pool.vanish(&event.pubkey, event.created_at).await {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::error!(pubkey = log_prefix(&event.pubkey), error = %e, "Vanish failed");
+            return vec![format!(r#"["OK","{}",false,"error: {e}"]"#, event.id)];
+        }
+    };
+    tracing::info!(
+        pubkey = log_prefix(&event.pubkey),
+        deleted,
+        groups = groups.len(),
+        "NIP-62 vanish"
+    );
+
+    for group_id in &groups {
+        let _ = state.membership_tx.send(MembershipChange {
+            group_id: group_id.clone(),
+            pubkey: Some(event.pubkey.clone()),
+            parent: None,
+        });
+        crate::nostr::nip29::metadata::publish_group_metadata(
+            &state.pool,
+            &state.relay_identity,
+            broadcast_tx,
+            group_id,
+        )
+        .await;
+    }
+
+    let ok = vec![format!(r#"["OK","{}",true,""]"#, event.id)];
+    store_and_broadcast_if_ok(state, broadcast_tx, &ok, event).await;
+    ok
+}
+
 async fn handle_req(
     msg: serde_json::Value,
     state: &Arc<AppState>,
     subscriptions: &Arc<Mutex<SubscriptionManager>>,
-    authed_pubkey: &Option<String>,
+    authed_pubkeys: &[String],
 ) -> Vec<String> {
     let sub_id = match msg.get(1).and_then(|v| v.as_str()) {
         Some(id) => id.to_string(),
@@ -370,7 +793,7 @@ async fn handle_req(
     // `auth-required` CLOSED so it knows to AUTH and retry (rather than a silent
     // empty EOSE). Members / public groups are unaffected. Check the union of all
     // filters' h_tags.
-    if authed_pubkey.is_none() {
+    if authed_pubkeys.is_empty() {
         let h_union: Vec<String> = filters.iter().flat_map(|f| f.h_tags.clone()).collect();
         if !h_union.is_empty() && state.pool.any_private(&h_union).await.unwrap_or(false) {
             return vec![format!(
@@ -384,11 +807,35 @@ async fn handle_req(
     let mut seen = HashSet::new();
     let mut merged: Vec<crate::nostr::event::Event> = Vec::new();
     for filter in &filters {
-        let events = state.pool
-            .query_events(filter, authed_pubkey.as_deref())
-            .await
-            .unwrap_or_default();
-        for e in events {
+        // NIP-50 catalog extensions (`artist:`, `album:`...) query the music
+        // catalog instead of the full-text index.
+        let events = match crate::music::catalog::CatalogQuery::from_filter(filter) {
+            Some(query) => crate::music::catalog::query(&state.pool, &query, filter, authed_pubkeys).await,
+            None => state.pool.query_events(filter, authed_pubkeys).await,
+        }
+        .unwrap_or_default();
+        let log = if audit::wants_log(filter) {
+            audit::query(&state.pool, filter, authed_pubkeys)
+                .await
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let stats = if crate::nostr::aggregates::wants_stats(filter) {
+            crate::nostr::aggregates::query(&state.pool, &state.relay_identity, filter, authed_pubkeys)
+                .await
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let reports = if crate::music::integrity::wants_report(filter) {
+            crate::music::integrity::query(&state.pool, &state.relay_identity, filter, authed_pubkeys)
+                .await
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        for e in events.into_iter().chain(log).chain(stats).chain(reports) {
             if seen.insert(e.id.clone()) {
                 merged.push(e);
             }
@@ -435,12 +882,38 @@ async fn handle_close(
     }
 }
 
-/// Handle NIP-42 AUTH message: verify kind:22242 event, set authenticated pubkey,
-/// and warm the per-connection space membership cache used by the broadcast filter.
+/// In auth-required mode, may `pubkey` use this relay at all? Checked after the
+/// AUTH signature/challenge verify, so a refused pubkey stays unauthenticated
+/// (and therefore locked out of REQ/EVENT).
+async fn auth_policy_admits(state: &Arc<AppState>, pubkey: &str) -> bool {
+    match &state.config.auth_policy {
+        AuthPolicy::Open | AuthPolicy::AnyPubkey => true,
+        AuthPolicy::Allowlist(allowed) => allowed.contains(pubkey),
+        // The embedded relay's owner must get in before any group exists.
+        AuthPolicy::Members => {
+            state.owner_pubkey.as_deref() == Some(pubkey)
+                || state
+                    .pool
+                    .members_of(pubkey)
+                    .await
+                    .map(|groups| !groups.is_empty())
+                    .unwrap_or(false)
+        }
+    }
+}
+
+/// Cap on pubkeys one connection may authenticate as. Each extra pubkey widens
+/// every visibility query, so keep a multi-account client's socket bounded.
+const MAX_AUTHED_PUBKEYS: usize = 8;
+
+/// Handle NIP-42 AUTH message: verify kind:22242 event, add its pubkey to the
+/// connection's authenticated set, and merge that pubkey's groups into the
+/// per-connection space membership cache used by the broadcast filter. Several
+/// AUTHs on one connection (multi-account clients) accumulate.
 async fn handle_auth(
     msg: serde_json::Value,
     state: &Arc<AppState>,
-    authed_pubkey: &mut Option<String>,
+    authed_pubkeys: &mut Vec<String>,
     space_memberships: &mut HashSet<String>,
     challenge: &str,
 ) -> Vec<String> {
@@ -470,16 +943,38 @@ async fn handle_auth(
         )];
     }
 
+    if !auth_policy_admits(state, &event.pubkey).await {
+        tracing::debug!(
+            pubkey = log_prefix(&event.pubkey),
+            "AUTH refused: pubkey not admitted by auth-required policy"
+        );
+        return vec![format!(
+            r#"["OK","{}",false,"restricted: this pubkey is not allowed on this relay"]"#,
+            event.id
+        )];
+    }
+
+    if authed_pubkeys.contains(&event.pubkey) {
+        return vec![format!(r#"["OK","{}",true,""]"#, event.id)];
+    }
+    if authed_pubkeys.len() >= MAX_AUTHED_PUBKEYS {
+        return vec![format!(
+            r#"["OK","{}",false,"restricted: too many authenticated pubkeys on this connection"]"#,
+            event.id
+        )];
+    }
+
     tracing::info!(
         pubkey = log_prefix(&event.pubkey),
+        authed = authed_pubkeys.len() + 1,
         "Client authenticated (NIP-42)"
     );
-    *authed_pubkey = Some(event.pubkey.clone());
+    authed_pubkeys.push(event.pubkey.clone());
 
-    // Populate the broadcast-path membership cache from BOTH membership worlds
+    // Merge into the broadcast-path membership cache from BOTH membership worlds
     // (app.space_members ∪ relay.group_members). Failures are logged but
-    // non-fatal — the cache stays empty and h-tagged broadcasts will be hidden,
-    // which is the safe default. Initial REQs still honour membership via the
+    // non-fatal — this pubkey's groups stay out of the cache and their h-tagged
+    // broadcasts will be hidden, which is the safe default. Initial REQs still honour membership via the
     // SQL filter in event_store.
     match state.pool.members_of(&event.pubkey).await {
         Ok(set) => {
@@ -488,7 +983,7 @@ async fn handle_auth(
                 space_count = set.len(),
                 "Loaded space memberships for broadcast filter"
             );
-            *space_memberships = set;
+            space_memberships.extend(set);
         }
         Err(e) => {
             tracing::warn!(
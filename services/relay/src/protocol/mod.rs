@@ -1,4 +1,7 @@
+pub mod auth_hold;
 pub mod handler;
 pub mod nip42;
 pub mod nip50;
+pub mod nip86;
+pub mod nip98;
 pub mod subscription;
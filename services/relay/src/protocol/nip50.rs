@@ -1,6 +1,8 @@
 use sqlx::{PgPool, Postgres};
 
 use crate::nostr::event::Event;
+use crate::nostr::nip56::KIND_REPORT;
+use crate::nostr::nip59::RECIPIENT_ONLY_KINDS_SQL;
 
 /// Execute a NIP-50 full-text search query, applying the same visibility gating
 /// as `query_events` (#18: search was previously ungated, leaking private/group
@@ -9,17 +11,28 @@ pub async fn search_events(
     pool: &PgPool,
     query: &str,
     limit: i64,
-    authed_pubkey: Option<&str>,
+    authed_pubkeys: &[String],
 ) -> anyhow::Result<Vec<Event>> {
     let limit = limit.clamp(0, 500);
-    // $1 = query, $2 = limit, $3 = authed pubkey (when present).
-    let visibility = match authed_pubkey {
-        Some(_) =>
-            " AND (visibility IS NULL OR pubkey = $3 OR $3 = ANY(p_tags)) \
-             AND (h_tag IS NULL OR pubkey = $3 \
-                   OR EXISTS (SELECT 1 FROM app.space_members WHERE space_id = h_tag AND pubkey = $3) \
-                   OR EXISTS (SELECT 1 FROM relay.group_members WHERE group_id = h_tag AND pubkey = $3))",
-        None => " AND visibility IS NULL AND h_tag IS NULL",
+    // $1 = query, $2 = limit, $3 = authed pubkeys (when any).
+    let visibility = if authed_pubkeys.is_empty() {
+        format!(
+            " AND visibility IS NULL AND h_tag IS NULL AND kind NOT IN {RECIPIENT_ONLY_KINDS_SQL} \
+             AND (kind <> {KIND_REPORT} OR NOT EXISTS (SELECT 1 FROM relay.reports r WHERE r.report_id = relay.events.id))"
+        )
+    } else {
+        format!(
+            " AND (visibility IS NULL OR pubkey = ANY($3) OR p_tags && $3) \
+             AND (h_tag IS NULL OR pubkey = ANY($3) \
+                   OR EXISTS (SELECT 1 FROM app.space_members WHERE space_id = h_tag AND pubkey = ANY($3)) \
+                   OR EXISTS (SELECT 1 FROM relay.group_members WHERE group_id = h_tag AND pubkey = ANY($3))) \
+             AND (kind NOT IN {RECIPIENT_ONLY_KINDS_SQL} OR p_tags && $3) \
+             AND (kind <> {KIND_REPORT} OR pubkey = ANY($3) \
+                   OR NOT EXISTS (SELECT 1 FROM relay.reports r WHERE r.report_id = relay.events.id) \
+                   OR EXISTS (SELECT 1 FROM relay.reports r \
+                              WHERE r.report_id = relay.events.id AND r.group_id IN ({moderated})))",
+            moderated = crate::db::group_store::moderated_groups_sql("$3")
+        )
     };
     let sql = format!(
         "SELECT id, pubkey, created_at, kind, tags, content, sig \
@@ -29,8 +42,8 @@ pub async fn search_events(
          LIMIT $2"
     );
     let mut q = sqlx::query_as::<Postgres, EventRow>(&sql).bind(query).bind(limit);
-    if let Some(pk) = authed_pubkey {
-        q = q.bind(pk.to_string());
+    if !authed_pubkeys.is_empty() {
+        q = q.bind(authed_pubkeys.to_vec());
     }
     let rows: Vec<EventRow> = q.fetch_all(pool).await?;
 
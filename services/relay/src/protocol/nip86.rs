@@ -0,0 +1,308 @@
+//! NIP-86 relay management API: JSON-RPC over `POST /`
+//! (`application/nostr+json+rpc`), each request authenticated with NIP-98
+//! (see `server::management_handler`).
+//!
+//! Relay operators (`Config::admin_pubkeys`, plus the embedded relay's owner)
+//! may call every method. A group moderator (see `nip56`) may work their
+//! groups' NIP-56 report queue without operator access:
+//! `listeventsneedingmoderation` lists it, and `banevent` / `allowevent` take
+//! down or dismiss a reported event posted to a group they moderate.
+//!
+//! Bans are enforced on every EVENT from an in-memory copy of the lists
+//! ([`Bans`]), reloaded whenever a call here changes them. The allowlist only
+//! applies in allowlist mode (`Config::allowlist_only`).
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+use crate::db::Db;
+use crate::nostr::event::Event;
+use crate::nostr::nip56::FiledReport;
+use crate::server::AppState;
+
+/// `relay.settings` keys for the NIP-11 fields operators can change at runtime.
+pub const SETTING_RELAY_NAME: &str = "relay_name";
+pub const SETTING_RELAY_DESCRIPTION: &str = "relay_description";
+
+pub const SUPPORTED_METHODS: &[&str] = &[
+    "supportedmethods",
+    "banpubkey",
+    "unbanpubkey",
+    "listbannedpubkeys",
+    "allowpubkey",
+    "listallowedpubkeys",
+    "banevent",
+    "allowevent",
+    "listbannedevents",
+    "listeventsneedingmoderation",
+    "changerelayname",
+    "changerelaydescription",
+];
+
+/// The ban and allow lists, as checked on every EVENT.
+#[derive(Debug, Default)]
+pub struct Bans {
+    pubkeys: HashSet<String>,
+    allowed: HashSet<String>,
+    events: HashSet<String>,
+}
+
+impl Bans {
+    pub async fn load(db: &Db) -> anyhow::Result<Self> {
+        let keys = |rows: Vec<(String, Option<String>)>| rows.into_iter().map(|(k, _)| k).collect();
+        Ok(Self {
+            pubkeys: keys(db.list_banned_pubkeys().await?),
+            allowed: keys(db.list_allowed_pubkeys().await?),
+            events: keys(db.list_banned_events().await?),
+        })
+    }
+
+    /// Why `event` may not be published here, if it can't: its pubkey is
+    /// banned (or, with `allowlist_only`, not allowlisted), or its id is
+    /// banned. A blocked pubkey may still ask to vanish.
+    pub fn block_reason(&self, event: &Event, allowlist_only: bool) -> Option<&'static str> {
+        let pubkey_blocked = self.pubkeys.contains(&event.pubkey)
+            || (allowlist_only && !self.allowed.contains(&event.pubkey));
+        if pubkey_blocked && event.kind != crate::nostr::nip62::KIND_VANISH {
+            Some("blocked: this pubkey may not publish to this relay")
+        } else if self.events.contains(&event.id) {
+            Some("blocked: this event is banned from this relay")
+        } else {
+            None
+        }
+    }
+}
+
+/// Re-read the lists after a change.
+async fn reload_bans(state: &AppState) -> Result<(), String> {
+    let bans = Bans::load(&state.pool).await.map_err(internal)?;
+    *state.bans.write().unwrap_or_else(|e| e.into_inner()) = bans;
+    Ok(())
+}
+
+/// A NIP-86 request body: `{"method": "...", "params": [...]}`.
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    pub method: String,
+    #[serde(default)]
+    pub params: Vec<Value>,
+}
+
+/// May `pubkey` call every management method?
+pub fn is_operator(state: &AppState, pubkey: &str) -> bool {
+    state.config.admin_pubkeys.contains(pubkey) || state.owner_pubkey.as_deref() == Some(pubkey)
+}
+
+/// Run one RPC call for the NIP-98-authenticated `caller` and build the
+/// response body (`{"result": ...}` or `{"result": null, "error": "..."}`).
+pub async fn handle(state: &AppState, caller: &str, req: Request) -> Value {
+    match dispatch(state, caller, &req).await {
+        Ok(result) => json!({ "result": result }),
+        Err(error) => json!({ "result": null, "error": error }),
+    }
+}
+
+async fn dispatch(state: &AppState, caller: &str, req: &Request) -> Result<Value, String> {
+    if !is_operator(state, caller) {
+        return match req.method.as_str() {
+            "listeventsneedingmoderation" => Ok(moderation_queue(
+                state.pool.open_reports(Some(caller)).await.map_err(internal)?,
+            )),
+            "banevent" | "allowevent" => moderate_group_event(state, caller, req).await,
+            _ => Err("unauthorized".to_string()),
+        };
+    }
+
+    let db = &state.pool;
+    let params = &req.params;
+    tracing::info!(method = %req.method, operator = %caller, "NIP-86 management call");
+    match req.method.as_str() {
+        "supportedmethods" => Ok(json!(SUPPORTED_METHODS)),
+        "banpubkey" => {
+            db.ban_pubkey(&hex_param(params, 0)?, str_param(params, 1))
+                .await
+                .map_err(internal)?;
+            reload_bans(state).await?;
+            Ok(json!(true))
+        }
+        "unbanpubkey" => {
+            db.unban_pubkey(&hex_param(params, 0)?).await.map_err(internal)?;
+            reload_bans(state).await?;
+            Ok(json!(true))
+        }
+        "allowpubkey" => {
+            db.allow_pubkey(&hex_param(params, 0)?, str_param(params, 1))
+                .await
+                .map_err(internal)?;
+            reload_bans(state).await?;
+            Ok(json!(true))
+        }
+        "listbannedpubkeys" => Ok(listing(
+            "pubkey",
+            db.list_banned_pubkeys().await.map_err(internal)?,
+        )),
+        "listallowedpubkeys" => Ok(listing(
+            "pubkey",
+            db.list_allowed_pubkeys().await.map_err(internal)?,
+        )),
+        "banevent" | "allowevent" => {
+            moderate_event(state, caller, &req.method, &hex_param(params, 0)?, str_param(params, 1)).await
+        }
+        "listbannedevents" => Ok(listing("id", db.list_banned_events().await.map_err(internal)?)),
+        "listeventsneedingmoderation" => {
+            Ok(moderation_queue(db.open_reports(None).await.map_err(internal)?))
+        }
+        "changerelayname" => {
+            let name = required_str(params, 0)?;
+            db.set_setting(SETTING_RELAY_NAME, name).await.map_err(internal)?;
+            state.relay_info.write().unwrap_or_else(|e| e.into_inner()).name = name.to_string();
+            Ok(json!(true))
+        }
+        "changerelaydescription" => {
+            let description = required_str(params, 0)?;
+            db.set_setting(SETTING_RELAY_DESCRIPTION, description)
+                .await
+                .map_err(internal)?;
+            state.relay_info.write().unwrap_or_else(|e| e.into_inner()).description =
+                description.to_string();
+            Ok(json!(true))
+        }
+        other => Err(format!("unsupported method: {other}")),
+    }
+}
+
+/// `banevent` / `allowevent` from a non-operator: allowed only for an event
+/// stored in a group the caller moderates.
+async fn moderate_group_event(state: &AppState, caller: &str, req: &Request) -> Result<Value, String> {
+    let id = hex_param(&req.params, 0)?;
+    let event = state
+        .pool
+        .get_event_by_id(&id)
+        .await
+        .map_err(internal)?
+        .ok_or("unauthorized")?;
+    let group_id = event.get_tag_value("h").ok_or("unauthorized")?;
+    if !state.pool.can_moderate(&group_id, caller).await.map_err(internal)? {
+        return Err("unauthorized".to_string());
+    }
+    tracing::info!(method = %req.method, event_id = %id, group = %group_id, admin = %caller, "NIP-86 group moderation");
+    moderate_event(state, caller, &req.method, &id, str_param(&req.params, 1)).await
+}
+
+/// Ban (delete + refuse) or allow an event, closing its NIP-56 reports as
+/// `banned` or `dismissed`.
+async fn moderate_event(
+    state: &AppState,
+    caller: &str,
+    method: &str,
+    id: &str,
+    reason: Option<&str>,
+) -> Result<Value, String> {
+    let resolution = if method == "banevent" {
+        state.pool.ban_event(id, reason).await.map_err(internal)?;
+        "banned"
+    } else {
+        state.pool.allow_event(id).await.map_err(internal)?;
+        "dismissed"
+    };
+    reload_bans(state).await?;
+    state
+        .pool
+        .resolve_reports(id, caller, resolution)
+        .await
+        .map_err(internal)?;
+    Ok(json!(true))
+}
+
+/// The open report queue as NIP-86 `[{"id", "reason"}]`, one entry per
+/// reported event (the first report's type as the reason).
+fn moderation_queue(reports: Vec<FiledReport>) -> Value {
+    let mut seen = HashSet::new();
+    Value::Array(
+        reports
+            .into_iter()
+            .filter(|r| seen.insert(r.target_event_id.clone()))
+            .map(|r| json!({ "id": r.target_event_id, "reason": r.report_type }))
+            .collect(),
+    )
+}
+
+/// `[{"<key>": ..., "reason": ...}]`, the NIP-86 list shape.
+fn listing(key: &str, rows: Vec<(String, Option<String>)>) -> Value {
+    Value::Array(
+        rows.into_iter()
+            .map(|(value, reason)| json!({ key: value, "reason": reason }))
+            .collect(),
+    )
+}
+
+fn str_param(params: &[Value], i: usize) -> Option<&str> {
+    params.get(i).and_then(Value::as_str)
+}
+
+fn required_str(params: &[Value], i: usize) -> Result<&str, String> {
+    str_param(params, i).ok_or_else(|| format!("param {i} must be a string"))
+}
+
+/// A 32-byte hex param (pubkey or event id), lowercased.
+fn hex_param(params: &[Value], i: usize) -> Result<String, String> {
+    let raw = required_str(params, i)?;
+    if raw.len() == 64 && raw.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(raw.to_ascii_lowercase())
+    } else {
+        Err(format!("param {i} must be 64 hex characters"))
+    }
+}
+
+fn internal(e: anyhow::Error) -> String {
+    tracing::error!(error = %e, "NIP-86 management call failed");
+    "internal error".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_param_validates_and_lowercases() {
+        let params = vec![json!("AB".repeat(32)), json!("xyz"), json!(7)];
+        assert_eq!(hex_param(&params, 0), Ok("ab".repeat(32)));
+        assert!(hex_param(&params, 1).is_err());
+        assert!(hex_param(&params, 2).is_err());
+        assert!(hex_param(&params, 3).is_err());
+    }
+
+    #[test]
+    fn allowlist_only_applies_in_allowlist_mode() {
+        let event = |pubkey: &str, kind: i32| Event {
+            id: format!("{pubkey}-{kind}"),
+            pubkey: pubkey.into(),
+            created_at: 1,
+            kind,
+            tags: vec![],
+            content: String::new(),
+            sig: String::new(),
+        };
+        let bans = Bans {
+            pubkeys: HashSet::from(["banned".to_string()]),
+            allowed: HashSet::from(["friend".to_string()]),
+            events: HashSet::from(["friend-7".to_string()]),
+        };
+        assert_eq!(bans.block_reason(&event("stranger", 1), false), None);
+        assert!(bans.block_reason(&event("stranger", 1), true).is_some());
+        assert_eq!(bans.block_reason(&event("friend", 1), true), None);
+        assert!(bans.block_reason(&event("banned", 1), false).is_some());
+        assert_eq!(bans.block_reason(&event("banned", 62), false), None, "vanish is always allowed");
+        assert!(bans.block_reason(&event("friend", 7), false).unwrap().contains("event"));
+    }
+
+    #[test]
+    fn listing_uses_the_nip86_shape() {
+        let rows = vec![("a".to_string(), Some("spam".to_string())), ("b".to_string(), None)];
+        assert_eq!(
+            listing("pubkey", rows),
+            json!([{ "pubkey": "a", "reason": "spam" }, { "pubkey": "b", "reason": null }])
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! NIP-98 HTTP auth: an `Authorization: Nostr <base64 event>` header carrying
+//! a signed kind:27235 event that binds one HTTP request (url, method, body
+//! hash) to a pubkey. Used by the NIP-86 management endpoint.
+
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use crate::nostr::event::Event;
+use crate::protocol::nip42::normalize_relay_url;
+
+pub const KIND_HTTP_AUTH: i32 = 27235;
+
+/// How far the auth event's `created_at` may be from now (seconds). NIP-98
+/// suggests a tight window: the event authorizes a single request.
+const HTTP_AUTH_MAX_SKEW: i64 = 60;
+
+/// Verify a NIP-98 `Authorization` header for a request to `url` with
+/// `method` and `body`, returning the authenticated pubkey or why it failed.
+///
+/// `strict_url` mirrors [`super::nip42::verify_auth_event`]: the embedded relay
+/// is reachable under many addresses, so there only the presence of a `u` tag
+/// is checked. The body hash (`payload` tag) is always required — it is what
+/// stops a captured header from authorizing a different RPC call.
+pub fn verify_http_auth(
+    authorization: Option<&str>,
+    url: &str,
+    method: &str,
+    body: &[u8],
+    strict_url: bool,
+    now: i64,
+) -> Result<String, &'static str> {
+    let encoded = authorization
+        .and_then(|h| h.strip_prefix("Nostr "))
+        .ok_or("missing Nostr authorization header")?;
+    let raw = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| "authorization is not base64")?;
+    let event: Event = serde_json::from_slice(&raw).map_err(|_| "authorization is not an event")?;
+
+    if event.kind != KIND_HTTP_AUTH {
+        return Err("auth event must be kind 27235");
+    }
+    if (now - event.created_at).abs() > HTTP_AUTH_MAX_SKEW {
+        return Err("auth event is stale");
+    }
+    match event.get_tag_value("u") {
+        Some(u) if !strict_url || normalize_relay_url(&u) == normalize_relay_url(url) => {}
+        _ => return Err("auth event url does not match"),
+    }
+    if !event
+        .get_tag_value("method")
+        .is_some_and(|m| m.eq_ignore_ascii_case(method))
+    {
+        return Err("auth event method does not match");
+    }
+    let payload = hex::encode(Sha256::digest(body));
+    if event.get_tag_value("payload").as_deref() != Some(payload.as_str()) {
+        return Err("auth event payload does not match the body");
+    }
+    if !crate::nostr::verify::verify_event(&event) {
+        return Err("auth event signature is invalid");
+    }
+    Ok(event.pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay_identity::RelayIdentity;
+
+    const URL: &str = "https://relay.thewired.app/";
+    const BODY: &[u8] = br#"{"method":"supportedmethods","params":[]}"#;
+
+    fn header(signer: &RelayIdentity, u: &str, method: &str, body: &[u8]) -> String {
+        let tags = vec![
+            vec!["u".to_string(), u.to_string()],
+            vec!["method".to_string(), method.to_string()],
+            vec!["payload".to_string(), hex::encode(Sha256::digest(body))],
+        ];
+        let event = signer.sign_event(KIND_HTTP_AUTH, tags, "");
+        let json = serde_json::to_vec(&event).unwrap();
+        format!("Nostr {}", base64::engine::general_purpose::STANDARD.encode(json))
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    #[test]
+    fn accepts_a_matching_request() {
+        let signer = RelayIdentity::new(None, "test");
+        let h = header(&signer, "wss://relay.thewired.app", "POST", BODY);
+        assert_eq!(
+            verify_http_auth(Some(&h), URL, "POST", BODY, true, now()),
+            Ok(signer.pubkey.clone())
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_url_method_body_and_age() {
+        let signer = RelayIdentity::new(None, "test");
+        let h = header(&signer, "https://other.relay/", "POST", BODY);
+        assert!(verify_http_auth(Some(&h), URL, "POST", BODY, true, now()).is_err());
+        // The embedded relay only requires the tag to be present.
+        assert!(verify_http_auth(Some(&h), URL, "POST", BODY, false, now()).is_ok());
+
+        let h = header(&signer, URL, "GET", BODY);
+        assert!(verify_http_auth(Some(&h), URL, "POST", BODY, true, now()).is_err());
+
+        let h = header(&signer, URL, "POST", BODY);
+        assert!(verify_http_auth(Some(&h), URL, "POST", b"{}", true, now()).is_err());
+        assert!(verify_http_auth(Some(&h), URL, "POST", BODY, true, now() + 120).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_or_malformed_headers() {
+        assert!(verify_http_auth(None, URL, "POST", BODY, true, now()).is_err());
+        assert!(verify_http_auth(Some("Bearer abc"), URL, "POST", BODY, true, now()).is_err());
+        assert!(verify_http_auth(Some("Nostr !!!"), URL, "POST", BODY, true, now()).is_err());
+    }
+}
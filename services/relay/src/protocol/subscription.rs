@@ -34,6 +34,21 @@ impl SubscriptionManager {
         self.subscriptions.remove(id);
     }
 
+    /// Remove every subscription with a filter scoped to `group_id` via `#h`,
+    /// returning their ids so the caller can send CLOSED (membership revoked).
+    pub fn remove_targeting_group(&mut self, group_id: &str) -> Vec<String> {
+        let ids: Vec<String> = self
+            .subscriptions
+            .iter()
+            .filter(|(_, filters)| filters.iter().any(|f| f.h_tags.iter().any(|h| h == group_id)))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &ids {
+            self.subscriptions.remove(id);
+        }
+        ids
+    }
+
     /// Check which subscriptions match a given event (any filter matches).
     pub fn matching_subs(&self, event: &Event) -> Vec<String> {
         self.subscriptions
@@ -124,6 +139,22 @@ mod tests {
         assert!(subs.matching_subs(&mk(7)).is_empty()); // matches neither
     }
 
+    /// Losing membership of a group closes exactly the subs scoped to it.
+    #[test]
+    fn remove_targeting_group_only_drops_matching_subs() {
+        let mut subs = SubscriptionManager::new();
+        let h = |g: &str| Filter { h_tags: vec![g.to_string()], ..Default::default() };
+        subs.add("chat_g".to_string(), vec![h("g")]).unwrap();
+        subs.add("mixed".to_string(), vec![h("other"), h("g")]).unwrap();
+        subs.add("chat_other".to_string(), vec![h("other")]).unwrap();
+        subs.add("global".to_string(), empty_filter()).unwrap();
+
+        let mut closed = subs.remove_targeting_group("g");
+        closed.sort();
+        assert_eq!(closed, vec!["chat_g", "mixed"]);
+        assert_eq!(subs.len(), 2);
+    }
+
     #[test]
     fn reusing_sub_id_does_not_consume_cap_slot() {
         let mut subs = SubscriptionManager::new();
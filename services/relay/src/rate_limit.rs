@@ -0,0 +1,410 @@
+//! Shared token-bucket rate limiting.
+//!
+//! One [`RateLimiter`] lives in `AppState`, so its buckets are shared by every
+//! connection: opening more sockets buys no extra budget. Each client frame is
+//! charged to the remote IP and to the pubkey(s) it acts as, and is refused
+//! with a `rate-limited:` OK/CLOSED when any of those buckets is empty.
+//!
+//! Budgets are per action — EVENT (split by kind class), REQ and AUTH — and
+//! set per deployment through `RELAY_RATE_*` (see [`RateLimits::from_env_values`]).
+//! What is charged to which pubkey:
+//!   - EVENT: the author, if authenticated on this connection,
+//!   - REQ: every authenticated pubkey,
+//!   - AUTH: nobody — the claimed pubkey isn't verified yet, so charging it
+//!     would let anyone drain a stranger's budget. IP only.
+
+use axum::http::HeaderMap;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Reason prefix on refused frames (NIP-01 machine-readable prefix).
+const REFUSAL: &str = "rate-limited: slow down";
+
+/// Bucket count above which idle (full) buckets are swept out.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// A token-bucket budget: up to `burst` actions at once, refilling at
+/// `burst` per `per_secs` seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub burst: u32,
+    pub per_secs: u32,
+}
+
+impl Budget {
+    pub const fn new(burst: u32, per_secs: u32) -> Self {
+        Self { burst, per_secs }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.burst as f64 / self.per_secs.max(1) as f64
+    }
+}
+
+/// Parse a `RELAY_RATE_*` value: `<count>/<seconds>` (e.g. `120/60`), or
+/// `off` / `0` for no limit. Unset or malformed keeps `default`.
+pub fn parse_budget(name: &str, raw: Option<&str>, default: Option<Budget>) -> Option<Budget> {
+    let Some(raw) = raw.map(str::trim).filter(|s| !s.is_empty()) else {
+        return default;
+    };
+    if raw.eq_ignore_ascii_case("off") || raw == "0" {
+        return None;
+    }
+    let parsed = raw
+        .split_once('/')
+        .and_then(|(n, s)| Some(Budget::new(n.trim().parse().ok()?, s.trim().parse().ok()?)))
+        .filter(|b| b.burst > 0 && b.per_secs > 0);
+    if parsed.is_none() {
+        tracing::warn!(name, value = raw, "Malformed rate budget (want <count>/<seconds>); using default");
+        return default;
+    }
+    parsed
+}
+
+/// EVENT kind classes with separate budgets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KindClass {
+    /// Regular (stored, non-replaceable) events: chat, posts, reactions, ...
+    Regular,
+    /// Replaceable and addressable events (0, 3, 10000–19999, 30000–39999).
+    Replaceable,
+    /// Ephemeral events (20000–29999): typing indicators, signaling.
+    Ephemeral,
+    /// NIP-29 moderation and join/leave requests (9000–9030).
+    GroupOp,
+}
+
+impl KindClass {
+    pub fn of(kind: i32) -> Self {
+        match kind {
+            9000..=9030 => KindClass::GroupOp,
+            0 | 3 | 10000..=19999 | 30000..=39999 => KindClass::Replaceable,
+            20000..=29999 => KindClass::Ephemeral,
+            _ => KindClass::Regular,
+        }
+    }
+}
+
+/// What a client frame spends budget on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Event(KindClass),
+    Req,
+    Auth,
+}
+
+/// Per-action budgets, each applied separately to every IP and every pubkey.
+/// `None` means unlimited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimits {
+    pub events: Option<Budget>,
+    pub replaceable_events: Option<Budget>,
+    pub ephemeral_events: Option<Budget>,
+    pub group_ops: Option<Budget>,
+    pub reqs: Option<Budget>,
+    pub auths: Option<Budget>,
+}
+
+impl Default for RateLimits {
+    /// Generous enough never to bother a real client (a burst of a few hundred
+    /// REQs on startup, chat at a couple of messages per second) while still
+    /// stopping a flood.
+    fn default() -> Self {
+        Self {
+            events: Some(Budget::new(120, 60)),
+            replaceable_events: Some(Budget::new(60, 60)),
+            ephemeral_events: Some(Budget::new(600, 60)),
+            group_ops: Some(Budget::new(60, 60)),
+            reqs: Some(Budget::new(600, 60)),
+            auths: Some(Budget::new(60, 60)),
+        }
+    }
+}
+
+impl RateLimits {
+    /// Read the budgets through `get` (the env in production):
+    /// `RELAY_RATE_EVENT`, `RELAY_RATE_EVENT_REPLACEABLE`,
+    /// `RELAY_RATE_EVENT_EPHEMERAL`, `RELAY_RATE_GROUP_OP`, `RELAY_RATE_REQ`,
+    /// `RELAY_RATE_AUTH` — each `<count>/<seconds>` or `off`.
+    pub fn from_env_values(get: impl Fn(&str) -> Option<String>) -> Self {
+        let d = Self::default();
+        let budget = |name: &str, default| parse_budget(name, get(name).as_deref(), default);
+        Self {
+            events: budget("RELAY_RATE_EVENT", d.events),
+            replaceable_events: budget("RELAY_RATE_EVENT_REPLACEABLE", d.replaceable_events),
+            ephemeral_events: budget("RELAY_RATE_EVENT_EPHEMERAL", d.ephemeral_events),
+            group_ops: budget("RELAY_RATE_GROUP_OP", d.group_ops),
+            reqs: budget("RELAY_RATE_REQ", d.reqs),
+            auths: budget("RELAY_RATE_AUTH", d.auths),
+        }
+    }
+
+    fn budget(&self, action: Action) -> Option<Budget> {
+        match action {
+            Action::Event(KindClass::Regular) => self.events,
+            Action::Event(KindClass::Replaceable) => self.replaceable_events,
+            Action::Event(KindClass::Ephemeral) => self.ephemeral_events,
+            Action::Event(KindClass::GroupOp) => self.group_ops,
+            Action::Req => self.reqs,
+            Action::Auth => self.auths,
+        }
+    }
+}
+
+/// Who a bucket belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Subject {
+    Ip(IpAddr),
+    Pubkey(String),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Tokens available at `now` (refilled since the last update, capped).
+    fn refill(&mut self, budget: Budget, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * budget.refill_per_sec()).min(budget.burst as f64);
+        self.updated = now;
+    }
+}
+
+/// What one client frame costs, and how to refuse it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Charge {
+    pub action: Action,
+    /// Pubkeys charged alongside the IP.
+    pub pubkeys: Vec<String>,
+    /// The OK / CLOSED frame sent when the charge is refused.
+    pub refusal: String,
+}
+
+/// The charge for a raw client frame, or `None` for frames that cost nothing
+/// (CLOSE, malformed JSON — the handler answers those).
+pub fn charge_for(text: &str, authed_pubkeys: &[String]) -> Option<Charge> {
+    let msg: Value = serde_json::from_str(text).ok()?;
+    let field = |i: usize, key: &str| msg.get(i).and_then(|v| v.get(key)).and_then(Value::as_str);
+    match msg.get(0).and_then(Value::as_str)? {
+        "EVENT" => {
+            let kind = msg.get(1).and_then(|e| e.get("kind")).and_then(Value::as_i64)?;
+            let id = field(1, "id").unwrap_or("");
+            let author = field(1, "pubkey").unwrap_or("");
+            Some(Charge {
+                action: Action::Event(KindClass::of(kind as i32)),
+                pubkeys: authed_pubkeys.iter().filter(|pk| *pk == author).cloned().collect(),
+                refusal: json!(["OK", id, false, REFUSAL]).to_string(),
+            })
+        }
+        "REQ" => {
+            let sub_id = msg.get(1).and_then(Value::as_str).unwrap_or("");
+            Some(Charge {
+                action: Action::Req,
+                pubkeys: authed_pubkeys.to_vec(),
+                refusal: json!(["CLOSED", sub_id, REFUSAL]).to_string(),
+            })
+        }
+        "AUTH" => Some(Charge {
+            action: Action::Auth,
+            pubkeys: Vec::new(),
+            refusal: json!(["OK", field(1, "id").unwrap_or(""), false, REFUSAL]).to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// The IP a connection is charged to. Behind a reverse proxy every socket
+/// comes from the proxy, so with `trust_forwarded_for` the rightmost
+/// `X-Forwarded-For` entry (the hop the proxy vouches for) is used instead.
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr, trust_forwarded_for: bool) -> IpAddr {
+    if trust_forwarded_for {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|xff| xff.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+    peer.ip()
+}
+
+/// Token buckets keyed by (subject, action), shared by every connection.
+pub struct RateLimiter {
+    limits: RateLimits,
+    buckets: Mutex<HashMap<(Subject, Action), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Spend one token of `charge.action` from `ip`'s bucket and each charged
+    /// pubkey's. All-or-nothing: if any bucket is empty, nothing is spent and
+    /// the frame is refused.
+    pub fn allow(&self, charge: &Charge, ip: IpAddr) -> bool {
+        self.allow_at(charge, ip, Instant::now())
+    }
+
+    fn allow_at(&self, charge: &Charge, ip: IpAddr, now: Instant) -> bool {
+        let Some(budget) = self.limits.budget(charge.action) else {
+            return true;
+        };
+        let subjects: Vec<Subject> = std::iter::once(Subject::Ip(ip))
+            .chain(charge.pubkeys.iter().cloned().map(Subject::Pubkey))
+            .collect();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= PRUNE_THRESHOLD {
+            prune(&mut buckets, &self.limits, now);
+        }
+        let mut allowed = true;
+        for subject in &subjects {
+            let bucket = buckets
+                .entry((subject.clone(), charge.action))
+                .or_insert_with(|| Bucket {
+                    tokens: budget.burst as f64,
+                    updated: now,
+                });
+            bucket.refill(budget, now);
+            allowed &= bucket.tokens >= 1.0;
+        }
+        if allowed {
+            for subject in subjects {
+                if let Some(bucket) = buckets.get_mut(&(subject, charge.action)) {
+                    bucket.tokens -= 1.0;
+                }
+            }
+        }
+        allowed
+    }
+}
+
+/// Drop buckets that have refilled completely — they are indistinguishable
+/// from a fresh one.
+fn prune(buckets: &mut HashMap<(Subject, Action), Bucket>, limits: &RateLimits, now: Instant) {
+    buckets.retain(|(_, action), bucket| match limits.budget(*action) {
+        Some(budget) => {
+            bucket.refill(budget, now);
+            bucket.tokens < budget.burst as f64
+        }
+        None => false,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limits(budget: Budget) -> RateLimits {
+        RateLimits {
+            events: Some(budget),
+            replaceable_events: None,
+            ephemeral_events: Some(budget),
+            group_ops: Some(budget),
+            reqs: Some(budget),
+            auths: Some(budget),
+        }
+    }
+
+    fn req(pubkeys: &[&str]) -> Charge {
+        Charge {
+            action: Action::Req,
+            pubkeys: pubkeys.iter().map(|s| s.to_string()).collect(),
+            refusal: String::new(),
+        }
+    }
+
+    const IP_A: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+    const IP_B: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn bucket_drains_and_refills() {
+        let limiter = RateLimiter::new(limits(Budget::new(2, 10)));
+        let t0 = Instant::now();
+        assert!(limiter.allow_at(&req(&[]), IP_A, t0));
+        assert!(limiter.allow_at(&req(&[]), IP_A, t0));
+        assert!(!limiter.allow_at(&req(&[]), IP_A, t0));
+        // Another IP has its own bucket.
+        assert!(limiter.allow_at(&req(&[]), IP_B, t0));
+        // 2 per 10 s: one token back after 5 s.
+        assert!(limiter.allow_at(&req(&[]), IP_A, t0 + Duration::from_secs(5)));
+        assert!(!limiter.allow_at(&req(&[]), IP_A, t0 + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn pubkey_budget_follows_the_pubkey_across_ips() {
+        let limiter = RateLimiter::new(limits(Budget::new(1, 60)));
+        let t0 = Instant::now();
+        assert!(limiter.allow_at(&req(&["alice"]), IP_A, t0));
+        assert!(!limiter.allow_at(&req(&["alice"]), IP_B, t0));
+        // The refused frame spent nothing from IP_B.
+        assert!(limiter.allow_at(&req(&["bob"]), IP_B, t0));
+    }
+
+    #[test]
+    fn actions_have_separate_budgets_and_none_is_unlimited() {
+        let limiter = RateLimiter::new(limits(Budget::new(1, 60)));
+        let t0 = Instant::now();
+        let event = |kind| charge_for(&format!(r#"["EVENT",{{"id":"x","pubkey":"p","kind":{kind}}}]"#), &[]).unwrap();
+        assert!(limiter.allow_at(&event(1), IP_A, t0));
+        assert!(!limiter.allow_at(&event(1), IP_A, t0));
+        assert!(limiter.allow_at(&event(9000), IP_A, t0));
+        assert!(limiter.allow_at(&req(&[]), IP_A, t0));
+        for _ in 0..10 {
+            assert!(limiter.allow_at(&event(30023), IP_A, t0));
+        }
+    }
+
+    #[test]
+    fn charge_for_classifies_frames_and_builds_refusals() {
+        let authed = vec!["alice".to_string(), "bob".to_string()];
+        let ev = charge_for(r#"["EVENT",{"id":"e1","pubkey":"bob","kind":20001}]"#, &authed).unwrap();
+        assert_eq!(ev.action, Action::Event(KindClass::Ephemeral));
+        assert_eq!(ev.pubkeys, vec!["bob".to_string()]);
+        assert_eq!(ev.refusal, r#"["OK","e1",false,"rate-limited: slow down"]"#);
+
+        let req = charge_for(r#"["REQ","s\"1",{}]"#, &authed).unwrap();
+        assert_eq!(req.pubkeys, authed);
+        assert_eq!(req.refusal, r#"["CLOSED","s\"1","rate-limited: slow down"]"#);
+
+        let auth = charge_for(r#"["AUTH",{"id":"a1","pubkey":"alice","kind":22242}]"#, &authed).unwrap();
+        assert_eq!(auth.action, Action::Auth);
+        assert!(auth.pubkeys.is_empty());
+
+        assert_eq!(charge_for(r#"["CLOSE","s1"]"#, &authed), None);
+        assert_eq!(charge_for("not json", &authed), None);
+    }
+
+    #[test]
+    fn parse_budget_reads_count_per_seconds() {
+        let d = Some(Budget::new(5, 5));
+        assert_eq!(parse_budget("X", None, d), d);
+        assert_eq!(parse_budget("X", Some("30/10"), d), Some(Budget::new(30, 10)));
+        assert_eq!(parse_budget("X", Some("off"), d), None);
+        assert_eq!(parse_budget("X", Some("0"), d), None);
+        assert_eq!(parse_budget("X", Some("30"), d), d);
+        assert_eq!(parse_budget("X", Some("0/10"), d), d);
+    }
+
+    #[test]
+    fn client_ip_uses_rightmost_forwarded_for_only_when_trusted() {
+        let peer: SocketAddr = "172.18.0.5:40000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "9.9.9.9, 203.0.113.7".parse().unwrap());
+        assert_eq!(client_ip(&headers, peer, true), "203.0.113.7".parse::<IpAddr>().unwrap());
+        assert_eq!(client_ip(&headers, peer, false), peer.ip());
+        assert_eq!(client_ip(&HeaderMap::new(), peer, true), peer.ip());
+    }
+}
@@ -7,6 +7,8 @@ use crate::nostr::event::Event;
 pub struct RelayIdentity {
     pub pubkey: String,
     secret_key: SecretKey,
+    /// Generated for this run rather than configured.
+    ephemeral: bool,
 }
 
 impl RelayIdentity {
@@ -14,14 +16,10 @@ impl RelayIdentity {
     /// In production (env == "production"), panics if no key is provided.
     /// In development, generates a random ephemeral key (without logging the secret).
     pub fn new(secret_key_hex: Option<String>, env: &str) -> Self {
-        let secp = Secp256k1::new();
-
-        let secret_key = if let Some(hex_str) = secret_key_hex {
-            let bytes = hex::decode(hex_str.trim())
-                .expect("RELAY_SECRET_KEY must be valid hex");
-            SecretKey::from_slice(&bytes)
-                .expect("RELAY_SECRET_KEY must be a valid 32-byte secret key")
-        } else if env == "production" {
+        if let Some(hex_str) = secret_key_hex {
+            return Self::from_secret_key(&hex_str);
+        }
+        let secret_key = if env == "production" {
             panic!(
                 "RELAY_SECRET_KEY is required in production. Generate one with: openssl rand -hex 32"
             );
@@ -35,7 +33,20 @@ impl RelayIdentity {
             tracing::warn!("No RELAY_SECRET_KEY set. Generated ephemeral relay key (not suitable for production).");
             sk
         };
+        Self::from_key(secret_key, true)
+    }
 
+    /// The identity of a configured hex-encoded secret key.
+    pub fn from_secret_key(secret_key_hex: &str) -> Self {
+        let bytes = hex::decode(secret_key_hex.trim())
+            .expect("RELAY_SECRET_KEY must be valid hex");
+        let secret_key = SecretKey::from_slice(&bytes)
+            .expect("RELAY_SECRET_KEY must be a valid 32-byte secret key");
+        Self::from_key(secret_key, false)
+    }
+
+    fn from_key(secret_key: SecretKey, ephemeral: bool) -> Self {
+        let secp = Secp256k1::new();
         let keypair = Keypair::from_secret_key(&secp, &secret_key);
         let (xonly, _parity) = XOnlyPublicKey::from_keypair(&keypair);
         let pubkey = hex::encode(xonly.serialize());
@@ -45,9 +56,15 @@ impl RelayIdentity {
         Self {
             pubkey,
             secret_key,
+            ephemeral,
         }
     }
 
+    /// Was the key generated for this run (no configured secret)?
+    pub fn is_ephemeral(&self) -> bool {
+        self.ephemeral
+    }
+
     /// Sign and return a complete Nostr event with the relay's identity.
     pub fn sign_event(
         &self,
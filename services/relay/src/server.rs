@@ -1,26 +1,39 @@
 use axum::{
+    body::Bytes,
     extract::{connect_info::ConnectInfo, FromRequest, Request, State, WebSocketUpgrade},
-    http::{header, Method},
+    http::{header, HeaderMap, Method, StatusCode},
     response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
 use std::net::SocketAddr;
 use std::sync::atomic::AtomicUsize;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use tokio::sync::broadcast;
 use tower_http::cors::{AllowOrigin, CorsLayer};
 
+#[cfg(feature = "embedded")]
+use crate::config::AuthPolicy;
 use crate::config::Config;
 use crate::connection;
 use crate::db::Db;
+use crate::key_rotation::{self, KeyHistory};
+use crate::membership_bus::{self, MembershipChange};
 use crate::nostr::event::Event;
+#[cfg(feature = "embedded")]
+use crate::nostr::nip13::PowPolicy;
+use crate::nostr::nip29::slow_mode::SlowMode;
+use crate::protocol::{nip86, nip98};
+use crate::rate_limit::{self, RateLimiter};
 use crate::relay_identity::RelayIdentity;
 
 pub struct AppState {
     pub pool: Db,
     pub config: Config,
     pub broadcast_tx: broadcast::Sender<Event>,
+    /// Membership-change bus: every connection re-resolves its cached group
+    /// set when a change concerning it is published (see `membership_bus`).
+    pub membership_tx: broadcast::Sender<MembershipChange>,
     pub relay_identity: RelayIdentity,
     pub active_connections: AtomicUsize,
     /// Relay WebSocket URL used for NIP-42 AUTH challenge verification
@@ -32,29 +45,91 @@ pub struct AppState {
     /// relay (behind the rate-limiting gateway) leaves this false.
     pub hosted_only: bool,
     /// In `hosted_only` mode, the only pubkey allowed to create groups (9007).
+    /// Also a NIP-86 operator.
     pub owner_pubkey: Option<String>,
+    /// NIP-11 name/description as currently served: the config values unless
+    /// an operator changed them through NIP-86.
+    pub relay_info: RwLock<RelayInfo>,
+    /// NIP-86 ban and allow lists, checked on every EVENT.
+    pub bans: RwLock<nip86::Bans>,
+    /// Token buckets shared by every connection (see `rate_limit`).
+    pub rate_limiter: RateLimiter,
+    /// Last chat post per (group, member), for group slow mode.
+    pub slow_mode: SlowMode,
+    /// The relay's previous signing key, if it was rotated (see
+    /// `key_rotation`); NIP-11 lists it during the grace period.
+    pub key_history: Option<KeyHistory>,
+}
+
+/// The NIP-11 fields operators can change at runtime.
+pub struct RelayInfo {
+    pub name: String,
+    pub description: String,
+}
+
+/// The configured NIP-11 name/description with any stored NIP-86 overrides
+/// applied.
+async fn load_relay_info(db: &Db, config: &Config) -> anyhow::Result<RelayInfo> {
+    Ok(RelayInfo {
+        name: db
+            .get_setting(nip86::SETTING_RELAY_NAME)
+            .await?
+            .unwrap_or_else(|| config.relay_name.clone()),
+        description: db
+            .get_setting(nip86::SETTING_RELAY_DESCRIPTION)
+            .await?
+            .unwrap_or_else(|| config.relay_description.clone()),
+    })
 }
 
 pub async fn run(config: Config, pool: Db) -> anyhow::Result<()> {
     let port = config.port;
 
     let (broadcast_tx, _) = broadcast::channel::<Event>(4096);
+    let (membership_tx, _) = broadcast::channel::<MembershipChange>(membership_bus::BUS_CAPACITY);
+
+    // Backend REST joins/kicks write `app.space_members` directly; its trigger
+    // NOTIFYs us so open connections learn about them immediately.
+    if let Some(pg) = pool.as_pg() {
+        membership_bus::spawn_pg_listener(pg.clone(), membership_tx.clone());
+    }
 
     let relay_identity = RelayIdentity::new(config.relay_secret_key.clone(), &config.rust_env);
+    let previous_identity = config
+        .relay_previous_secret_key
+        .clone()
+        .map(|key| RelayIdentity::from_secret_key(&key));
 
     let relay_url = std::env::var("RELAY_URL")
         .unwrap_or_else(|_| format!("ws://localhost:{}", port));
+    let key_history = key_rotation::rotate_if_needed(
+        &pool,
+        &relay_identity,
+        previous_identity.as_ref(),
+        &relay_url,
+        &broadcast_tx,
+    )
+    .await?;
+    let relay_info = load_relay_info(&pool, &config).await?;
+    let bans = nip86::Bans::load(&pool).await?;
+    let rate_limiter = RateLimiter::new(config.rate_limits.clone());
 
     let state = Arc::new(AppState {
         pool,
         config,
         broadcast_tx,
+        membership_tx,
         relay_identity,
         active_connections: AtomicUsize::new(0),
         relay_url,
         // Production is a multi-tenant relay behind the rate-limiting gateway.
         hosted_only: false,
         owner_pubkey: None,
+        relay_info: RwLock::new(relay_info),
+        bans: RwLock::new(bans),
+        rate_limiter,
+        slow_mode: SlowMode::new(),
+        key_history,
     });
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
@@ -67,17 +142,17 @@ pub async fn run(config: Config, pool: Db) -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Build the axum router (NIP-11 / WebSocket / health) for a given state.
+/// Build the axum router (NIP-11 / WebSocket / NIP-86 / health) for a given state.
 /// Shared by the production server ([`run`]) and the embedded relay
 /// ([`run_embedded`]).
 fn build_app(state: Arc<AppState>) -> Router {
     let cors = CorsLayer::new()
         .allow_origin(AllowOrigin::any())
-        .allow_methods([Method::GET, Method::OPTIONS])
-        .allow_headers([header::ACCEPT, header::CONTENT_TYPE]);
+        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_headers([header::ACCEPT, header::CONTENT_TYPE, header::AUTHORIZATION]);
 
     Router::new()
-        .route("/", get(root_handler))
+        .route("/", get(root_handler).post(management_handler))
         .route("/health", get(health))
         .layer(cors)
         .with_state(state)
@@ -123,25 +198,54 @@ impl EmbeddedRelay {
     }
 }
 
+/// How the host app configures an embedded relay ([`run_embedded`]).
+#[cfg(feature = "embedded")]
+#[derive(Default)]
+pub struct EmbeddedConfig {
+    /// `0` lets the OS choose a free port — read it back from
+    /// [`EmbeddedRelay::addr`].
+    pub port: u16,
+    pub relay_name: String,
+    /// Should be a persisted 32-byte hex key so the relay's 39xxx signatures
+    /// stay stable across restarts; `None` generates an ephemeral key (fine
+    /// for tests / first run).
+    pub relay_secret_key: Option<String>,
+    /// The key `relay_secret_key` replaces, if the host rotated it: the relay
+    /// then signs a handover and re-signs its groups (`key_rotation`).
+    pub relay_previous_secret_key: Option<String>,
+    /// The only identity allowed to create groups.
+    pub owner_pubkey: Option<String>,
+    /// Bind `0.0.0.0` instead of loopback.
+    pub bind_lan: bool,
+    /// NIP-42 auth-required mode for a fully private relay
+    /// ([`AuthPolicy::Open`] keeps anonymous reads of public events).
+    pub auth_policy: AuthPolicy,
+    /// NIP-13 minimum difficulties, relay-wide and per kind (groups can ask
+    /// for more with 9002 `min_pow`).
+    pub pow_policy: PowPolicy,
+}
+
 /// Start an embedded relay bound to **loopback only** (`127.0.0.1`), backed by
-/// `db` (a `Db::Sqlite`). `port = 0` lets the OS choose a free port — read it
-/// back from [`EmbeddedRelay::addr`]. `relay_secret_key` should be a persisted
-/// 32-byte hex key so the relay's 39xxx signatures stay stable across restarts;
-/// `None` generates an ephemeral key (fine for tests / first run).
+/// `db` (a `Db::Sqlite`), configured by `embedded` (see [`EmbeddedConfig`]).
 ///
 /// Returns once the listener is bound, so the caller can immediately hand out
 /// the `ws_url()`.
 #[cfg(feature = "embedded")]
-pub async fn run_embedded(
-    db: Db,
-    port: u16,
-    relay_name: String,
-    relay_secret_key: Option<String>,
-    owner_pubkey: Option<String>,
-    bind_lan: bool,
-) -> anyhow::Result<EmbeddedRelay> {
+pub async fn run_embedded(db: Db, embedded: EmbeddedConfig) -> anyhow::Result<EmbeddedRelay> {
+    let EmbeddedConfig {
+        port,
+        relay_name,
+        relay_secret_key,
+        relay_previous_secret_key,
+        owner_pubkey,
+        bind_lan,
+        auth_policy,
+        pow_policy,
+    } = embedded;
     let (broadcast_tx, _) = broadcast::channel::<Event>(4096);
+    let (membership_tx, _) = broadcast::channel::<MembershipChange>(membership_bus::BUS_CAPACITY);
     let relay_identity = RelayIdentity::new(relay_secret_key, "development");
+    let previous_identity = relay_previous_secret_key.map(|key| RelayIdentity::from_secret_key(&key));
     let pubkey = relay_identity.pubkey.clone();
 
     // Loopback by default; `bind_lan` exposes the relay on the local network
@@ -176,13 +280,31 @@ pub async fn run_embedded(
         relay_name,
         relay_description: "Embedded NIP-29 relay".to_string(),
         relay_secret_key: None,
+        relay_previous_secret_key: None,
+        key_grace: key_rotation::DEFAULT_KEY_GRACE,
         rust_env: "development".to_string(),
+        auth_policy,
+        auth_req_hold: crate::config::DEFAULT_AUTH_REQ_HOLD,
+        admin_pubkeys: Default::default(),
+        rate_limits: Default::default(),
+        trust_forwarded_for: false,
+        allowlist_only: false,
+        pow_policy,
+        timeline_policy: Default::default(),
+        zap_provider_pubkeys: Default::default(),
     };
+    let relay_info = load_relay_info(&db, &config).await?;
+    let bans = nip86::Bans::load(&db).await?;
+    let rate_limiter = RateLimiter::new(config.rate_limits.clone());
+    let key_history =
+        key_rotation::rotate_if_needed(&db, &relay_identity, previous_identity.as_ref(), &relay_url, &broadcast_tx)
+            .await?;
 
     let state = Arc::new(AppState {
         pool: db,
         config,
         broadcast_tx,
+        membership_tx,
         relay_identity,
         active_connections: AtomicUsize::new(0),
         relay_url,
@@ -190,6 +312,11 @@ pub async fn run_embedded(
         // and only the owner may create them.
         hosted_only: true,
         owner_pubkey,
+        relay_info: RwLock::new(relay_info),
+        bans: RwLock::new(bans),
+        rate_limiter,
+        slow_mode: SlowMode::new(),
+        key_history,
     });
 
     let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
@@ -337,13 +464,15 @@ async fn root_handler(
     }
 
     // Otherwise, attempt WebSocket upgrade
+    let client_ip = rate_limit::client_ip(req.headers(), addr, state.config.trust_forwarded_for);
     match WebSocketUpgrade::from_request(req, &*state).await {
         Ok(ws) => {
             let broadcast_rx = state.broadcast_tx.subscribe();
+            let membership_rx = state.membership_tx.subscribe();
             tracing::debug!(remote = %addr, "WebSocket upgrade");
             let resp: Response = ws
                 .on_upgrade(move |socket| {
-                    connection::handle_connection(socket, state, broadcast_rx, addr)
+                    connection::handle_connection(socket, state, broadcast_rx, membership_rx, addr, client_ip)
                 })
                 .into_response();
             resp
@@ -352,15 +481,67 @@ async fn root_handler(
     }
 }
 
+/// Handles POST / — the NIP-86 management API. Every request carries its own
+/// NIP-98 `Authorization` header bound to this URL, method and body.
+async fn management_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let is_rpc = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("application/nostr+json+rpc"));
+    if !is_rpc {
+        return (StatusCode::UNSUPPORTED_MEDIA_TYPE, "expected application/nostr+json+rpc")
+            .into_response();
+    }
+
+    let caller = match nip98::verify_http_auth(
+        headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()),
+        &state.relay_url,
+        "POST",
+        &body,
+        // Same rule as NIP-42: the embedded relay has no single canonical URL.
+        !state.hosted_only,
+        chrono::Utc::now().timestamp(),
+    ) {
+        Ok(pubkey) => pubkey,
+        Err(reason) => return (StatusCode::UNAUTHORIZED, reason).into_response(),
+    };
+
+    let response = match serde_json::from_slice::<nip86::Request>(&body) {
+        Ok(req) => nip86::handle(&state, &caller, req).await,
+        Err(_) => serde_json::json!({ "result": null, "error": "invalid request" }),
+    };
+    (
+        [(header::CONTENT_TYPE, "application/nostr+json+rpc")],
+        Json(response),
+    )
+        .into_response()
+}
+
 fn nip11_response(state: &AppState) -> impl IntoResponse {
+    let (name, description) = {
+        let info = state.relay_info.read().unwrap_or_else(|e| e.into_inner());
+        (info.name.clone(), info.description.clone())
+    };
     let info = serde_json::json!({
-        "name": state.config.relay_name,
-        "description": state.config.relay_description,
+        "name": name,
+        "description": description,
         // The relay's master key that signs NIP-29 group state (39000/39001/39002).
         // Clients MUST pin this as the expected author when reading group metadata,
         // otherwise any pubkey can forge a group's admin/member lists.
         "pubkey": state.relay_identity.pubkey,
-        "supported_nips": [1, 2, 9, 11, 29, 42, 50],
+        // After a key rotation, the previous key too until its grace period
+        // ends; a kind:39011 signed by it names the current one.
+        "pubkeys": key_rotation::advertised_pubkeys(
+            &state.relay_identity.pubkey,
+            state.key_history.as_ref(),
+            chrono::Utc::now().timestamp(),
+            state.config.key_grace,
+        ),
+        "supported_nips": [1, 2, 9, 11, 13, 29, 42, 50, 56, 59, 62, 70, 86, 98],
         "software": "thewired-relay",
         "version": env!("CARGO_PKG_VERSION"),
         "limitation": {
@@ -369,7 +550,11 @@ fn nip11_response(state: &AppState) -> impl IntoResponse {
             // that respect NIP-11 don't build REQs the relay then rejects.
             "max_filters": 16,
             "max_event_tags": 2500,
-            "max_content_length": 102400
+            "max_content_length": 102400,
+            "auth_required": state.config.auth_policy.is_required(),
+            // Relay-wide NIP-13 minimum; per-kind and per-group (39000
+            // `min_pow`) minimums can be higher.
+            "min_pow_difficulty": state.config.pow_policy.min_difficulty
         }
     });
     (
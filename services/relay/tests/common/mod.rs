@@ -33,8 +33,9 @@ use tokio::sync::OnceCell;
 use thewired_relay::{
     config::Config,
     nostr::event::Event,
+    rate_limit::RateLimiter,
     relay_identity::RelayIdentity,
-    server::AppState,
+    server::{AppState, RelayInfo},
 };
 use tokio::sync::broadcast;
 
@@ -109,12 +110,38 @@ pub async fn setup_test_pool() -> Result<PgPool, String> {
                 joined_at TIMESTAMPTZ DEFAULT NOW(),
                 PRIMARY KEY (space_id, pubkey)
             );
+            CREATE TABLE app.space_roles (
+                id TEXT PRIMARY KEY,
+                space_id TEXT NOT NULL REFERENCES app.spaces(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                is_admin BOOLEAN NOT NULL DEFAULT FALSE
+            );
+            CREATE TABLE app.role_permissions (
+                role_id TEXT NOT NULL REFERENCES app.space_roles(id) ON DELETE CASCADE,
+                permission TEXT NOT NULL,
+                PRIMARY KEY (role_id, permission)
+            );
+            CREATE TABLE app.member_roles (
+                space_id TEXT NOT NULL,
+                pubkey TEXT NOT NULL,
+                role_id TEXT NOT NULL,
+                PRIMARY KEY (space_id, pubkey, role_id)
+            );
             "#,
         )
         .execute(&init_pool)
         .await
         .map_err(|e| format!("app.* tables: {e}"))?;
 
+        // The backend's membership NOTIFY trigger (0027_space_members_notify.sql),
+        // which the relay's membership bus LISTENs to.
+        sqlx::raw_sql(include_str!(
+            "../../../backend/src/db/migrations/0027_space_members_notify.sql"
+        ))
+        .execute(&init_pool)
+        .await
+        .map_err(|e| format!("app.* trigger: {e}"))?;
+
         Ok::<(), String>(())
     })
     .await?;
@@ -135,8 +162,21 @@ async fn truncate_all(pool: &PgPool) -> sqlx::Result<()> {
             relay.groups,
             relay.group_members,
             relay.group_roles,
+            relay.group_mutes,
             relay.invite_codes,
+            relay.vanished_pubkeys,
+            relay.deleted_events,
+            relay.deleted_addresses,
+            relay.banned_pubkeys,
+            relay.allowed_pubkeys,
+            relay.banned_events,
+            relay.settings,
+            relay.reports,
+            relay.moderation_log,
             app.space_members,
+            app.member_roles,
+            app.role_permissions,
+            app.space_roles,
             app.spaces
         RESTART IDENTITY CASCADE;
         "#,
@@ -187,6 +227,40 @@ pub async fn remove_member(pool: &PgPool, space_id: &str, pubkey: &str) -> sqlx:
     Ok(())
 }
 
+/// Give `pubkey` a backend space role (`app.member_roles`) with these
+/// permissions, creating the role if needed.
+pub async fn grant_space_role(
+    pool: &PgPool,
+    space_id: &str,
+    pubkey: &str,
+    role_id: &str,
+    is_admin: bool,
+    permissions: &[&str],
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO app.space_roles (id, space_id, name, is_admin) VALUES ($1, $2, $1, $3) ON CONFLICT DO NOTHING",
+    )
+    .bind(role_id)
+    .bind(space_id)
+    .bind(is_admin)
+    .execute(pool)
+    .await?;
+    for permission in permissions {
+        sqlx::query("INSERT INTO app.role_permissions (role_id, permission) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(role_id)
+            .bind(permission)
+            .execute(pool)
+            .await?;
+    }
+    sqlx::query("INSERT INTO app.member_roles (space_id, pubkey, role_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+        .bind(space_id)
+        .bind(pubkey)
+        .bind(role_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 // ── Event signing helpers ───────────────────────────────────────────────
 
 /// A reproducible test identity: 32-byte secret key derived from a seed byte.
@@ -270,11 +344,23 @@ pub fn sign_h_tagged(
 /// the production server would compute for `ws://localhost:7777`.
 pub fn make_app_state(pool: PgPool) -> (Arc<AppState>, broadcast::Sender<Event>) {
     let (tx, _) = broadcast::channel::<Event>(64);
+    let (membership_tx, _) = broadcast::channel(64);
     let config = Config {
         port: 7777,
         database_url: test_db_url(),
         rust_env: "test".to_string(),
+        auth_policy: Default::default(),
+        auth_req_hold: std::time::Duration::ZERO,
+        admin_pubkeys: Default::default(),
+        rate_limits: Default::default(),
+        trust_forwarded_for: false,
+        allowlist_only: false,
+        pow_policy: Default::default(),
+        timeline_policy: Default::default(),
+        zap_provider_pubkeys: Default::default(),
         relay_secret_key: None,
+        relay_previous_secret_key: None,
+        key_grace: Default::default(),
         relay_name: "test-relay".to_string(),
         relay_description: "test".to_string(),
     };
@@ -283,11 +369,20 @@ pub fn make_app_state(pool: PgPool) -> (Arc<AppState>, broadcast::Sender<Event>)
         pool: thewired_relay::db::Db::Pg(pool),
         config,
         broadcast_tx: tx.clone(),
+        membership_tx,
         relay_identity,
         active_connections: std::sync::atomic::AtomicUsize::new(0),
         relay_url: "ws://localhost:7777".to_string(),
         hosted_only: false,
         owner_pubkey: None,
+        relay_info: std::sync::RwLock::new(RelayInfo {
+            name: "test-relay".to_string(),
+            description: "test".to_string(),
+        }),
+        bans: Default::default(),
+        rate_limiter: RateLimiter::new(Default::default()),
+        slow_mode: thewired_relay::nostr::nip29::slow_mode::SlowMode::new(),
+        key_history: None,
     };
     (Arc::new(state), tx)
 }
@@ -305,7 +400,7 @@ pub async fn send_event(
     let subs = Arc::new(tokio::sync::Mutex::new(
         thewired_relay::protocol::subscription::SubscriptionManager::new(),
     ));
-    let mut authed: Option<String> = None;
+    let mut authed: Vec<String> = Vec::new();
     let mut memberships: std::collections::HashSet<String> = Default::default();
     let challenge = "test-challenge";
 
@@ -20,6 +20,7 @@ mod common;
 
 use common::{sign_event, TestIdentity};
 use thewired_relay::db::{sqlite, Db};
+use thewired_relay::music::catalog::CatalogQuery;
 
 /// Everything observable from running the standard op sequence. `PartialEq` so
 /// we can assert the two backends agree field-for-field.
@@ -43,6 +44,11 @@ struct Obs {
     group_has_bob_after_remove: bool,
     existed_before_delete: bool,
     exists_after_delete: bool,
+    refused_newer_profile_stored: bool,
+    profile_kept_after_refused_newer: bool,
+    note_a_counters: Vec<(String, String, i64)>,
+    note_a_gated_contributions: Vec<String>,
+    catalog_pages: Vec<Vec<String>>,
 }
 
 /// Drive a full relay-native lifecycle through `db` and capture observations.
@@ -56,15 +62,15 @@ async fn exercise(db: &Db, alice: &TestIdentity, bob: &TestIdentity) -> Obs {
     let inserted_second = db.store_event(&note_b).await.unwrap();
 
     let by_id = db
-        .query_events(&filt(serde_json::json!({ "ids": [note_a.id] })), None)
+        .query_events(&filt(serde_json::json!({ "ids": [note_a.id] })), &[])
         .await
         .unwrap();
     let by_author = db
-        .query_events(&filt(serde_json::json!({ "authors": [bob.pubkey] })), None)
+        .query_events(&filt(serde_json::json!({ "authors": [bob.pubkey] })), &[])
         .await
         .unwrap();
     let by_kind = db
-        .query_events(&filt(serde_json::json!({ "kinds": [1] })), None)
+        .query_events(&filt(serde_json::json!({ "kinds": [1] })), &[])
         .await
         .unwrap();
 
@@ -92,6 +98,54 @@ async fn exercise(db: &Db, alice: &TestIdentity, bob: &TestIdentity) -> Obs {
     db.delete_event(&note_a.id).await.unwrap();
     let exists_after_delete = db.get_event_by_id(&note_a.id).await.unwrap().is_some();
 
+    // --- replaceable: a newer version refused by a tombstone keeps the old ---
+    let profile_v1 = sign_event(alice, 0, vec![], "{\"name\":\"alice\"}", 200);
+    let profile_v2 = sign_event(alice, 0, vec![], "{\"name\":\"banned\"}", 300);
+    db.store_event(&profile_v1).await.unwrap();
+    db.ban_event(&profile_v2.id, None).await.unwrap();
+    let refused_newer_profile_stored = db.store_event(&profile_v2).await.unwrap();
+    let profiles = db
+        .query_events(&filt(serde_json::json!({ "kinds": [0], "authors": [alice.pubkey] })), &[])
+        .await
+        .unwrap();
+    let profile_kept_after_refused_newer =
+        profiles.iter().map(|e| e.id.as_str()).collect::<Vec<_>>() == [profile_v1.id.as_str()];
+
+    // --- aggregates: a group-scoped reaction stays out of the counters ---
+    let like = sign_event(bob, 7, vec![vec!["e".into(), note_a.id.clone()]], "+", 400);
+    let group_like = sign_event(
+        bob,
+        7,
+        vec![vec!["h".into(), "g1".into()], vec!["e".into(), note_a.id.clone()]],
+        "🔥",
+        401,
+    );
+    db.store_event(&like).await.unwrap();
+    db.store_event(&group_like).await.unwrap();
+    let targets = [note_a.id.clone()];
+    let note_a_counters = db
+        .event_aggregates(&targets)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(_, metric, key, count, _)| (metric, key, count))
+        .collect();
+    let note_a_gated_contributions =
+        db.gated_contributions(&targets).await.unwrap().into_iter().map(|(id, ..)| id).collect();
+
+    // --- music catalog: indexed on store, paged best first ---
+    for track in tracks(alice) {
+        db.store_event(&track).await.unwrap();
+    }
+    let by_artist = CatalogQuery {
+        artist: Some("band".into()),
+        ..Default::default()
+    };
+    let mut catalog_pages = Vec::new();
+    for offset in [0, 1] {
+        catalog_pages.push(db.music_catalog_ids(&by_artist, &[], &[], 1, offset).await.unwrap());
+    }
+
     Obs {
         inserted_first,
         inserted_dup,
@@ -111,9 +165,29 @@ async fn exercise(db: &Db, alice: &TestIdentity, bob: &TestIdentity) -> Obs {
         group_has_bob_after_remove,
         existed_before_delete,
         exists_after_delete,
+        refused_newer_profile_stored,
+        profile_kept_after_refused_newer,
+        note_a_counters,
+        note_a_gated_contributions,
+        catalog_pages,
     }
 }
 
+/// Two tracks by the same artist, the second released later.
+fn tracks(author: &TestIdentity) -> Vec<thewired_relay::nostr::event::Event> {
+    [("early", 500), ("late", 501)]
+        .into_iter()
+        .map(|(d, at)| {
+            let tags = vec![
+                vec!["d".into(), d.into()],
+                vec!["title".into(), d.into()],
+                vec!["artist".into(), "Band".into()],
+            ];
+            sign_event(author, 31683, tags, "", at)
+        })
+        .collect()
+}
+
 fn filt(json: serde_json::Value) -> thewired_relay::nostr::filter::Filter {
     serde_json::from_value(json).unwrap()
 }
@@ -145,6 +219,18 @@ fn expected(alice: &TestIdentity, bob: &TestIdentity, note_a_id: &str, note_b_id
         group_has_bob_after_remove: false,
         existed_before_delete: true,
         exists_after_delete: false,
+        refused_newer_profile_stored: false,
+        profile_kept_after_refused_newer: true,
+        note_a_counters: vec![("reaction".into(), "+".into(), 1)],
+        note_a_gated_contributions: vec![sign_event(
+            bob,
+            7,
+            vec![vec!["h".into(), "g1".into()], vec!["e".into(), note_a_id.to_string()]],
+            "🔥",
+            401,
+        )
+        .id],
+        catalog_pages: tracks(alice).into_iter().rev().map(|t| vec![t.id]).collect(),
     }
 }
 
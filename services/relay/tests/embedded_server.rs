@@ -15,8 +15,10 @@ mod common;
 use common::{sign_event, TestIdentity};
 use futures::{SinkExt, Stream, StreamExt};
 use std::time::Duration;
+use thewired_relay::config::AuthPolicy;
 use thewired_relay::db::{sqlite, Db};
-use thewired_relay::server;
+use thewired_relay::nostr::nip13::PowPolicy;
+use thewired_relay::server::{self, EmbeddedConfig};
 use tokio::time::timeout;
 use tokio_tungstenite::{connect_async, tungstenite::Error as WsError, tungstenite::Message};
 
@@ -46,6 +48,16 @@ fn event_frame(event: &thewired_relay::nostr::event::Event) -> Message {
     Message::Text(format!(r#"["EVENT",{}]"#, serde_json::to_string(event).unwrap()).into())
 }
 
+/// An embedded relay on an OS-assigned port, with an ephemeral key, owned by
+/// `owner`.
+fn owned_by(owner: &TestIdentity) -> EmbeddedConfig {
+    EmbeddedConfig {
+        relay_name: "t".into(),
+        owner_pubkey: Some(owner.pubkey.clone()),
+        ..Default::default()
+    }
+}
+
 /// Current unix time — AUTH events (kind:22242) must be fresh (±10 min).
 fn now() -> i64 {
     std::time::SystemTime::now()
@@ -61,7 +73,7 @@ async fn embedded_relay_round_trips_nip29_group() {
     // create groups.
     let alice = TestIdentity::from_seed(7);
     let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
-    let relay = server::run_embedded(db, 0, "embedded-test".to_string(), None, Some(alice.pubkey.clone()), false)
+    let relay = server::run_embedded(db, EmbeddedConfig { relay_name: "embedded-test".into(), ..owned_by(&alice) })
         .await
         .expect("embedded relay should start");
 
@@ -158,7 +170,7 @@ async fn embedded_relay_rejects_nonmember_publish() {
     let owner = TestIdentity::from_seed(7);
     let stranger = TestIdentity::from_seed(8);
     let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
-    let relay = server::run_embedded(db, 0, "embedded-test".to_string(), None, Some(owner.pubkey.clone()), false)
+    let relay = server::run_embedded(db, EmbeddedConfig { relay_name: "embedded-test".into(), ..owned_by(&owner) })
         .await
         .unwrap();
     let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
@@ -203,14 +215,7 @@ async fn embedded_relay_is_not_an_open_relay() {
     let owner = TestIdentity::from_seed(7);
     let stranger = TestIdentity::from_seed(8);
     let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
-    let relay = server::run_embedded(
-        db,
-        0,
-        "embedded-test".to_string(),
-        None,
-        Some(owner.pubkey.clone()),
-        false,
-    )
+    let relay = server::run_embedded(db, EmbeddedConfig { relay_name: "embedded-test".into(), ..owned_by(&owner) })
     .await
     .unwrap();
     let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
@@ -269,7 +274,7 @@ async fn embedded_relay_rejects_forged_group_metadata() {
     let owner = TestIdentity::from_seed(7);
     let mallory = TestIdentity::from_seed(42);
     let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
-    let relay = server::run_embedded(db, 0, "t".into(), None, Some(owner.pubkey.clone()), false)
+    let relay = server::run_embedded(db, owned_by(&owner))
         .await
         .unwrap();
     let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
@@ -306,7 +311,7 @@ async fn embedded_relay_nonadmin_moddelete_not_stored() {
     let owner = TestIdentity::from_seed(7);
     let mallory = TestIdentity::from_seed(42);
     let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
-    let relay = server::run_embedded(db, 0, "t".into(), None, Some(owner.pubkey.clone()), false)
+    let relay = server::run_embedded(db, owned_by(&owner))
         .await
         .unwrap();
 
@@ -349,3 +354,1603 @@ async fn embedded_relay_nonadmin_moddelete_not_stored() {
 
     relay.stop().await;
 }
+
+/// Sign and frame a NIP-42 AUTH response for `challenge`.
+fn auth_frame(who: &TestIdentity, relay_url: &str, challenge: &str) -> Message {
+    let auth = sign_event(
+        who,
+        22242,
+        vec![
+            vec!["relay".into(), relay_url.to_string()],
+            vec!["challenge".into(), challenge.to_string()],
+        ],
+        "",
+        now(),
+    );
+    Message::Text(format!(r#"["AUTH",{}]"#, serde_json::to_string(&auth).unwrap()).into())
+}
+
+/// A kick (9001) takes effect on the kicked member's OPEN socket immediately:
+/// their group-scoped subscription is CLOSED and later messages never reach
+/// them — no 30 s membership-cache window.
+#[tokio::test]
+async fn embedded_relay_kick_closes_member_subscriptions() {
+    let owner = TestIdentity::from_seed(7);
+    let bob = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    // Owner: create the group and add bob.
+    let (ws1, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx1, mut rx1) = ws1.split();
+    assert!(read_until(&mut rx1, "AUTH").await.is_some());
+    let create = sign_event(&owner, 9007, vec![vec!["h".into(), "g".into()]], "G", 1_700_000_000);
+    tx1.send(event_frame(&create)).await.unwrap();
+    assert_eq!(read_until(&mut rx1, "OK").await.unwrap().get(2).and_then(|v| v.as_bool()), Some(true));
+    let put = sign_event(
+        &owner,
+        9000,
+        vec![vec!["h".into(), "g".into()], vec!["p".into(), bob.pubkey.clone()]],
+        "",
+        1_700_000_001,
+    );
+    tx1.send(event_frame(&put)).await.unwrap();
+    assert_eq!(read_until(&mut rx1, "OK").await.unwrap().get(2).and_then(|v| v.as_bool()), Some(true));
+
+    // Bob: authenticate and subscribe to the group's chat.
+    let (ws2, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx2, mut rx2) = ws2.split();
+    let ch = read_until(&mut rx2, "AUTH").await.unwrap();
+    let challenge = ch.get(1).and_then(|v| v.as_str()).unwrap().to_string();
+    tx2.send(auth_frame(&bob, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut rx2, "OK").await.unwrap().get(2).and_then(|v| v.as_bool()), Some(true));
+    tx2.send(Message::Text(r##"["REQ","chat",{"#h":["g"],"kinds":[9]}]"##.into()))
+        .await
+        .unwrap();
+    assert!(read_until(&mut rx2, "EOSE").await.is_some());
+
+    // Owner kicks bob → bob's open subscription is CLOSED right away.
+    let kick = sign_event(
+        &owner,
+        9001,
+        vec![vec!["h".into(), "g".into()], vec!["p".into(), bob.pubkey.clone()]],
+        "",
+        1_700_000_002,
+    );
+    tx1.send(event_frame(&kick)).await.unwrap();
+    assert_eq!(read_until(&mut rx1, "OK").await.unwrap().get(2).and_then(|v| v.as_bool()), Some(true));
+    let closed = read_until(&mut rx2, "CLOSED").await.expect("kicked member's sub not CLOSED");
+    assert_eq!(closed.get(1).and_then(|v| v.as_str()), Some("chat"));
+    assert!(
+        closed.get(2).and_then(|v| v.as_str()).unwrap_or_default().starts_with("restricted:"),
+        "unexpected CLOSED reason: {closed}"
+    );
+
+    relay.stop().await;
+}
+
+/// GET the NIP-11 document over a bare HTTP/1.1 request (no HTTP client dep).
+async fn fetch_nip11(addr: std::net::SocketAddr) -> serde_json::Value {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nAccept: application/nostr+json\r\nConnection: close\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut raw = String::new();
+    timeout(T, stream.read_to_string(&mut raw)).await.unwrap().unwrap();
+    let body = raw.split_once("\r\n\r\n").map(|(_, b)| b).unwrap_or("");
+    serde_json::from_str(body).unwrap_or_else(|e| panic!("bad NIP-11 body ({e}): {raw}"))
+}
+
+/// Auth-required mode (allowlist): REQ/EVENT before AUTH get `auth-required:`
+/// CLOSED/OK, a non-allowlisted pubkey's AUTH is refused, and an allowlisted
+/// pubkey is served normally once authenticated. NIP-11 advertises it.
+#[tokio::test]
+async fn embedded_relay_auth_required_mode() {
+    let owner = TestIdentity::from_seed(7);
+    let stranger = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let policy = AuthPolicy::Allowlist([owner.pubkey.clone()].into_iter().collect());
+    let relay = server::run_embedded(db, EmbeddedConfig { auth_policy: policy, ..owned_by(&owner) })
+        .await
+        .unwrap();
+
+    let info: serde_json::Value = fetch_nip11(relay.addr).await;
+    assert_eq!(info["limitation"]["auth_required"], serde_json::json!(true), "{info}");
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    let challenge = read_until(&mut rx, "AUTH").await.unwrap()[1].as_str().unwrap().to_string();
+
+    tx.send(Message::Text(r#"["REQ","early",{"kinds":[1]}]"#.into())).await.unwrap();
+    let closed = read_until(&mut rx, "CLOSED").await.expect("no CLOSED before AUTH");
+    assert_eq!(closed[1], "early");
+    assert!(closed[2].as_str().unwrap().starts_with("auth-required:"), "{closed}");
+
+    let note = sign_event(&owner, 1, vec![], "hi", now());
+    tx.send(event_frame(&note)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.expect("no OK before AUTH");
+    assert_eq!(ok[1], note.id.as_str());
+    assert_eq!(ok[2], false);
+    assert!(ok[3].as_str().unwrap().starts_with("auth-required:"), "{ok}");
+
+    // A pubkey outside the allowlist can't authenticate…
+    tx.send(auth_frame(&stranger, &relay.ws_url(), &challenge)).await.unwrap();
+    let refused = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(refused[2], false, "{refused}");
+    assert!(refused[3].as_str().unwrap().starts_with("restricted:"), "{refused}");
+    tx.send(Message::Text(r#"["REQ","still",{"kinds":[1]}]"#.into())).await.unwrap();
+    let closed = read_until(&mut rx, "CLOSED").await.unwrap();
+    assert!(closed[2].as_str().unwrap().starts_with("auth-required:"), "{closed}");
+
+    // …the allowlisted owner can, and is then served.
+    tx.send(auth_frame(&owner, &relay.ws_url(), &challenge)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], true, "{ok}");
+    tx.send(Message::Text(r#"["REQ","late",{"kinds":[1]}]"#.into())).await.unwrap();
+    let eose = read_until(&mut rx, "EOSE").await.expect("no EOSE after AUTH");
+    assert_eq!(eose[1], "late");
+
+    relay.stop().await;
+}
+
+/// One socket, two accounts: a second AUTH adds to (not replaces) the
+/// connection's identities and group reads union their memberships. In
+/// auth-required mode the connection must be authenticated to publish, but
+/// not as the event's author (events may be relayed on someone's behalf).
+#[tokio::test]
+async fn embedded_relay_multi_account_connection() {
+    let owner = TestIdentity::from_seed(7);
+    let carol = TestIdentity::from_seed(9);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, EmbeddedConfig { auth_policy: AuthPolicy::AnyPubkey, ..owned_by(&owner) })
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    let challenge = read_until(&mut rx, "AUTH").await.unwrap()[1].as_str().unwrap().to_string();
+
+    // Unauthenticated: refused.
+    let create = sign_event(&owner, 9007, vec![vec!["h".into(), "g".into()]], "G", 1_700_000_000);
+    tx.send(event_frame(&create)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false, "{ok}");
+    assert!(ok[3].as_str().unwrap().starts_with("auth-required:"), "{ok}");
+
+    // Authenticated as carol only: the owner's events go through.
+    tx.send(auth_frame(&carol, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    tx.send(event_frame(&create)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let chat = sign_event(&owner, 9, vec![vec!["h".into(), "g".into()]], "hi", 1_700_000_001);
+    tx.send(event_frame(&chat)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    // Add the owner on the same socket.
+    tx.send(auth_frame(&owner, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    // carol isn't in g, but the owner is — the shared socket reads g's history.
+    tx.send(Message::Text(r##"["REQ","g",{"#h":["g"],"kinds":[9]}]"##.into()))
+        .await
+        .unwrap();
+    let evt = read_until(&mut rx, "EVENT").await.expect("no group history for multi-account socket");
+    assert_eq!(evt[2]["id"], chat.id.as_str());
+
+    relay.stop().await;
+}
+
+/// NIP29_CHAT Phase 3: a client that REQs a group before answering the AUTH
+/// challenge still gets the group's history — the relay holds the REQ and
+/// replays it once AUTH succeeds, instead of answering it as anonymous.
+#[tokio::test]
+async fn embedded_relay_replays_reqs_sent_before_auth() {
+    let owner = TestIdentity::from_seed(7);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws1, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx1, mut rx1) = ws1.split();
+    assert!(read_until(&mut rx1, "AUTH").await.is_some());
+    let create = sign_event(&owner, 9007, vec![vec!["h".into(), "g".into()]], "G", 1_700_000_000);
+    tx1.send(event_frame(&create)).await.unwrap();
+    assert_eq!(read_until(&mut rx1, "OK").await.unwrap()[2], true);
+    let chat = sign_event(&owner, 9, vec![vec!["h".into(), "g".into()]], "hi", 1_700_000_001);
+    tx1.send(event_frame(&chat)).await.unwrap();
+    assert_eq!(read_until(&mut rx1, "OK").await.unwrap()[2], true);
+
+    // Reconnect: the REQ goes out before the challenge is even read.
+    let (ws2, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx2, mut rx2) = ws2.split();
+    tx2.send(Message::Text(r##"["REQ","chat",{"#h":["g"],"kinds":[9]}]"##.into()))
+        .await
+        .unwrap();
+    let challenge = read_until(&mut rx2, "AUTH").await.unwrap()[1].as_str().unwrap().to_string();
+    tx2.send(auth_frame(&owner, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut rx2, "OK").await.unwrap()[2], true);
+    let evt = read_until(&mut rx2, "EVENT").await.expect("held REQ not replayed after AUTH");
+    assert_eq!(evt[1], "chat");
+    assert_eq!(evt[2]["id"], chat.id.as_str());
+    assert_eq!(read_until(&mut rx2, "EOSE").await.unwrap()[1], "chat");
+
+    // The replayed REQ is a live subscription too.
+    let live = sign_event(&owner, 9, vec![vec!["h".into(), "g".into()]], "again", 1_700_000_002);
+    tx1.send(event_frame(&live)).await.unwrap();
+    let evt = read_until(&mut rx2, "EVENT").await.expect("replayed REQ not registered");
+    assert_eq!(evt[2]["id"], live.id.as_str());
+
+    relay.stop().await;
+}
+
+/// NIP-70: a `["-"]`-tagged event is accepted only from a connection
+/// authenticated as its author, and NIP-11 advertises support.
+#[tokio::test]
+async fn embedded_relay_protected_events_need_author_auth() {
+    let owner = TestIdentity::from_seed(7);
+    let bob = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let info = fetch_nip11(relay.addr).await;
+    assert!(info["supported_nips"].as_array().unwrap().contains(&serde_json::json!(70)), "{info}");
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    let challenge = read_until(&mut rx, "AUTH").await.unwrap()[1].as_str().unwrap().to_string();
+    let create = sign_event(&owner, 9007, vec![vec!["h".into(), "g".into()]], "G", 1_700_000_000);
+    tx.send(event_frame(&create)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    let chat = sign_event(
+        &owner,
+        9,
+        vec![vec!["h".into(), "g".into()], vec!["-".into()]],
+        "hi",
+        1_700_000_001,
+    );
+    tx.send(event_frame(&chat)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false, "{ok}");
+    assert!(ok[3].as_str().unwrap().starts_with("auth-required:"), "{ok}");
+
+    // Authenticated as someone else: still refused.
+    tx.send(auth_frame(&bob, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    tx.send(event_frame(&chat)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false, "{ok}");
+    assert!(ok[3].as_str().unwrap().starts_with("restricted:"), "{ok}");
+
+    // As the author: accepted.
+    tx.send(auth_frame(&owner, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    tx.send(event_frame(&chat)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    relay.stop().await;
+}
+
+/// NIP-62: a vanish request addressed to this relay purges the author's
+/// events and group membership, refuses re-broadcasts of the purged events,
+/// and is itself kept. One aimed at another relay is refused.
+#[tokio::test]
+async fn embedded_relay_vanish_purges_and_tombstones() {
+    let owner = TestIdentity::from_seed(7);
+    let bob = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let create = sign_event(&owner, 9007, vec![vec!["h".into(), "g".into()]], "G", 1_700_000_000);
+    tx.send(event_frame(&create)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let put = sign_event(
+        &owner,
+        9000,
+        vec![vec!["h".into(), "g".into()], vec!["p".into(), bob.pubkey.clone()]],
+        "",
+        1_700_000_001,
+    );
+    tx.send(event_frame(&put)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let chat = sign_event(&bob, 9, vec![vec!["h".into(), "g".into()]], "bye", 1_700_000_002);
+    tx.send(event_frame(&chat)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    let elsewhere = sign_event(&bob, 62, vec![vec!["relay".into(), "wss://other.example".into()]], "", now());
+    tx.send(event_frame(&elsewhere)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false, "{ok}");
+
+    let vanish = sign_event(&bob, 62, vec![vec!["relay".into(), relay.ws_url()]], "", now());
+    tx.send(event_frame(&vanish)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    // Re-broadcast of the purged message is refused (bob is no longer a
+    // member either, but the tombstone answers first).
+    tx.send(event_frame(&chat)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false, "{ok}");
+
+    // The owner no longer sees bob's message; the vanish request remains.
+    let (ws2, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx2, mut rx2) = ws2.split();
+    let challenge = read_until(&mut rx2, "AUTH").await.unwrap()[1].as_str().unwrap().to_string();
+    tx2.send(auth_frame(&owner, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut rx2, "OK").await.unwrap()[2], true);
+    let q = format!(
+        r##"["REQ","all",{{"#h":["g"],"kinds":[9]}},{{"kinds":[62],"authors":["{}"]}}]"##,
+        bob.pubkey
+    );
+    tx2.send(Message::Text(q.into())).await.unwrap();
+    let kept = read_until(&mut rx2, "EVENT").await.expect("vanish request not kept");
+    assert_eq!(kept[2]["id"], vanish.id.as_str());
+    let next = timeout(T, rx2.next()).await.unwrap().unwrap().unwrap();
+    let next: serde_json::Value = serde_json::from_str(next.to_text().unwrap()).unwrap();
+    assert_eq!(next[0], "EOSE", "purged message still served: {next}");
+
+    relay.stop().await;
+}
+
+/// A moderator-deleted (9005) message can't be re-published by a client
+/// re-syncing it from another relay: the deletion leaves a tombstone.
+#[tokio::test]
+async fn embedded_relay_mod_deleted_event_stays_deleted() {
+    let owner = TestIdentity::from_seed(7);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let create = sign_event(&owner, 9007, vec![vec!["h".into(), "g".into()]], "G", 1_700_000_000);
+    tx.send(event_frame(&create)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let chat = sign_event(&owner, 9, vec![vec!["h".into(), "g".into()]], "spam", 1_700_000_001);
+    tx.send(event_frame(&chat)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    let del = sign_event(
+        &owner,
+        9005,
+        vec![vec!["h".into(), "g".into()], vec!["e".into(), chat.id.clone()]],
+        "",
+        1_700_000_002,
+    );
+    tx.send(event_frame(&del)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    tx.send(event_frame(&chat)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false, "{ok}");
+    assert!(ok[3].as_str().unwrap().starts_with("blocked:"), "{ok}");
+
+    relay.stop().await;
+}
+
+/// POST a NIP-86 call over bare HTTP/1.1 with a NIP-98 header signed by `who`
+/// (or none). Returns the status code and the parsed body (Null if not JSON).
+async fn nip86_call(
+    addr: std::net::SocketAddr,
+    who: Option<&TestIdentity>,
+    method: &str,
+    params: serde_json::Value,
+) -> (u16, serde_json::Value) {
+    use base64::Engine;
+    use sha2::{Digest, Sha256};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let body = serde_json::json!({ "method": method, "params": params }).to_string();
+    let auth = who
+        .map(|who| {
+            let tags = vec![
+                vec!["u".into(), format!("http://{addr}/")],
+                vec!["method".into(), "POST".into()],
+                vec!["payload".into(), hex::encode(Sha256::digest(body.as_bytes()))],
+            ];
+            let event = sign_event(who, 27235, tags, "", now());
+            let encoded = base64::engine::general_purpose::STANDARD
+                .encode(serde_json::to_vec(&event).unwrap());
+            format!("Authorization: Nostr {encoded}\r\n")
+        })
+        .unwrap_or_default();
+    let request = format!(
+        "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/nostr+json+rpc\r\n{auth}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut raw = String::new();
+    timeout(T, stream.read_to_string(&mut raw)).await.unwrap().unwrap();
+    let status = raw.split(' ').nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+    let body = raw.split_once("\r\n\r\n").map(|(_, b)| b).unwrap_or("");
+    (status, serde_json::from_str(body).unwrap_or_default())
+}
+
+/// NIP-86: the owner can ban events and pubkeys and rename the relay over
+/// NIP-98-authenticated HTTP; other pubkeys and unauthenticated calls can't.
+#[tokio::test]
+async fn embedded_relay_nip86_management() {
+    let owner = TestIdentity::from_seed(7);
+    let member = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let g = || vec!["h".to_string(), "g".to_string()];
+    let create = sign_event(&owner, 9007, vec![g()], "G", 1_700_000_000);
+    tx.send(event_frame(&create)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let add = sign_event(&owner, 9000, vec![g(), vec!["p".into(), member.pubkey.clone()]], "", 1_700_000_001);
+    tx.send(event_frame(&add)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let chat = sign_event(&member, 9, vec![g()], "spam", 1_700_000_002);
+    tx.send(event_frame(&chat)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    // No NIP-98 header → 401; a non-operator, non-admin → RPC error.
+    let (status, _) = nip86_call(relay.addr, None, "supportedmethods", serde_json::json!([])).await;
+    assert_eq!(status, 401);
+    let (status, resp) =
+        nip86_call(relay.addr, Some(&member), "banpubkey", serde_json::json!([owner.pubkey])).await;
+    assert_eq!(status, 200);
+    assert_eq!(resp["error"], "unauthorized", "{resp}");
+
+    // Banning the event removes it and refuses it on re-publish.
+    let (_, resp) =
+        nip86_call(relay.addr, Some(&owner), "banevent", serde_json::json!([chat.id, "spam"])).await;
+    assert_eq!(resp["result"], true, "{resp}");
+    tx.send(event_frame(&chat)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false, "{ok}");
+    assert!(ok[3].as_str().unwrap().starts_with("blocked:"), "{ok}");
+
+    // Banning the pubkey refuses its new events.
+    let (_, resp) =
+        nip86_call(relay.addr, Some(&owner), "banpubkey", serde_json::json!([member.pubkey])).await;
+    assert_eq!(resp["result"], true, "{resp}");
+    let (_, resp) = nip86_call(relay.addr, Some(&owner), "listbannedpubkeys", serde_json::json!([])).await;
+    assert_eq!(resp["result"][0]["pubkey"], member.pubkey.as_str(), "{resp}");
+    let again = sign_event(&member, 9, vec![g()], "more spam", 1_700_000_003);
+    tx.send(event_frame(&again)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false, "{ok}");
+    assert!(ok[3].as_str().unwrap().starts_with("blocked:"), "{ok}");
+
+    // Un-banning (or allowlisting) lifts the ban without closing the relay to
+    // everyone else: the allowlist only binds in allowlist mode.
+    for method in ["unbanpubkey", "allowpubkey"] {
+        let (_, resp) = nip86_call(relay.addr, Some(&owner), method, serde_json::json!([member.pubkey])).await;
+        assert_eq!(resp["result"], true, "{resp}");
+    }
+    let back = sign_event(&member, 9, vec![g()], "sorry", 1_700_000_004);
+    tx.send(event_frame(&back)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let other = sign_event(&owner, 9, vec![g()], "welcome back", 1_700_000_005);
+    tx.send(event_frame(&other)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], true, "a pubkey off the allowlist still publishes: {ok}");
+
+    // Renaming the relay shows up in NIP-11 immediately.
+    let (_, resp) =
+        nip86_call(relay.addr, Some(&owner), "changerelayname", serde_json::json!(["Renamed"])).await;
+    assert_eq!(resp["result"], true, "{resp}");
+    let info = fetch_nip11(relay.addr).await;
+    assert_eq!(info["name"], "Renamed");
+    assert!(info["supported_nips"].as_array().unwrap().contains(&86.into()));
+
+    relay.stop().await;
+}
+
+/// Authenticate a fresh connection as `who` and count the kind:1984 reports
+/// it can read.
+async fn reports_visible_to(relay: &server::EmbeddedRelay, who: &TestIdentity) -> usize {
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    let challenge = read_until(&mut rx, "AUTH").await.unwrap()[1].as_str().unwrap().to_string();
+    tx.send(auth_frame(who, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    tx.send(Message::Text(r#"["REQ","r",{"kinds":[1984]}]"#.into())).await.unwrap();
+    let mut seen = 0;
+    loop {
+        let frame = read_until_any(&mut rx).await.expect("no EOSE");
+        match frame[0].as_str() {
+            Some("EVENT") => seen += 1,
+            Some("EOSE") => return seen,
+            _ => {}
+        }
+    }
+}
+
+/// Next EVENT or EOSE frame, or None on timeout.
+async fn read_until_any<S>(rx: &mut S) -> Option<serde_json::Value>
+where
+    S: Stream<Item = Result<Message, WsError>> + Unpin,
+{
+    loop {
+        let msg = match timeout(T, rx.next()).await {
+            Ok(Some(Ok(m))) => m,
+            _ => return None,
+        };
+        if let Message::Text(t) = msg {
+            if let Ok(v) = serde_json::from_str::<serde_json::Value>(&t) {
+                if matches!(v[0].as_str(), Some("EVENT") | Some("EOSE")) {
+                    return Some(v);
+                }
+            }
+        }
+    }
+}
+
+/// NIP-56: a report against a group message is readable only by the reporter
+/// and the group's admins, shows up in the NIP-86 moderation queue, and leaves
+/// the queue once a moderator deletes the message (9005).
+#[tokio::test]
+async fn embedded_relay_group_reports_reach_only_admins() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let bob = TestIdentity::from_seed(9);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let g = || vec!["h".to_string(), "g".to_string()];
+    let setup = [
+        sign_event(&owner, 9007, vec![g()], "G", 1_700_000_000),
+        sign_event(&owner, 9000, vec![g(), vec!["p".into(), alice.pubkey.clone()]], "", 1_700_000_001),
+        sign_event(&owner, 9000, vec![g(), vec!["p".into(), bob.pubkey.clone()]], "", 1_700_000_002),
+    ];
+    for e in &setup {
+        tx.send(event_frame(e)).await.unwrap();
+        assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    }
+    let chat = sign_event(&alice, 9, vec![g()], "rude", 1_700_000_003);
+    tx.send(event_frame(&chat)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    // bob reports alice's message — no h tag needed, the target decides.
+    let report = sign_event(
+        &bob,
+        1984,
+        vec![
+            vec!["e".into(), chat.id.clone(), "spam".into()],
+            vec!["p".into(), alice.pubkey.clone()],
+        ],
+        "",
+        1_700_000_004,
+    );
+    tx.send(event_frame(&report)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], true, "{ok}");
+
+    assert_eq!(reports_visible_to(&relay, &bob).await, 1);
+    assert_eq!(reports_visible_to(&relay, &owner).await, 1);
+    assert_eq!(reports_visible_to(&relay, &alice).await, 0, "the reported user must not see the report");
+
+    let (_, queue) =
+        nip86_call(relay.addr, Some(&owner), "listeventsneedingmoderation", serde_json::json!([])).await;
+    assert_eq!(queue["result"], serde_json::json!([{ "id": chat.id, "reason": "spam" }]), "{queue}");
+    let (_, queue) =
+        nip86_call(relay.addr, Some(&bob), "listeventsneedingmoderation", serde_json::json!([])).await;
+    assert_eq!(queue["result"], serde_json::json!([]), "non-admins see no queue: {queue}");
+
+    let del = sign_event(&owner, 9005, vec![g(), vec!["e".into(), chat.id.clone()]], "", 1_700_000_005);
+    tx.send(event_frame(&del)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let (_, queue) =
+        nip86_call(relay.addr, Some(&owner), "listeventsneedingmoderation", serde_json::json!([])).await;
+    assert_eq!(queue["result"], serde_json::json!([]), "{queue}");
+    // The report stays hidden after its target is gone.
+    assert_eq!(reports_visible_to(&relay, &alice).await, 0);
+
+    relay.stop().await;
+}
+
+/// Authenticate a fresh connection as `who`, run one REQ filter and collect
+/// the events it returns.
+async fn req_as(relay: &server::EmbeddedRelay, who: &TestIdentity, filter: serde_json::Value) -> Vec<serde_json::Value> {
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    let challenge = read_until(&mut rx, "AUTH").await.unwrap()[1].as_str().unwrap().to_string();
+    tx.send(auth_frame(who, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    tx.send(Message::Text(format!(r#"["REQ","q",{filter}]"#).into())).await.unwrap();
+    let mut events = Vec::new();
+    while let Some(frame) = read_until_any(&mut rx).await {
+        if frame[0] == "EOSE" {
+            break;
+        }
+        events.push(frame[2].clone());
+    }
+    events
+}
+
+/// Every NIP-29 management op lands in the group's audit log (relay-signed
+/// kind:9080), readable by admins only and kept after the targets are gone.
+#[tokio::test]
+async fn embedded_relay_moderation_audit_log() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let g = || vec!["h".to_string(), "g".to_string()];
+    let chat = sign_event(&alice, 9, vec![g()], "spam", 1_700_000_002);
+    let steps = [
+        (sign_event(&owner, 9007, vec![g()], "G", 1_700_000_000), &owner),
+        (sign_event(&owner, 9000, vec![g(), vec!["p".into(), alice.pubkey.clone()]], "", 1_700_000_001), &owner),
+        (chat.clone(), &alice),
+        (
+            sign_event(
+                &owner,
+                9005,
+                vec![g(), vec!["e".into(), chat.id.clone()], vec!["reason".into(), "spam".into()]],
+                "",
+                1_700_000_003,
+            ),
+            &owner,
+        ),
+    ];
+    for (e, _) in &steps {
+        tx.send(event_frame(e)).await.unwrap();
+        assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    }
+
+    let log = req_as(&relay, &owner, serde_json::json!({ "kinds": [9080], "#h": ["g"] })).await;
+    let mut actions: Vec<&str> = log
+        .iter()
+        .map(|e| e["tags"].as_array().unwrap().iter().find(|t| t[0] == "k").unwrap()[1].as_str().unwrap())
+        .collect();
+    actions.sort();
+    assert_eq!(actions, ["9000", "9005", "9007"], "{log:?}");
+    let deletion = log.iter().find(|e| e["content"] == "spam").expect("9005 entry with its reason");
+    assert_eq!(deletion["pubkey"], relay.pubkey.as_str());
+    assert!(deletion["tags"].as_array().unwrap().iter().any(|t| t[0] == "e" && t[1] == chat.id.as_str()));
+
+    assert!(req_as(&relay, &alice, serde_json::json!({ "kinds": [9080], "#h": ["g"] })).await.is_empty());
+
+    // Log entries can't be forged over EVENT.
+    let forged = sign_event(&owner, 9080, vec![g()], "", 1_700_000_004);
+    tx.send(event_frame(&forged)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false, "{ok}");
+
+    relay.stop().await;
+}
+
+/// The AUTH budget (60/min by default) is shared across sockets from one IP:
+/// opening a second connection doesn't reset it, and the overflow gets a
+/// `rate-limited:` OK. Other actions keep their own budgets.
+#[tokio::test]
+async fn embedded_relay_rate_limits_across_connections() {
+    let alice = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, EmbeddedConfig { owner_pubkey: None, ..owned_by(&alice) })
+        .await
+        .unwrap();
+
+    let mut reasons = Vec::new();
+    let mut last = None;
+    for _ in 0..2 {
+        let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+        let (mut tx, mut rx) = ws.split();
+        let challenge = read_until(&mut rx, "AUTH").await.unwrap()[1].as_str().unwrap().to_string();
+        for _ in 0..40 {
+            // Wrong challenge: refused, but still spends the AUTH budget.
+            tx.send(auth_frame(&alice, &relay.ws_url(), "stale")).await.unwrap();
+            reasons.push(read_until(&mut rx, "OK").await.unwrap()[3].as_str().unwrap().to_string());
+        }
+        last = Some((tx, rx, challenge));
+    }
+    let limited = reasons.iter().filter(|r| r.starts_with("rate-limited:")).count();
+    assert_eq!(limited, 20, "{reasons:?}");
+    assert!(reasons[..60].iter().all(|r| !r.starts_with("rate-limited:")));
+
+    // REQ has its own budget.
+    let (mut tx, mut rx, _) = last.unwrap();
+    tx.send(Message::Text(r#"["REQ","s",{"kinds":[1]}]"#.into())).await.unwrap();
+    assert!(read_until(&mut rx, "EOSE").await.is_some());
+
+    relay.stop().await;
+}
+
+/// Sign a `kind` event for group `g`, mining a NIP-13 nonce to `target` bits.
+fn mined(who: &TestIdentity, kind: i32, target: u32, created_at: i64) -> thewired_relay::nostr::event::Event {
+    (0u64..)
+        .map(|nonce| {
+            let tags = vec![
+                vec!["h".to_string(), "g".to_string()],
+                vec!["nonce".to_string(), nonce.to_string(), target.to_string()],
+            ];
+            sign_event(who, kind, tags, "worked for it", created_at)
+        })
+        .find(|e| thewired_relay::nostr::nip13::leading_zero_bits(&e.id) >= target)
+        .unwrap()
+}
+
+/// Sign a kind:9 for group `g`, mining a NIP-13 nonce to `target` bits.
+fn mined_chat(who: &TestIdentity, target: u32, created_at: i64) -> thewired_relay::nostr::event::Event {
+    mined(who, 9, target, created_at)
+}
+
+/// The host's relay-wide and per-kind NIP-13 minimums apply to everyone,
+/// admins included, and the relay-wide one is advertised in NIP-11.
+#[tokio::test]
+async fn embedded_relay_min_pow_from_the_host() {
+    let owner = TestIdentity::from_seed(7);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let embedded = EmbeddedConfig {
+        pow_policy: PowPolicy::from_env_values(Some("2"), Some("9:8")),
+        ..owned_by(&owner)
+    };
+    let relay = server::run_embedded(db, embedded).await.unwrap();
+    assert_eq!(fetch_nip11(relay.addr).await["limitation"]["min_pow_difficulty"], 2);
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let t = now();
+    let steps = [
+        (sign_event(&owner, 9007, vec![vec!["h".into(), "g".into()]], "G", t), false),
+        (mined(&owner, 9007, 2, t), true),
+        (mined_chat(&owner, 2, t + 1), false),
+        (mined_chat(&owner, 8, t + 2), true),
+    ];
+    for (e, accepted) in steps {
+        tx.send(event_frame(&e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], accepted, "{ok}");
+        if !accepted {
+            assert!(ok[3].as_str().unwrap().starts_with("pow:"), "{ok}");
+        }
+    }
+
+    relay.stop().await;
+}
+
+/// A 9002 `min_pow` makes the group demand NIP-13 work from everyone but its
+/// admins, and shows up in the 39000 metadata.
+#[tokio::test]
+async fn embedded_relay_group_min_pow() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let info = fetch_nip11(relay.addr).await;
+    assert!(info["supported_nips"].as_array().unwrap().contains(&serde_json::json!(13)));
+    assert_eq!(info["limitation"]["min_pow_difficulty"], 0);
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let g = || vec!["h".to_string(), "g".to_string()];
+    let t = now();
+    for e in [
+        sign_event(&owner, 9007, vec![g()], "G", t),
+        sign_event(&owner, 9000, vec![g(), vec!["p".into(), alice.pubkey.clone()]], "", t + 1),
+        sign_event(&owner, 9002, vec![g(), vec!["min_pow".into(), "8".into()]], "", t + 2),
+    ] {
+        tx.send(event_frame(&e)).await.unwrap();
+        assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    }
+
+    let lazy = sign_event(&alice, 9, vec![g()], "no work", t + 3);
+    tx.send(event_frame(&lazy)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false);
+    assert!(ok[3].as_str().unwrap().starts_with("pow:"), "{ok}");
+
+    tx.send(event_frame(&mined_chat(&alice, 8, t + 4))).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    // Admins are exempt.
+    tx.send(event_frame(&sign_event(&owner, 9, vec![g()], "admin", t + 5))).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+
+    tx.send(Message::Text(r##"["REQ","m",{"kinds":[39000],"#d":["g"]}]"##.into())).await.unwrap();
+    let meta = read_until(&mut rx, "EVENT").await.unwrap();
+    assert!(meta[2]["tags"].as_array().unwrap().iter().any(|t| t[0] == "min_pow" && t[1] == "8"), "{meta}");
+
+    relay.stop().await;
+}
+
+/// Slow mode spaces out a member's kind:9 messages; admins are exempt, a resend
+/// of a stored message is a duplicate rather than rate-limited, and the
+/// interval is advertised in 39000.
+#[tokio::test]
+async fn embedded_relay_group_slow_mode() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let g = || vec!["h".to_string(), "g".to_string()];
+    let t = now();
+    let bad = sign_event(&owner, 9002, vec![g(), vec!["slow_mode".into(), "forever".into()]], "", t);
+    let first = sign_event(&alice, 9, vec![g()], "first", t + 3);
+    let steps = [
+        (sign_event(&owner, 9007, vec![g()], "G", t), true),
+        (sign_event(&owner, 9000, vec![g(), vec!["p".into(), alice.pubkey.clone()]], "", t + 1), true),
+        (bad, false),
+        (sign_event(&owner, 9002, vec![g(), vec!["slow_mode".into(), "60".into()]], "", t + 2), true),
+        (first.clone(), true),
+        (sign_event(&owner, 9, vec![g()], "admin 1", t + 4), true),
+        (sign_event(&owner, 9, vec![g()], "admin 2", t + 5), true),
+    ];
+    for (e, accepted) in steps {
+        tx.send(event_frame(&e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], accepted, "{ok}");
+    }
+
+    tx.send(event_frame(&first)).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], true);
+    assert!(ok[3].as_str().unwrap().starts_with("duplicate:"), "{ok}");
+
+    tx.send(event_frame(&sign_event(&alice, 9, vec![g()], "second", t + 6))).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false);
+    assert!(ok[3].as_str().unwrap().starts_with("rate-limited: slow mode"), "{ok}");
+
+    tx.send(Message::Text(r##"["REQ","m",{"kinds":[39000],"#d":["g"]}]"##.into())).await.unwrap();
+    let meta = read_until(&mut rx, "EVENT").await.unwrap();
+    assert!(meta[2]["tags"].as_array().unwrap().iter().any(|t| t[0] == "slow_mode" && t[1] == "60"), "{meta}");
+
+    relay.stop().await;
+}
+
+/// A timed mute (9010) stops a member posting until it runs out or an admin
+/// lifts it (9011); the relay lists active mutes in a signed kind:39005.
+#[tokio::test]
+async fn embedded_relay_group_mutes() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let g = || vec!["h".to_string(), "g".to_string()];
+    let mute = |who: &str, secs: &str| vec![g(), vec!["p".into(), who.to_string()], vec!["duration".into(), secs.into()]];
+    let t = now();
+    let steps = [
+        (sign_event(&owner, 9007, vec![g()], "G", t), true),
+        (sign_event(&owner, 9000, vec![g(), vec!["p".into(), alice.pubkey.clone()]], "", t + 1), true),
+        (sign_event(&alice, 9, vec![g()], "before", t + 2), true),
+        (sign_event(&owner, 9010, mute(&alice.pubkey, "0"), "", t + 3), false),
+        (sign_event(&owner, 9010, mute(&owner.pubkey, "600"), "", t + 4), false),
+        (sign_event(&alice, 9010, mute(&owner.pubkey, "600"), "", t + 5), false),
+        (sign_event(&owner, 9010, mute(&alice.pubkey, "600"), "", t + 6), true),
+    ];
+    for (e, accepted) in steps {
+        tx.send(event_frame(&e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], accepted, "{ok}");
+    }
+
+    tx.send(event_frame(&sign_event(&alice, 9, vec![g()], "muted", t + 7))).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], false);
+    assert!(ok[3].as_str().unwrap().starts_with("restricted:"), "{ok}");
+
+    // Muted, not kicked: alice still reads the group.
+    let seen = req_as(&relay, &alice, serde_json::json!({"kinds":[9],"#h":["g"]})).await;
+    assert_eq!(seen.len(), 1);
+
+    let mutes = req_as(&relay, &alice, serde_json::json!({"kinds":[39005],"#d":["g"]})).await;
+    assert_eq!(mutes.len(), 1);
+    let listed = mutes[0]["tags"].as_array().unwrap();
+    assert!(listed.iter().any(|t| t[0] == "p" && t[1] == alice.pubkey.as_str()), "{listed:?}");
+
+    tx.send(event_frame(&sign_event(&owner, 9011, vec![g(), vec!["p".into(), alice.pubkey.clone()]], "", t + 8)))
+        .await
+        .unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    tx.send(event_frame(&sign_event(&alice, 9, vec![g()], "unmuted", t + 9))).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], true, "{ok}");
+
+    let mutes = req_as(&relay, &alice, serde_json::json!({"kinds":[39005],"#d":["g"]})).await;
+    assert!(!mutes[0]["tags"].as_array().unwrap().iter().any(|t| t[0] == "p"));
+
+    relay.stop().await;
+}
+
+/// NIP-29 `previous` tags must reference the group's recent events; the
+/// embedded relay checks leniently (references optional, but never unknown).
+/// A non-member is refused by the membership gate without learning whether
+/// their references matched.
+#[tokio::test]
+async fn embedded_relay_checks_previous_tags() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let g = || vec!["h".to_string(), "g".to_string()];
+    let previous = |id: &str| vec!["previous".to_string(), id.to_string()];
+    let t = now();
+    let first = sign_event(&owner, 9, vec![g()], "first", t + 1);
+    let steps = [
+        (sign_event(&owner, 9007, vec![g()], "G", t), true),
+        (first.clone(), true),
+        (sign_event(&owner, 9, vec![g(), previous(&first.id[..8])], "reply", t + 2), true),
+        (sign_event(&owner, 9, vec![g(), previous("deadbeef")], "replayed", t + 3), false),
+        (sign_event(&owner, 9, vec![g(), previous("abc")], "short", t + 4), false),
+    ];
+    for (e, accepted) in steps {
+        tx.send(event_frame(&e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], accepted, "{ok}");
+        if !accepted {
+            assert!(ok[3].as_str().unwrap().starts_with("invalid:"), "{ok}");
+        }
+    }
+
+    for guess in [&first.id[..8], "deadbeef"] {
+        let probe = sign_event(&alice, 9, vec![g(), previous(guess)], "probe", t + 5);
+        tx.send(event_frame(&probe)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], false);
+        assert!(ok[3].as_str().unwrap().starts_with("auth-required: not a member"), "{ok}");
+    }
+
+    relay.stop().await;
+}
+
+/// Per-group policies: an announcement channel only takes admins' posts, an
+/// allowed-kinds list refuses other content kinds, and a restricted group's
+/// metadata is readable by members only.
+#[tokio::test]
+async fn embedded_relay_group_policies() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let bob = TestIdentity::from_seed(9);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let g = || vec!["h".to_string(), "g".to_string()];
+    let tag = |t: &[&str]| t.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let t = now();
+    let create = sign_event(&owner, 9007, vec![g(), tag(&["announcement"]), tag(&["allowed_kinds", "9", "11"])], "G", t);
+    let steps = [
+        (create, true),
+        (sign_event(&owner, 9000, vec![g(), vec!["p".into(), alice.pubkey.clone()]], "", t + 1), true),
+        (sign_event(&owner, 9, vec![g()], "news", t + 2), true),
+        (sign_event(&alice, 9, vec![g()], "reply", t + 3), false),
+        (sign_event(&owner, 1, vec![g()], "note", t + 4), false),
+        (sign_event(&owner, 9002, vec![g(), tag(&["allowed_kinds", "chat"])], "", t + 5), false),
+        (sign_event(&owner, 9002, vec![g(), tag(&["announcement", "false"]), tag(&["restricted"])], "", t + 6), true),
+        (sign_event(&alice, 9, vec![g()], "reply", t + 7), true),
+    ];
+    for (e, accepted) in steps {
+        tx.send(event_frame(&e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], accepted, "{ok}");
+    }
+
+    let meta = req_as(&relay, &alice, serde_json::json!({"kinds":[39000],"#d":["g"]})).await;
+    assert_eq!(meta.len(), 1);
+    let tags = meta[0]["tags"].as_array().unwrap();
+    assert!(tags.iter().any(|t| t[0] == "restricted"), "{tags:?}");
+    assert!(!tags.iter().any(|t| t[0] == "announcement"), "{tags:?}");
+    assert!(tags.iter().any(|t| t[0] == "allowed_kinds" && t[1] == "9" && t[2] == "11"), "{tags:?}");
+
+    // Non-members see none of the restricted group's state.
+    assert!(req_as(&relay, &bob, serde_json::json!({"kinds":[39000, 39001, 39002],"#d":["g"]})).await.is_empty());
+
+    relay.stop().await;
+}
+
+/// Sub-groups: a 9007 with a `parent` tag creates a channel whose members and
+/// admins come from the parent (unless it opts out), and the parent's 39000
+/// lists it.
+#[tokio::test]
+async fn embedded_relay_sub_groups() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let h = |g: &str| vec!["h".to_string(), g.to_string()];
+    let parent = || vec!["parent".to_string(), "g".to_string()];
+    let t = now();
+    for e in [
+        sign_event(&owner, 9007, vec![h("g")], "G", t),
+        sign_event(&owner, 9000, vec![h("g"), vec!["p".into(), alice.pubkey.clone()]], "", t + 1),
+    ] {
+        tx.send(event_frame(&e)).await.unwrap();
+        assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    }
+
+    // Alice is online before the channel exists.
+    let (alice_ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut alice_tx, mut alice_rx) = alice_ws.split();
+    let challenge = read_until(&mut alice_rx, "AUTH").await.unwrap()[1].as_str().unwrap().to_string();
+    alice_tx.send(auth_frame(&alice, &relay.ws_url(), &challenge)).await.unwrap();
+    assert_eq!(read_until(&mut alice_rx, "OK").await.unwrap()[2], true);
+    alice_tx.send(Message::Text(r##"["REQ","chat",{"kinds":[9]}]"##.into())).await.unwrap();
+    assert!(read_until(&mut alice_rx, "EOSE").await.is_some());
+
+    let steps = [
+        (sign_event(&owner, 9007, vec![h("general"), parent()], "general", t + 2), true),
+        (sign_event(&owner, 9007, vec![h("staff"), parent(), vec!["inherit".into(), "false".into()]], "staff", t + 3), true),
+        (sign_event(&owner, 9007, vec![h("secret"), parent(), vec!["restricted".into()]], "secret", t + 3), true),
+        (sign_event(&owner, 9007, vec![h("orphan"), vec!["parent".into(), "nope".into()]], "x", t + 4), false),
+        (sign_event(&owner, 9, vec![h("general")], "welcome", t + 4), true),
+        // Inherited membership: alice posts in the channel without being added.
+        (sign_event(&alice, 9, vec![h("general")], "hi", t + 5), true),
+        (sign_event(&alice, 9, vec![h("staff")], "hi", t + 6), false),
+        // Inherited admin role: the parent's admin moderates the channel.
+        (sign_event(&owner, 9002, vec![h("general"), vec!["about".into(), "chat".into()]], "", t + 7), true),
+    ];
+    for (e, accepted) in steps {
+        tx.send(event_frame(&e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], accepted, "{ok}");
+    }
+
+    // Alice reads the channel's history through her parent membership, and
+    // her open connection picked the new channel up live.
+    assert_eq!(req_as(&relay, &alice, serde_json::json!({"kinds":[9],"#h":["general"]})).await.len(), 2);
+    let live = read_until(&mut alice_rx, "EVENT").await.expect("no live event in the new channel");
+    assert_eq!(live[2]["content"], "welcome");
+
+    let meta = req_as(&relay, &alice, serde_json::json!({"kinds":[39000],"#d":["g", "staff"]})).await;
+    let tags_of = |d: &str| {
+        meta.iter()
+            .find(|e| e["tags"][0][1] == d)
+            .map(|e| e["tags"].as_array().unwrap().clone())
+            .unwrap()
+    };
+    let root = tags_of("g");
+    assert!(root.iter().any(|t| t[0] == "child" && t[1] == "general"), "{root:?}");
+    assert!(root.iter().any(|t| t[0] == "child" && t[1] == "staff"), "{root:?}");
+    // A restricted child's id isn't advertised to the parent's readers.
+    assert!(!root.iter().any(|t| t[0] == "child" && t[1] == "secret"), "{root:?}");
+    let staff = tags_of("staff");
+    assert!(staff.iter().any(|t| t[0] == "parent" && t[1] == "g"), "{staff:?}");
+    assert!(staff.iter().any(|t| t[0] == "inherit" && t[1] == "false"), "{staff:?}");
+
+    // Lifting the restriction lists it.
+    let lift = sign_event(&owner, 9002, vec![h("secret"), vec!["restricted".into(), "false".into()]], "", t + 8);
+    tx.send(event_frame(&lift)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let meta = req_as(&relay, &alice, serde_json::json!({"kinds":[39000],"#d":["g"]})).await;
+    let root = meta[0]["tags"].as_array().unwrap();
+    assert!(root.iter().any(|t| t[0] == "child" && t[1] == "secret"), "{root:?}");
+
+    relay.stop().await;
+}
+
+/// A group migration: the admin asks the old relay to announce the move, the
+/// old relay signs a 39010 embedding the admin's request, and the new relay
+/// (only) accepts it and adopts the group under that admin.
+#[tokio::test]
+async fn embedded_relay_group_migration() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let start = |db| server::run_embedded(db, owned_by(&owner));
+    let old = start(Db::Sqlite(sqlite::connect_memory().await.unwrap())).await.unwrap();
+    let new = start(Db::Sqlite(sqlite::connect_memory().await.unwrap())).await.unwrap();
+
+    let (ws, _) = connect_async(old.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let tag = |k: &str, v: &str| vec![k.to_string(), v.to_string()];
+    let migrate = |from: &str| {
+        vec![tag("h", "g"), tag("from", from), tag("relay", &new.ws_url()), tag("relay_pubkey", &new.pubkey)]
+    };
+    let t = now();
+    let steps = [
+        (sign_event(&owner, 9007, vec![tag("h", "g")], "G", t), true),
+        (sign_event(&owner, 9000, vec![tag("h", "g"), tag("p", &alice.pubkey)], "", t + 1), true),
+        // Members who aren't admins can't move the group.
+        (sign_event(&alice, 9012, migrate(&old.pubkey), "", t + 2), false),
+        // The request must name the relay it is sent to.
+        (sign_event(&owner, 9012, migrate(&new.pubkey), "", t + 3), false),
+        (sign_event(&owner, 9012, migrate(&old.pubkey), "", t + 4), true),
+    ];
+    for (e, accepted) in steps {
+        tx.send(event_frame(&e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], accepted, "{ok}");
+    }
+
+    let announcements = req_as(&old, &alice, serde_json::json!({"kinds":[39010],"#d":["g"]})).await;
+    assert_eq!(announcements.len(), 1);
+    let announcement = &announcements[0];
+    assert_eq!(announcement["pubkey"], old.pubkey);
+    let tags = announcement["tags"].as_array().unwrap();
+    assert!(tags.iter().any(|t| t[0] == "relay" && t[1] == new.ws_url()), "{tags:?}");
+    assert!(tags.iter().any(|t| t[0] == "p" && t[1] == owner.pubkey), "{tags:?}");
+    let frame = Message::Text(format!(r#"["EVENT",{announcement}]"#).into());
+
+    // The old relay won't take it back: it points elsewhere.
+    tx.send(frame.clone()).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], false);
+
+    // Anyone may carry it to the new relay, which adopts the group.
+    let (ws, _) = connect_async(new.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    tx.send(frame).await.unwrap();
+    let ok = read_until(&mut rx, "OK").await.unwrap();
+    assert_eq!(ok[2], true, "{ok}");
+    let admins = req_as(&new, &owner, serde_json::json!({"kinds":[39001],"#d":["g"]})).await;
+    assert_eq!(admins.len(), 1);
+    assert_eq!(admins[0]["pubkey"], new.pubkey);
+    assert!(admins[0]["tags"].as_array().unwrap().iter().any(|t| t[0] == "p" && t[1] == owner.pubkey));
+    assert_eq!(req_as(&new, &alice, serde_json::json!({"kinds":[39010]})).await.len(), 1);
+
+    old.stop().await;
+    new.stop().await;
+}
+
+/// Rotating the relay key: restarted with the new key and the old one as
+/// previous, the relay publishes an old-key-signed handover, re-signs group
+/// state with the new key, and lists both keys in NIP-11.
+#[tokio::test]
+async fn embedded_relay_key_rotation() {
+    let owner = TestIdentity::from_seed(7);
+    let (old_key, new_key) = ("11".repeat(32), "22".repeat(32));
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let start = |db, key: &str, previous: Option<&str>| {
+        let embedded = EmbeddedConfig {
+            relay_secret_key: Some(key.to_string()),
+            relay_previous_secret_key: previous.map(str::to_string),
+            ..owned_by(&owner)
+        };
+        server::run_embedded(db, embedded)
+    };
+
+    let relay = start(db.clone(), &old_key, None).await.unwrap();
+    let old_pubkey = relay.pubkey.clone();
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    tx.send(event_frame(&sign_event(&owner, 9007, vec![vec!["h".into(), "g".into()]], "G", now())))
+        .await
+        .unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    assert_eq!(fetch_nip11(relay.addr).await["pubkeys"], serde_json::json!([old_pubkey]));
+    relay.stop().await;
+
+    let relay = start(db.clone(), &new_key, Some(&old_key)).await.unwrap();
+    let new_pubkey = relay.pubkey.clone();
+    assert_ne!(new_pubkey, old_pubkey);
+
+    let handovers = req_as(&relay, &owner, serde_json::json!({"kinds":[39011]})).await;
+    assert_eq!(handovers.len(), 1);
+    assert_eq!(handovers[0]["pubkey"], old_pubkey);
+    assert!(handovers[0]["tags"].as_array().unwrap().iter().any(|t| t[0] == "p" && t[1] == new_pubkey));
+
+    let state = req_as(&relay, &owner, serde_json::json!({"kinds":[39000, 39001, 39002],"#d":["g"]})).await;
+    assert_eq!(state.len(), 3);
+    assert!(state.iter().all(|e| e["pubkey"] == new_pubkey), "{state:?}");
+
+    let info = fetch_nip11(relay.addr).await;
+    assert_eq!(info["pubkey"], new_pubkey);
+    assert_eq!(info["pubkeys"], serde_json::json!([new_pubkey, old_pubkey]));
+
+    // Nobody else can publish a handover.
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+    let forged = sign_event(&owner, 39011, vec![vec!["p".into(), owner.pubkey.clone()]], "", now());
+    tx.send(event_frame(&forged)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], false);
+    relay.stop().await;
+
+    // A later restart with the same key keeps the history without a second
+    // handover.
+    let relay = start(db, &new_key, Some(&old_key)).await.unwrap();
+    assert_eq!(req_as(&relay, &owner, serde_json::json!({"kinds":[39011]})).await.len(), 1);
+    assert_eq!(fetch_nip11(relay.addr).await["pubkeys"], serde_json::json!([new_pubkey, old_pubkey]));
+    relay.stop().await;
+}
+
+/// Only pubkeys are recorded: rotating without the previous key re-signs the
+/// state but can't sign a handover, and no secret is ever in the database. An
+/// ephemeral key isn't recorded either, so restarting without one isn't a
+/// rotation.
+#[tokio::test]
+async fn embedded_relay_key_rotation_without_the_old_key() {
+    let owner = TestIdentity::from_seed(7);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let start = |db, key: Option<&str>| {
+        let embedded = EmbeddedConfig {
+            relay_secret_key: key.map(str::to_string),
+            ..owned_by(&owner)
+        };
+        server::run_embedded(db, embedded)
+    };
+
+    for _ in 0..2 {
+        let relay = start(db.clone(), None).await.unwrap();
+        assert!(req_as(&relay, &owner, serde_json::json!({"kinds":[39011]})).await.is_empty());
+        assert_eq!(fetch_nip11(relay.addr).await["pubkeys"], serde_json::json!([relay.pubkey]));
+        relay.stop().await;
+    }
+
+    let relay = start(db.clone(), Some(&"11".repeat(32))).await.unwrap();
+    let old_pubkey = relay.pubkey.clone();
+    relay.stop().await;
+
+    let relay = start(db.clone(), Some(&"22".repeat(32))).await.unwrap();
+    assert!(req_as(&relay, &owner, serde_json::json!({"kinds":[39011]})).await.is_empty());
+    assert_eq!(fetch_nip11(relay.addr).await["pubkeys"], serde_json::json!([relay.pubkey, old_pubkey]));
+    assert_eq!(db.get_setting("relay_pubkey").await.unwrap(), Some(relay.pubkey.clone()));
+    for secret in ["relay_secret_key", "relay_previous_secret_key"] {
+        assert_eq!(db.get_setting(secret).await.unwrap(), None);
+    }
+    relay.stop().await;
+}
+
+/// Reactions, replies and their deletions keep per-event counters, read back
+/// as a relay-signed kind:9081 summary counting only what the reader can see.
+#[tokio::test]
+async fn embedded_relay_event_aggregates() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+
+    let tag = |k: &str, v: &str| vec![k.to_string(), v.to_string()];
+    let t = now();
+    let msg = sign_event(&owner, 9, vec![tag("h", "g")], "hello", t + 2);
+    let like = sign_event(&owner, 7, vec![tag("h", "g"), tag("e", &msg.id)], "+", t + 3);
+    let events = [
+        sign_event(&owner, 9007, vec![tag("h", "g")], "G", t),
+        sign_event(&owner, 9000, vec![tag("h", "g"), tag("p", &alice.pubkey)], "", t + 1),
+        msg.clone(),
+        like.clone(),
+        sign_event(&alice, 7, vec![tag("h", "g"), tag("e", &msg.id)], "", t + 4),
+        sign_event(&alice, 7, vec![tag("h", "g"), tag("e", &msg.id)], "🔥", t + 5),
+        sign_event(&alice, 9, vec![tag("h", "g"), tag("q", &msg.id)], "hi back", t + 6),
+    ];
+    for e in &events {
+        tx.send(event_frame(e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], true, "{ok}");
+    }
+
+    let summary = |tags: &serde_json::Value| {
+        let mut tags: Vec<Vec<String>> = serde_json::from_value(tags.clone()).unwrap();
+        tags.retain(|t| t[0] != "e");
+        tags
+    };
+    let stats = req_as(&relay, &alice, serde_json::json!({"kinds":[9081],"#e":[msg.id]})).await;
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0]["pubkey"], relay.pubkey);
+    let expected: Vec<Vec<String>> = serde_json::from_value(serde_json::json!([
+        ["reaction", "+", "2"],
+        ["reaction", "🔥", "1"],
+        ["replies", "1"],
+        ["zaps", "0", "0"]
+    ]))
+    .unwrap();
+    assert_eq!(summary(&stats[0]["tags"]), expected);
+
+    // Deleting a reaction takes it off the count.
+    let deletion = sign_event(&owner, 5, vec![tag("e", &like.id)], "", t + 7);
+    tx.send(event_frame(&deletion)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let stats = req_as(&relay, &alice, serde_json::json!({"kinds":[9081],"#e":[msg.id]})).await;
+    assert!(summary(&stats[0]["tags"]).contains(&vec!["reaction".into(), "+".into(), "1".into()]));
+
+    // A reaction filed in another group only counts for readers of that
+    // group.
+    let elsewhere = sign_event(&owner, 7, vec![tag("h", "other"), tag("e", &msg.id)], "👀", t + 9);
+    for e in [sign_event(&owner, 9007, vec![tag("h", "other")], "O", t + 8), elsewhere] {
+        tx.send(event_frame(&e)).await.unwrap();
+        assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    }
+    let eyes = vec!["reaction".to_string(), "👀".into(), "1".into()];
+    let stats = req_as(&relay, &owner, serde_json::json!({"kinds":[9081],"#e":[msg.id]})).await;
+    assert!(summary(&stats[0]["tags"]).contains(&eyes), "{stats:?}");
+    let stats = req_as(&relay, &alice, serde_json::json!({"kinds":[9081],"#e":[msg.id]})).await;
+    assert!(!summary(&stats[0]["tags"]).contains(&eyes), "{stats:?}");
+
+    // Summaries can't be published.
+    let forged = sign_event(&owner, 9081, vec![tag("e", &msg.id)], "", t + 11);
+    tx.send(event_frame(&forged)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], false);
+    relay.stop().await;
+}
+
+/// Zap receipts are stored only when they carry a genuine zap request for the
+/// invoiced amount; accepted ones add to the zapped event's totals.
+#[tokio::test]
+async fn embedded_relay_zap_receipts() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let provider = TestIdentity::from_seed(9);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+
+    let tag = |k: &str, v: &str| vec![k.to_string(), v.to_string()];
+    let t = now();
+    let note = sign_event(&alice, 9, vec![tag("h", "g")], "zap me", t + 2);
+    let request = sign_event(
+        &provider,
+        9734,
+        vec![tag("p", &alice.pubkey), tag("e", &note.id), tag("amount", "21000")],
+        "",
+        t + 3,
+    );
+    let receipt = |bolt11: &str, request: &serde_json::Value, at: i64| {
+        let tags = vec![
+            tag("h", "g"),
+            tag("p", &alice.pubkey),
+            tag("e", &note.id),
+            tag("bolt11", bolt11),
+            tag("description", &request.to_string()),
+        ];
+        sign_event(&provider, 9735, tags, "", at)
+    };
+    let genuine = serde_json::to_value(&request).unwrap();
+    let mut forged = genuine.clone();
+    forged["tags"][2][1] = "21000000".into();
+    let steps = [
+        (sign_event(&owner, 9007, vec![tag("h", "g")], "G", t), true),
+        (sign_event(&owner, 9000, vec![tag("h", "g"), tag("p", &alice.pubkey)], "", t + 1), true),
+        (sign_event(&owner, 9000, vec![tag("h", "g"), tag("p", &provider.pubkey)], "", t + 1), true),
+        (note.clone(), true),
+        (receipt("lnbc210n1xyz", &genuine, t + 4), true),
+        // Invoice for more than was asked.
+        (receipt("lnbc1u1xyz", &genuine, t + 5), false),
+        // Request edited after signing.
+        (receipt("lnbc210u1xyz", &forged, t + 6), false),
+    ];
+    for (e, accepted) in steps {
+        tx.send(event_frame(&e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], accepted, "{ok}");
+        if !accepted {
+            assert!(ok[3].as_str().unwrap().starts_with("invalid:"), "{ok}");
+        }
+    }
+
+    let stats = req_as(&relay, &alice, serde_json::json!({"kinds":[9081],"#e":[note.id]})).await;
+    let tags = stats[0]["tags"].as_array().unwrap();
+    assert!(tags.iter().any(|t| t == &serde_json::json!(["zaps", "1", "21000"])), "{tags:?}");
+    relay.stop().await;
+}
+
+/// NIP-50 catalog extensions query the music index: tracks by artist/genre,
+/// an album's tracklist, all gated like any other read and narrowed by the
+/// filter's other fields.
+#[tokio::test]
+async fn embedded_relay_music_catalog() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let outsider = TestIdentity::from_seed(9);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+
+    let tag = |k: &str, v: &str| vec![k.to_string(), v.to_string()];
+    let t = now();
+    let imeta = vec![
+        "imeta".to_string(),
+        "url https://cdn.example/a.mp3".to_string(),
+        "m audio/mpeg".to_string(),
+        format!("x {}", "ab".repeat(32)),
+    ];
+    let track = |group: &str, d: &str, genre: &str, at: i64| {
+        let tags = vec![tag("h", group), tag("d", d), tag("title", d), tag("artist", "Band"), tag("genre", genre), imeta.clone()];
+        sign_event(&owner, 31683, tags, "", at)
+    };
+    let (one, two) = (track("g", "one", "dub", t + 1), track("g", "two", "jazz", t + 2));
+    let coordinate = |d: &str, kind: i32| format!("{kind}:{}:{d}", owner.pubkey);
+    let album = |group: &str, d: &str, at: i64| {
+        let tracks = [tag("a", &coordinate("two", 31683)), tag("a", &coordinate("one", 31683))];
+        let mut tags = vec![tag("h", group), tag("d", d), tag("title", "LP")];
+        tags.extend(tracks);
+        sign_event(&owner, 33123, tags, "", at)
+    };
+    let events = [
+        sign_event(&owner, 9007, vec![tag("h", "g")], "G", t),
+        sign_event(&owner, 9000, vec![tag("h", "g"), tag("p", &alice.pubkey)], "", t),
+        sign_event(&owner, 9007, vec![tag("h", "other")], "O", t),
+        one.clone(),
+        two.clone(),
+        album("g", "lp", t + 3),
+        // Newest, so ranked first, but only readable in `other`.
+        track("other", "three", "dub", t + 4),
+        album("other", "secret-lp", t + 5),
+    ];
+    for e in &events {
+        tx.send(event_frame(e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], true, "{ok}");
+    }
+
+    let ids = |events: Vec<serde_json::Value>| {
+        let mut ids: Vec<String> = events.iter().map(|e| e["id"].as_str().unwrap().to_string()).collect();
+        ids.sort();
+        ids
+    };
+    let mut both = vec![one.id.clone(), two.id.clone()];
+    both.sort();
+    let search = |s: String| serde_json::json!({"kinds":[31683], "search": s});
+    assert_eq!(ids(req_as(&relay, &alice, search("artist:band".into())).await), both);
+    assert_eq!(ids(req_as(&relay, &alice, search("genre:dub".into())).await), vec![one.id.clone()]);
+    let tracklist = req_as(&relay, &owner, search(format!("album:{}", coordinate("lp", 33123)))).await;
+    assert_eq!(ids(tracklist), both);
+    // Group music stays hidden from non-members.
+    assert!(req_as(&relay, &outsider, search("artist:band".into())).await.is_empty());
+    // Hidden candidates don't use up the limit.
+    let first = serde_json::json!({"kinds":[31683], "search": "artist:band", "limit": 1});
+    assert_eq!(ids(req_as(&relay, &alice, first).await), vec![two.id.clone()]);
+    // The filter's other fields still apply.
+    let in_g = serde_json::json!({"kinds":[31683], "search": "genre:dub", "#h": ["g"]});
+    assert_eq!(ids(req_as(&relay, &owner, in_g).await), vec![one.id.clone()]);
+    // An album's tracklist is only resolved for those who can read the album.
+    let secret = search(format!("album:{}", coordinate("secret-lp", 33123)));
+    assert_eq!(ids(req_as(&relay, &owner, secret.clone()).await), both);
+    assert!(req_as(&relay, &alice, secret).await.is_empty());
+    relay.stop().await;
+}
+
+/// Album entries that were never stored or were deleted are reported to the
+/// album's author; deleting a listed track answers with a NOTICE.
+#[tokio::test]
+async fn embedded_relay_music_integrity() {
+    let owner = TestIdentity::from_seed(7);
+    let alice = TestIdentity::from_seed(8);
+    let db = Db::Sqlite(sqlite::connect_memory().await.unwrap());
+    let relay = server::run_embedded(db, owned_by(&owner))
+        .await
+        .unwrap();
+    let (ws, _) = connect_async(relay.ws_url()).await.unwrap();
+    let (mut tx, mut rx) = ws.split();
+    assert!(read_until(&mut rx, "AUTH").await.is_some());
+
+    let tag = |k: &str, v: &str| vec![k.to_string(), v.to_string()];
+    let t = now();
+    let coordinate = |kind: i32, d: &str| format!("{kind}:{}:{d}", owner.pubkey);
+    let imeta = vec![
+        "imeta".to_string(),
+        "url https://cdn.example/a.mp3".to_string(),
+        "m audio/mpeg".to_string(),
+        format!("x {}", "ab".repeat(32)),
+    ];
+    let track = |d: &str| {
+        let tags = vec![tag("h", "g"), tag("d", d), tag("title", d), imeta.clone()];
+        sign_event(&owner, 31683, tags, "", t + 1)
+    };
+    let album = sign_event(
+        &owner,
+        33123,
+        vec![
+            tag("h", "g"),
+            tag("d", "lp"),
+            tag("title", "LP"),
+            tag("a", &coordinate(31683, "one")),
+            tag("a", &coordinate(31683, "two")),
+            tag("a", &coordinate(31683, "never")),
+        ],
+        "",
+        t + 2,
+    );
+    // alice's track, listed by a playlist in g and one in a group she isn't in.
+    let hers = format!("31683:{}:mine", alice.pubkey);
+    let playlist = |group: &str| {
+        let tags = vec![tag("h", group), tag("d", group), tag("title", group), tag("a", &hers)];
+        sign_event(&owner, 30119, tags, "", t + 2)
+    };
+    let events = [
+        sign_event(&owner, 9007, vec![tag("h", "g")], "G", t),
+        sign_event(&owner, 9000, vec![tag("h", "g"), tag("p", &alice.pubkey)], "", t),
+        sign_event(&owner, 9007, vec![tag("h", "other")], "Other", t),
+        track("one"),
+        track("two"),
+        album,
+        sign_event(&alice, 31683, vec![tag("h", "g"), tag("d", "mine"), tag("title", "Mine"), imeta.clone()], "", t + 1),
+        playlist("g"),
+        playlist("other"),
+    ];
+    for e in &events {
+        tx.send(event_frame(e)).await.unwrap();
+        let ok = read_until(&mut rx, "OK").await.unwrap();
+        assert_eq!(ok[2], true, "{ok}");
+    }
+
+    let deletion = sign_event(&owner, 5, vec![tag("a", &coordinate(31683, "one"))], "", t + 3);
+    tx.send(event_frame(&deletion)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let notice = read_until(&mut rx, "NOTICE").await.unwrap();
+    assert!(notice[1].as_str().unwrap().contains("1 album(s)/playlist(s)"), "{notice}");
+    // alice only hears about the playlist she can read.
+    let deletion = sign_event(&alice, 5, vec![tag("a", &hers)], "", t + 3);
+    tx.send(event_frame(&deletion)).await.unwrap();
+    assert_eq!(read_until(&mut rx, "OK").await.unwrap()[2], true);
+    let notice = read_until(&mut rx, "NOTICE").await.unwrap();
+    assert!(notice[1].as_str().unwrap().contains("1 album(s)/playlist(s)"), "{notice}");
+
+    let request = serde_json::json!({"kinds":[9082],"#a":[coordinate(33123, "lp")]});
+    let reports = req_as(&relay, &owner, request.clone()).await;
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["pubkey"], relay.pubkey);
+    let expected = serde_json::json!([
+        ["a", coordinate(33123, "lp")],
+        ["removed", coordinate(31683, "one"), "1"],
+        ["missing", coordinate(31683, "never"), "3"]
+    ]);
+    assert_eq!(reports[0]["tags"], expected);
+    // Only the author gets the report.
+    assert!(req_as(&relay, &alice, request).await.is_empty());
+    relay.stop().await;
+}
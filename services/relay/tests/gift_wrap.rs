@@ -0,0 +1,51 @@
+//! Postgres coverage for NIP-59 gift wraps (kinds 1059/1060): stored like any
+//! event, but only ever served to their authenticated `p` recipient — not to
+//! anonymous readers, strangers, or the wrap's random author key. The SQLite
+//! side is covered by `db::sqlite`'s unit tests.
+
+mod common;
+
+use common::{setup_test_pool, sign_event, TestIdentity};
+use thewired_relay::db::event_store::{query_events, store_event};
+use thewired_relay::nostr::filter::Filter;
+use thewired_relay::protocol::nip50::search_events;
+
+/// Skips the test (with a printed warning) if Postgres isn't reachable.
+macro_rules! pool_or_skip {
+    () => {
+        match setup_test_pool().await {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!(
+                    "SKIP: relay integration test — DB unreachable ({e}). \
+                     Run `pnpm dev:infra` and ensure `thewired_test` exists."
+                );
+                return;
+            }
+        }
+    };
+}
+
+#[tokio::test]
+async fn gift_wraps_only_reach_their_recipient() {
+    let pool = pool_or_skip!();
+    let throwaway = TestIdentity::from_seed(0x61);
+    let bob = TestIdentity::from_seed(0x62);
+    let carol = TestIdentity::from_seed(0x63);
+
+    let wrap = sign_event(&throwaway, 1059, vec![vec!["p".into(), bob.pubkey.clone()]], "sealed", 1_700_000_000);
+    assert!(store_event(&pool, &wrap).await.unwrap());
+
+    let filter: Filter = serde_json::from_value(serde_json::json!({"kinds": [1059]})).unwrap();
+    let read = |authed: Vec<String>| {
+        let (pool, filter) = (pool.clone(), filter.clone());
+        async move { query_events(&pool, &filter, &authed).await.unwrap().len() }
+    };
+    assert_eq!(read(vec![]).await, 0, "anonymous readers never see gift wraps");
+    assert_eq!(read(vec![carol.pubkey.clone()]).await, 0, "nor do strangers");
+    assert_eq!(read(vec![throwaway.pubkey.clone()]).await, 0, "nor does the random author key");
+    assert_eq!(read(vec![carol.pubkey.clone(), bob.pubkey.clone()]).await, 1, "the recipient does");
+
+    let hits = search_events(&pool, "sealed", 50, std::slice::from_ref(&throwaway.pubkey)).await.unwrap();
+    assert!(hits.is_empty(), "search is gated the same way");
+}
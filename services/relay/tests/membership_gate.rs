@@ -239,3 +239,29 @@ async fn re_added_member_can_publish_again() {
     let resp = send_event(&state, &broadcast_tx, &third).await;
     assert!(parse_ok(&resp).1, "post after re-add should be accepted");
 }
+
+/// A backend-side kick (REST → `app.space_members` DELETE) reaches the relay's
+/// membership bus via the table's NOTIFY trigger, so open connections can drop
+/// the member's read access immediately instead of on a TTL.
+#[tokio::test]
+async fn backend_kick_is_pushed_on_membership_bus() {
+    let pool = pool_or_skip!();
+    let bob = TestIdentity::from_seed(0x61);
+
+    insert_space(&pool, SPACE_ID).await.unwrap();
+    add_member(&pool, SPACE_ID, &bob.pubkey).await.unwrap();
+
+    let (tx, mut rx) = tokio::sync::broadcast::channel(16);
+    thewired_relay::membership_bus::spawn_pg_listener(pool.clone(), tx);
+    // Give the listener a moment to issue LISTEN before we write.
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    remove_member(&pool, SPACE_ID, &bob.pubkey).await.unwrap();
+
+    let change = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+        .await
+        .expect("no membership notification within 5 s")
+        .expect("bus closed");
+    assert_eq!(change.group_id, SPACE_ID);
+    assert_eq!(change.pubkey.as_deref(), Some(bob.pubkey.as_str()));
+}
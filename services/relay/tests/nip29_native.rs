@@ -13,7 +13,10 @@ mod common;
 
 use std::collections::HashSet;
 use std::sync::Arc;
-use common::{make_app_state, send_event, setup_test_pool, sign_event, sign_h_tagged, TestIdentity};
+use common::{
+    add_member, grant_space_role, insert_space, make_app_state, send_event, setup_test_pool, sign_event,
+    sign_h_tagged, TestIdentity,
+};
 use thewired_relay::db::membership_source;
 use thewired_relay::protocol::handler::handle_message;
 use thewired_relay::protocol::subscription::SubscriptionManager;
@@ -135,7 +138,7 @@ async fn private_group_sets_flag_and_signals_auth_required() {
     // An anonymous REQ for the private group's chat gets an auth-required CLOSED.
     let req = serde_json::json!(["REQ", "s1", { "kinds": [9], "#h": [group_id] }]).to_string();
     let subs = Arc::new(tokio::sync::Mutex::new(SubscriptionManager::new()));
-    let mut authed: Option<String> = None;
+    let mut authed: Vec<String> = Vec::new();
     let mut memberships: HashSet<String> = HashSet::new();
     let resp = handle_message(&req, &state, &subs, &mut authed, &mut memberships, "ch", &tx).await;
     assert!(resp[0].contains("CLOSED"), "expected CLOSED, got: {:?}", resp);
@@ -278,3 +281,39 @@ async fn open_group_join_adds_member_and_republishes_39002() {
     .unwrap();
     assert!(members_tags.contains(&joiner.pubkey), "republished 39002 should include the joiner");
 }
+
+/// NIP-56: a report filed in a backend space is readable by the reporter and
+/// the space's moderators (an admin role, or one granting `MANAGE_MESSAGES`),
+/// not by other members.
+#[tokio::test]
+async fn space_moderators_read_group_reports() {
+    let pool = pool_or_skip!();
+    let space = "space-reports";
+    let (state, tx) = make_app_state(pool.clone());
+    let author = TestIdentity::from_seed(41);
+    let reporter = TestIdentity::from_seed(42);
+    let moderator = TestIdentity::from_seed(43);
+    let bystander = TestIdentity::from_seed(44);
+    insert_space(&pool, space).await.unwrap();
+    for who in [&author, &reporter, &moderator, &bystander] {
+        add_member(&pool, space, &who.pubkey).await.unwrap();
+    }
+    grant_space_role(&pool, space, &moderator.pubkey, "mods", false, &["MANAGE_MESSAGES"]).await.unwrap();
+    grant_space_role(&pool, space, &bystander.pubkey, "regulars", false, &["SEND_MESSAGES"]).await.unwrap();
+
+    let chat = sign_h_tagged(&author, 9, space, "spam");
+    assert!(parse_ok(&send_event(&state, &tx, &chat).await).0);
+    let report = sign_event(&reporter, 1984, vec![vec!["e".into(), chat.id.clone(), "spam".into()]], "", 1_700_000_001);
+    assert!(parse_ok(&send_event(&state, &tx, &report).await).0);
+
+    let filter: thewired_relay::nostr::filter::Filter =
+        serde_json::from_value(serde_json::json!({ "kinds": [1984] })).unwrap();
+    for (who, visible) in [(&reporter, 1), (&moderator, 1), (&bystander, 0)] {
+        let seen = state.pool.query_events(&filter, std::slice::from_ref(&who.pubkey)).await.unwrap();
+        assert_eq!(seen.len(), visible);
+        let queue = state.pool.open_reports(Some(&who.pubkey)).await.unwrap();
+        assert_eq!(queue.len(), if who.pubkey == reporter.pubkey { 0 } else { visible });
+    }
+    assert!(state.pool.can_moderate(space, &moderator.pubkey).await.unwrap());
+    assert!(!state.pool.can_moderate(space, &bystander.pubkey).await.unwrap());
+}
@@ -7,7 +7,8 @@
 //!
 //! ```sh
 //! pnpm dev:infra              # postgres
-//! pnpm dev:relay               # in another shell
+//! RELAY_RATE_REQ=off pnpm dev:relay   # in another shell (the concurrent
+//!                                     # test exceeds the default REQ budget)
 //! cargo test --test stress_subs -- --ignored --nocapture
 //! ```
 //!